@@ -1,30 +1,71 @@
 use bon::{Builder, bon};
 use derive_getters::Getters;
-use miette::{IntoDiagnostic, Result};
-use multitool_sdk::models::RolloutState;
+use miette::{IntoDiagnostic, Result, miette};
+use multitool_sdk::models::{RolloutState, RolloutStateData, RolloutStateType};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::{
     sync::{mpsc, oneshot},
     time::Duration,
 };
 
+use crate::WholePercent;
+
 pub(crate) type WorkspaceId = u32;
 pub(crate) type ApplicationId = u32;
 pub(crate) type RolloutId = u64;
+pub(crate) type StateId = u64;
 
 /// RolloutMetadata captures the relevant parameters for a particular
 /// rollout. This struct is mostly used in conjuction with a `BackendClient`
 /// to hold the context for the current rollout.
-#[derive(Builder, Getters, Clone)]
+#[derive(Builder, Getters, Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub(crate) struct RolloutMetadata {
     workspace_id: WorkspaceId,
     application_id: ApplicationId,
     rollout_id: RolloutId,
 }
 
+/// A [TargetState] is a state the rollout should be moved into, like
+/// deploying the canary or setting its traffic. It's the parsed form
+/// of the backend's `RolloutState`, and it can also be created locally
+/// when there is no backend to consult.
+#[derive(Builder, Getters, Clone, Debug)]
+pub(crate) struct TargetState {
+    id: StateId,
+    state_type: RolloutStateType,
+    /// The amount of traffic the canary should receive. This is only
+    /// present for `SetCanaryTraffic` states.
+    percent_traffic: Option<WholePercent>,
+}
+
+impl TryFrom<RolloutState> for TargetState {
+    type Error = miette::Report;
+
+    fn try_from(state: RolloutState) -> Result<Self> {
+        let percent_traffic = match state.state_type {
+            RolloutStateType::SetCanaryTraffic => {
+                let data = state
+                    .data
+                    .flatten()
+                    .ok_or_else(|| miette!("No data found in state"))?;
+                let RolloutStateData::RolloutStateDataOneOf(state_data) = *data;
+                let percent = state_data.set_canary_traffic.percent_traffic;
+                Some(WholePercent::try_from(percent).into_diagnostic()?)
+            }
+            _ => None,
+        };
+        Ok(Self {
+            id: state.id,
+            state_type: state.state_type,
+            percent_traffic,
+        })
+    }
+}
+
 #[derive(Getters, Clone)]
 pub(crate) struct LockedState {
-    state: RolloutState,
+    state: TargetState,
     /// How often the lease must be renewed.
     frequency: Duration,
     /// When the state has been effected, release the lock we have
@@ -38,7 +79,7 @@ pub(crate) struct LockedState {
 impl LockedState {
     #[builder]
     pub(crate) fn new(
-        state: RolloutState,
+        state: TargetState,
         frequency: Duration,
         task_done: mpsc::Sender<oneshot::Sender<()>>,
     ) -> Self {
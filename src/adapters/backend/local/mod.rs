@@ -0,0 +1,111 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use miette::{Result, miette};
+use tokio::sync::{mpsc::Sender, oneshot};
+use tokio::time::{Duration, Instant};
+use tracing::trace;
+
+use super::{Backend, LockedState, RolloutMetadata, TargetState};
+use crate::adapters::Measurement;
+
+pub(crate) use policy::PolicyConfig;
+use policy::PolicyEngine;
+
+/// There's no one else competing for locks, so they rarely need refreshing.
+const LOCK_FREQUENCY: Duration = Duration::from_secs(60);
+
+/// A [LocalBackend] makes every rollout decision in-process, using
+/// its [PolicyEngine], instead of asking the MultiTool backend.
+/// This lets rollouts proceed safely when the backend is unreachable.
+pub(crate) struct LocalBackend {
+    engine: Mutex<PolicyEngine>,
+}
+
+impl LocalBackend {
+    pub(crate) fn new(config: PolicyConfig) -> Result<Self> {
+        let engine = Mutex::new(PolicyEngine::new(config)?);
+        Ok(Self { engine })
+    }
+
+    fn with_engine<T>(&self, f: impl FnOnce(&mut PolicyEngine) -> T) -> Result<T> {
+        let mut engine = self
+            .engine
+            .lock()
+            .map_err(|_| miette!("The policy engine's lock was poisoned"))?;
+        Ok(f(&mut engine))
+    }
+}
+
+#[async_trait]
+impl Backend for LocalBackend {
+    async fn lock_state(
+        &self,
+        _meta: &RolloutMetadata,
+        state: &TargetState,
+        done_sender: Sender<oneshot::Sender<()>>,
+    ) -> Result<LockedState> {
+        trace!("Locking state {}...", state.state_type());
+        self.with_engine(|engine| engine.lock(*state.id()))??;
+        let locked_state = LockedState::builder()
+            .state(state.clone())
+            .frequency(LOCK_FREQUENCY)
+            .task_done(done_sender)
+            .build();
+        Ok(locked_state)
+    }
+
+    async fn refresh_lock(
+        &self,
+        _meta: &RolloutMetadata,
+        _locked_state: &LockedState,
+    ) -> Result<()> {
+        // Locks held locally never expire.
+        Ok(())
+    }
+
+    async fn abandon_lock(
+        &self,
+        _meta: &RolloutMetadata,
+        locked_state: &LockedState,
+    ) -> Result<()> {
+        trace!("Abandoning {} lock", locked_state.state().state_type());
+        self.with_engine(|engine| engine.abandon(*locked_state.state().id()))
+    }
+
+    async fn poll_for_state(&self, _meta: &RolloutMetadata) -> Result<Vec<TargetState>> {
+        self.with_engine(|engine| engine.poll(Instant::now()))
+    }
+
+    async fn mark_state_completed(
+        &self,
+        _meta: &RolloutMetadata,
+        locked_state: &LockedState,
+    ) -> Result<()> {
+        trace!(
+            "Marking state {} as completed...",
+            locked_state.state().state_type()
+        );
+        self.with_engine(|engine| engine.complete(*locked_state.state().id(), Instant::now()))?
+    }
+
+    async fn upload_observations(
+        &self,
+        _meta: &RolloutMetadata,
+        data: Vec<Measurement>,
+    ) -> Result<()> {
+        self.with_engine(|engine| {
+            for observation in &data {
+                engine.observe(observation);
+            }
+        })
+    }
+
+    async fn retire(&self, _meta: &RolloutMetadata) -> Result<()> {
+        trace!("Retiring the rollout...");
+        self.with_engine(|engine| engine.retire())
+    }
+}
+
+/// The decision-making logic used by the [LocalBackend].
+mod policy;
@@ -0,0 +1,696 @@
+use miette::{IntoDiagnostic, Result, bail};
+use multitool_sdk::models::RolloutStateType;
+use serde::{Deserialize, Serialize};
+use tokio::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    WholePercent,
+    adapters::{
+        DataGap, Measurement, MonitorErrorKind, StatusCode, backend::StateId, backend::TargetState,
+    },
+    metrics::{LATENCY_BUCKETS, LatencyBucket, ResponseStatusCode},
+    stats::{Categorical, ChiSquareTest, ContingencyTable, SequentialDecision, SequentialTest},
+};
+
+/// The percentage of traffic sent to the canary at each step, unless
+/// configured otherwise.
+const DEFAULT_STEPS: [u32; 3] = [10, 25, 50];
+/// By default, each traffic step is held for five minutes.
+const DEFAULT_STEP_DURATION_SECS: u64 = 300;
+const DEFAULT_ALPHA: f64 = 0.05;
+const DEFAULT_BETA: f64 = 0.2;
+const DEFAULT_RELATIVE_RISK: f64 = 2.0;
+/// By default, each group must serve at least this many requests
+/// during a traffic step for the step to be evaluated.
+const DEFAULT_MIN_REQUESTS: u32 = 100;
+/// How many times in a row a traffic step is extended because of
+/// gaps in its data, or too few requests, before we give up and roll back.
+const MAX_STEP_EXTENSIONS: u32 = 3;
+
+/// [PolicyConfig] describes how a rollout progresses when decisions
+/// are made locally instead of by the backend.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub(crate) struct PolicyConfig {
+    /// The percentage of traffic sent to the canary at each step.
+    pub(crate) steps: Vec<u32>,
+    /// How long each traffic step is held before the canary is evaluated.
+    pub(crate) step_duration_secs: u64,
+    /// The significance level for the tests deciding whether the canary
+    /// is a regression. This is the accepted probability of rolling back
+    /// a healthy canary.
+    pub(crate) alpha: f64,
+    /// The accepted probability of missing a regression.
+    pub(crate) beta: f64,
+    /// How many times more often the canary must fail than the baseline
+    /// for us to consider it a regression.
+    pub(crate) relative_risk: f64,
+    /// How many requests both the baseline and the canary must serve
+    /// during a traffic step before it can be evaluated. A step that
+    /// falls short is held again, since the tests can't tell a healthy
+    /// canary from one that hasn't been exercised.
+    pub(crate) min_requests: u32,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            steps: DEFAULT_STEPS.to_vec(),
+            step_duration_secs: DEFAULT_STEP_DURATION_SECS,
+            alpha: DEFAULT_ALPHA,
+            beta: DEFAULT_BETA,
+            relative_risk: DEFAULT_RELATIVE_RISK,
+            min_requests: DEFAULT_MIN_REQUESTS,
+        }
+    }
+}
+
+/// The [PolicyEngine] produces the same sequence of states the backend
+/// would: deploy the canary, step its traffic up, then promote or roll
+/// it back. At the end of each step, chi-square tests compare the
+/// canary's status codes and latencies to the baseline's. Independently, a sequential
+/// test watches every observation so an obvious regression is rolled
+/// back without waiting for the step to end.
+pub(crate) struct PolicyEngine {
+    steps: Vec<WholePercent>,
+    step_duration: Duration,
+    min_requests: u32,
+    chi_square: ChiSquareTest,
+    sprt: SequentialTest<5, ResponseStatusCode>,
+    /// The observations gathered during the current traffic step.
+    table: ContingencyTable<5, ResponseStatusCode>,
+    latency: ContingencyTable<LATENCY_BUCKETS, LatencyBucket>,
+    integration_latency: ContingencyTable<LATENCY_BUCKETS, LatencyBucket>,
+    /// The next state to be effected, if any.
+    pending: Option<TargetState>,
+    /// Whether the pending state has been locked by the relay.
+    locked: bool,
+    /// The index of the traffic step currently being held, and when it began.
+    holding: Option<(usize, Instant)>,
+    /// How many queries failed during the current traffic step.
+    gaps: u32,
+    /// How many times in a row the current step has been extended,
+    /// either because of gaps or because it saw too few requests.
+    extensions: u32,
+    /// Set when a regression is detected while a state is being effected.
+    /// The rollback is issued once that state is released.
+    rollback_requested: bool,
+    /// Set once the rollout has been promoted or rolled back.
+    concluded: bool,
+    next_id: StateId,
+}
+
+impl PolicyEngine {
+    pub(crate) fn new(config: PolicyConfig) -> Result<Self> {
+        if config.steps.is_empty() {
+            bail!("The rollout policy must have at least one traffic step");
+        }
+        let steps = config
+            .steps
+            .iter()
+            .map(|step| WholePercent::try_from(*step))
+            .collect::<Result<Vec<_>, _>>()
+            .into_diagnostic()?;
+        let chi_square = ChiSquareTest::new(config.alpha).into_diagnostic()?;
+        let sprt = SequentialTest::builder()
+            .failure(ResponseStatusCode::_5XX)
+            .alpha(config.alpha)
+            .beta(config.beta)
+            .relative_risk(config.relative_risk)
+            .build()
+            .into_diagnostic()?;
+        let mut engine = Self {
+            steps,
+            step_duration: Duration::from_secs(config.step_duration_secs),
+            min_requests: config.min_requests,
+            chi_square,
+            sprt,
+            table: ContingencyTable::new(),
+            latency: ContingencyTable::new(),
+            integration_latency: ContingencyTable::new(),
+            pending: None,
+            locked: false,
+            holding: None,
+            gaps: 0,
+            extensions: 0,
+            rollback_requested: false,
+            concluded: false,
+            next_id: 0,
+        };
+        // Every rollout starts by deploying the canary.
+        engine.schedule(RolloutStateType::DeployCanary, None);
+        Ok(engine)
+    }
+
+    /// Return the states that are ready to be locked. If the current
+    /// traffic step has been held long enough, the canary is evaluated
+    /// and the next state is scheduled.
+    pub(crate) fn poll(&mut self, now: Instant) -> Vec<TargetState> {
+        if self.pending.is_none()
+            && !self.concluded
+            && let Some((step, since)) = self.holding
+            && now.duration_since(since) >= self.step_duration
+        {
+            self.conclude_step(step, now);
+        }
+        match &self.pending {
+            Some(state) if !self.locked => vec![state.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Claim the pending state with the given id.
+    pub(crate) fn lock(&mut self, id: StateId) -> Result<()> {
+        match &self.pending {
+            Some(state) if *state.id() == id && !self.locked => {
+                self.locked = true;
+                Ok(())
+            }
+            _ => bail!("State {id} is not available to be locked"),
+        }
+    }
+
+    /// Release the lock on the state without completing it, so it
+    /// can be picked up again.
+    pub(crate) fn abandon(&mut self, id: StateId) {
+        if self.pending.as_ref().is_some_and(|state| *state.id() == id) {
+            self.locked = false;
+            if self.rollback_requested {
+                self.schedule_rollback();
+            }
+        }
+    }
+
+    /// Record that the state with the given id has been effected.
+    pub(crate) fn complete(&mut self, id: StateId, now: Instant) -> Result<()> {
+        let state = match self.pending.take() {
+            Some(state) if *state.id() == id => state,
+            other => {
+                self.pending = other;
+                bail!("State {id} is not the pending state");
+            }
+        };
+        self.locked = false;
+        match state.state_type() {
+            RolloutStateType::DeployCanary => {
+                self.schedule_step(0);
+            }
+            RolloutStateType::SetCanaryTraffic => {
+                let step = self.holding.map_or(0, |(step, _)| step);
+                self.holding = Some((step, now));
+                self.gaps = 0;
+                self.extensions = 0;
+                self.table = ContingencyTable::new();
+                self.latency = ContingencyTable::new();
+                self.integration_latency = ContingencyTable::new();
+            }
+            RolloutStateType::PromoteCanary | RolloutStateType::RollbackCanary => {
+                self.concluded = true;
+            }
+        }
+        if self.rollback_requested && !self.concluded {
+            self.schedule_rollback();
+        }
+        Ok(())
+    }
+
+    /// Stop scheduling states, since the rollout was settled
+    /// without the engine's say-so.
+    pub(crate) fn retire(&mut self) {
+        self.pending = None;
+        self.locked = false;
+        self.holding = None;
+        self.rollback_requested = false;
+        self.concluded = true;
+    }
+
+    /// Feed a new observation into the statistical tests.
+    pub(crate) fn observe(&mut self, measurement: &Measurement) {
+        match measurement {
+            Measurement::StatusCode(observation) => self.observe_status_code(observation),
+            Measurement::Latency(observation) => self.latency.add_observation(observation),
+            Measurement::IntegrationLatency(observation) => {
+                self.integration_latency.add_observation(observation)
+            }
+            // These don't factor into any decisions yet.
+            Measurement::Cpu(observation) => debug!("{observation}"),
+            Measurement::Counter(observation) => debug!("{observation}"),
+            Measurement::Gap(gap) => self.observe_gap(gap),
+        }
+    }
+
+    /// A gap caused by a transient error only delays the end of the
+    /// step. Any other gap means the monitor can't see the canary at
+    /// all, so we can't tell whether it's healthy.
+    fn observe_gap(&mut self, gap: &DataGap) {
+        if self.concluded {
+            return;
+        }
+        match gap.kind() {
+            MonitorErrorKind::Transient => {
+                warn!("{gap}. The current traffic step will be extended.");
+                self.gaps += 1;
+            }
+            MonitorErrorKind::Permanent | MonitorErrorKind::Auth => {
+                error!("{gap}. The canary can't be evaluated, so it will be rolled back.");
+                self.request_rollback();
+            }
+        }
+    }
+
+    fn observe_status_code(&mut self, observation: &StatusCode) {
+        self.table.add_observation(observation);
+        let previous = self.sprt.decision();
+        let decision = self.sprt.observe(observation);
+        // The decision is sticky, so only act on it the first time.
+        let detected = previous != decision && decision == SequentialDecision::Regression;
+        if detected && !self.concluded {
+            if let Some(llr) = self.sprt.log_likelihood_ratio() {
+                warn!("Sequential test detected a regression (log-likelihood ratio {llr:.3})");
+            }
+            self.request_rollback();
+        }
+    }
+
+    /// Decide what follows the given traffic step.
+    fn conclude_step(&mut self, step: usize, now: Instant) {
+        // Without a complete picture of the step, its data can't be
+        // trusted to be representative, so hold the step again.
+        if self.gaps > 0 {
+            if self.extensions >= MAX_STEP_EXTENSIONS {
+                error!("The monitor keeps failing, so the canary can't be evaluated.");
+                self.request_rollback();
+            } else {
+                warn!(
+                    "Traffic step {} had {} gaps in its data. Holding it for another {:?}.",
+                    self.steps[step], self.gaps, self.step_duration
+                );
+                self.extend_step(step, now);
+            }
+            return;
+        }
+        // Likewise, a step where either group barely served any requests
+        // says nothing about the canary, so it can't pass either. The
+        // counts carry over, so an extension only needs to make up the
+        // difference.
+        let (baseline, canary) = (self.table.expected_total(), self.table.observed_total());
+        if baseline < self.min_requests || canary < self.min_requests {
+            if self.extensions >= MAX_STEP_EXTENSIONS {
+                error!(
+                    "The canary served {canary} requests and the baseline served {baseline}, \
+                    but each must serve {} to be evaluated.",
+                    self.min_requests
+                );
+                self.request_rollback();
+            } else {
+                warn!(
+                    "Traffic step {} saw {canary} canary and {baseline} baseline requests, \
+                    fewer than the {} needed. Holding it for another {:?}.",
+                    self.steps[step], self.min_requests, self.step_duration
+                );
+                self.extend_step(step, now);
+            }
+            return;
+        }
+        let outcome = self.chi_square.independence(&self.table);
+        info!("Traffic step {}: {outcome}", self.steps[step]);
+        // The test is two-sided, so only roll back if the canary
+        // is the one returning more errors.
+        let index = ResponseStatusCode::_5XX.category();
+        let canary_is_worse =
+            f64::from(self.table.observed_by_index(index)) > self.table.expected_by_index(index);
+        let canary_is_slower = [
+            ("Latency", &self.latency),
+            ("Integration latency", &self.integration_latency),
+        ]
+        .into_iter()
+        .any(|(name, table)| self.is_slower(name, table));
+        if (outcome.is_significant() && canary_is_worse) || canary_is_slower {
+            self.request_rollback();
+        } else if step + 1 < self.steps.len() {
+            self.schedule_step(step + 1);
+        } else {
+            self.schedule(RolloutStateType::PromoteCanary, None);
+        }
+    }
+
+    /// Hold the given step for another step duration.
+    fn extend_step(&mut self, step: usize, now: Instant) {
+        self.gaps = 0;
+        self.extensions += 1;
+        self.holding = Some((step, now));
+    }
+
+    /// Whether the canary's latencies are significantly higher than
+    /// the baseline's. Monitors that don't report latency leave the
+    /// table empty, in which case the canary is never slower.
+    fn is_slower(
+        &self,
+        name: &str,
+        table: &ContingencyTable<LATENCY_BUCKETS, LatencyBucket>,
+    ) -> bool {
+        let (Some(baseline), Some(canary)) = (
+            mean_bucket(|i| table.expected_count_by_index(i)),
+            mean_bucket(|i| table.observed_by_index(i)),
+        ) else {
+            return false;
+        };
+        let outcome = self.chi_square.independence(table);
+        info!("{name}: {outcome}");
+        // Like status codes, the test is two-sided, so only
+        // roll back if the canary is the slower one.
+        outcome.is_significant() && canary > baseline
+    }
+
+    fn request_rollback(&mut self) {
+        if self.locked {
+            // The relay is effecting a state. Wait for it to finish
+            // before rolling back.
+            self.rollback_requested = true;
+        } else {
+            self.schedule_rollback();
+        }
+    }
+
+    fn schedule_rollback(&mut self) {
+        self.rollback_requested = false;
+        self.holding = None;
+        self.schedule(RolloutStateType::RollbackCanary, None);
+    }
+
+    fn schedule_step(&mut self, step: usize) {
+        self.holding = Some((step, Instant::now()));
+        let percent = self.steps[step].clone();
+        self.schedule(RolloutStateType::SetCanaryTraffic, Some(percent));
+    }
+
+    fn schedule(&mut self, state_type: RolloutStateType, percent_traffic: Option<WholePercent>) {
+        self.next_id += 1;
+        let state = TargetState::builder()
+            .id(self.next_id)
+            .state_type(state_type)
+            .maybe_percent_traffic(percent_traffic)
+            .build();
+        self.pending = Some(state);
+    }
+}
+
+/// The average bucket index of a row of the table, weighted by
+/// its counts. Returns `None` if the row is empty.
+fn mean_bucket(count: impl Fn(usize) -> u32) -> Option<f64> {
+    let (total, weighted) = (0..LATENCY_BUCKETS).fold((0.0, 0.0), |(total, weighted), i| {
+        let count = f64::from(count(i));
+        (total + count, weighted + count * i as f64)
+    });
+    (total > 0.0).then(|| weighted / total)
+}
+
+#[cfg(test)]
+mod tests {
+    use multitool_sdk::models::RolloutStateType;
+    use pretty_assertions::assert_eq;
+    use tokio::time::{Duration, Instant};
+
+    use super::{MAX_STEP_EXTENSIONS, PolicyConfig, PolicyEngine};
+    use crate::{
+        adapters::{DataGap, Measurement, MonitorErrorKind},
+        metrics::{LatencyBucket, ResponseStatusCode},
+        stats::{CategoricalObservation, Group},
+    };
+
+    fn observation(group: Group, ok: u32, errors: u32) -> Measurement {
+        let mut obs = CategoricalObservation::new(group);
+        obs.increment_by(&ResponseStatusCode::_2XX, ok);
+        obs.increment_by(&ResponseStatusCode::_5XX, errors);
+        Measurement::StatusCode(obs)
+    }
+
+    /// Observe `count` requests taking around `millis` milliseconds each.
+    fn latency(group: Group, millis: f64, count: u32) -> Measurement {
+        let mut obs = CategoricalObservation::new(group);
+        obs.increment_by(&LatencyBucket::from_millis(millis), count);
+        Measurement::Latency(obs)
+    }
+
+    fn engine(steps: Vec<u32>) -> PolicyEngine {
+        PolicyEngine::new(config(steps)).unwrap()
+    }
+
+    fn config(steps: Vec<u32>) -> PolicyConfig {
+        PolicyConfig {
+            steps,
+            step_duration_secs: 60,
+            ..Default::default()
+        }
+    }
+
+    /// Lock and complete the next pending state, returning its type.
+    fn advance(engine: &mut PolicyEngine, now: Instant) -> Option<RolloutStateType> {
+        let state = engine.poll(now).pop()?;
+        engine.lock(*state.id()).unwrap();
+        engine.complete(*state.id(), now).unwrap();
+        Some(*state.state_type())
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        assert!(
+            PolicyEngine::new(PolicyConfig {
+                steps: vec![],
+                ..Default::default()
+            })
+            .is_err()
+        );
+        assert!(
+            PolicyEngine::new(PolicyConfig {
+                steps: vec![150],
+                ..Default::default()
+            })
+            .is_err()
+        );
+    }
+
+    /// A healthy canary walks through every step, then gets promoted.
+    #[test]
+    fn healthy_canary_is_promoted() {
+        let mut engine = engine(vec![10, 50]);
+        let mut now = Instant::now();
+        assert_eq!(
+            advance(&mut engine, now),
+            Some(RolloutStateType::DeployCanary)
+        );
+        let state = engine.poll(now).pop().unwrap();
+        assert_eq!(state.percent_traffic().clone().unwrap().to_string(), "10%");
+        assert_eq!(
+            advance(&mut engine, now),
+            Some(RolloutStateType::SetCanaryTraffic)
+        );
+        // The step is held until its duration elapses.
+        engine.observe(&observation(Group::Control, 1000, 10));
+        engine.observe(&observation(Group::Experimental, 100, 1));
+        assert!(engine.poll(now).is_empty());
+        now += Duration::from_secs(61);
+        let state = engine.poll(now).pop().unwrap();
+        assert_eq!(state.percent_traffic().clone().unwrap().to_string(), "50%");
+        assert_eq!(
+            advance(&mut engine, now),
+            Some(RolloutStateType::SetCanaryTraffic)
+        );
+        engine.observe(&observation(Group::Control, 1000, 10));
+        engine.observe(&observation(Group::Experimental, 500, 5));
+        now += Duration::from_secs(61);
+        assert_eq!(
+            advance(&mut engine, now),
+            Some(RolloutStateType::PromoteCanary)
+        );
+        // Nothing happens once the rollout has concluded.
+        now += Duration::from_secs(61);
+        assert!(engine.poll(now).is_empty());
+    }
+
+    /// A canary that fails noticeably more often than the baseline is
+    /// rolled back at the end of the step.
+    #[test]
+    fn regression_is_rolled_back_at_end_of_step() {
+        // Only flag large regressions sequentially, so the step runs its course.
+        let mut engine = PolicyEngine::new(PolicyConfig {
+            relative_risk: 10.0,
+            ..config(vec![10, 50])
+        })
+        .unwrap();
+        let mut now = Instant::now();
+        advance(&mut engine, now);
+        advance(&mut engine, now);
+        // The canary fails three times as often as the baseline.
+        engine.observe(&observation(Group::Control, 10_000, 100));
+        engine.observe(&observation(Group::Experimental, 970, 30));
+        now += Duration::from_secs(61);
+        assert_eq!(
+            advance(&mut engine, now),
+            Some(RolloutStateType::RollbackCanary)
+        );
+    }
+
+    /// An obvious regression is rolled back immediately, but only once
+    /// the state currently being effected has been released.
+    #[test]
+    fn sequential_test_rolls_back_early() {
+        let mut engine = engine(vec![10, 50]);
+        let now = Instant::now();
+        advance(&mut engine, now);
+        let state = engine.poll(now).pop().unwrap();
+        engine.lock(*state.id()).unwrap();
+        engine.observe(&observation(Group::Control, 999, 1));
+        engine.observe(&observation(Group::Experimental, 10, 10));
+        // The traffic step is still locked, so nothing new is available.
+        assert!(engine.poll(now).is_empty());
+        engine.complete(*state.id(), now).unwrap();
+        assert_eq!(
+            advance(&mut engine, now),
+            Some(RolloutStateType::RollbackCanary)
+        );
+    }
+
+    /// A retired rollout doesn't schedule anything else, even
+    /// once the step it was holding has elapsed.
+    #[test]
+    fn retired_rollouts_stop() {
+        let mut engine = engine(vec![10, 50]);
+        let now = Instant::now();
+        advance(&mut engine, now);
+        engine.retire();
+        assert!(engine.poll(now + Duration::from_secs(120)).is_empty());
+    }
+
+    /// An abandoned state can be locked again.
+    #[test]
+    fn abandoned_states_are_retried() {
+        let mut engine = engine(vec![10]);
+        let now = Instant::now();
+        let state = engine.poll(now).pop().unwrap();
+        engine.lock(*state.id()).unwrap();
+        assert!(engine.lock(*state.id()).is_err());
+        engine.abandon(*state.id());
+        let retried = engine.poll(now).pop().unwrap();
+        assert_eq!(*retried.id(), *state.id());
+    }
+
+    /// A canary that doesn't fail, but responds noticeably slower
+    /// than the baseline, is rolled back at the end of the step.
+    #[test]
+    fn slow_canary_is_rolled_back() {
+        let mut engine = engine(vec![10, 50]);
+        let mut now = Instant::now();
+        advance(&mut engine, now);
+        advance(&mut engine, now);
+        engine.observe(&observation(Group::Control, 1000, 0));
+        engine.observe(&observation(Group::Experimental, 100, 0));
+        engine.observe(&latency(Group::Control, 40.0, 900));
+        engine.observe(&latency(Group::Control, 80.0, 100));
+        engine.observe(&latency(Group::Experimental, 40.0, 50));
+        engine.observe(&latency(Group::Experimental, 80.0, 50));
+        now += Duration::from_secs(61);
+        assert_eq!(
+            advance(&mut engine, now),
+            Some(RolloutStateType::RollbackCanary)
+        );
+    }
+
+    /// A canary nobody sends requests to is never promoted. Each
+    /// empty step is held again, until the engine gives up.
+    #[test]
+    fn idle_canary_is_never_promoted() {
+        let mut engine = engine(vec![10, 50]);
+        let mut now = Instant::now();
+        advance(&mut engine, now);
+        advance(&mut engine, now);
+        for _ in 0..MAX_STEP_EXTENSIONS {
+            now += Duration::from_secs(61);
+            assert!(engine.poll(now).is_empty());
+        }
+        now += Duration::from_secs(61);
+        assert_eq!(
+            advance(&mut engine, now),
+            Some(RolloutStateType::RollbackCanary)
+        );
+    }
+
+    /// A step that's short on requests proceeds once
+    /// an extension makes up the difference.
+    #[test]
+    fn sparse_steps_are_extended() {
+        let mut engine = engine(vec![10]);
+        let mut now = Instant::now();
+        advance(&mut engine, now);
+        advance(&mut engine, now);
+        engine.observe(&observation(Group::Control, 1000, 0));
+        engine.observe(&observation(Group::Experimental, 3, 0));
+        now += Duration::from_secs(61);
+        assert!(engine.poll(now).is_empty());
+        engine.observe(&observation(Group::Experimental, 97, 0));
+        now += Duration::from_secs(61);
+        assert_eq!(
+            advance(&mut engine, now),
+            Some(RolloutStateType::PromoteCanary)
+        );
+    }
+
+    fn gap(kind: MonitorErrorKind) -> Measurement {
+        Measurement::Gap(DataGap::new(kind))
+    }
+
+    /// A step with gaps in its data is held again instead of advancing.
+    #[test]
+    fn gaps_extend_the_step() {
+        let mut engine = engine(vec![10]);
+        let mut now = Instant::now();
+        advance(&mut engine, now);
+        advance(&mut engine, now);
+        engine.observe(&observation(Group::Control, 1000, 10));
+        engine.observe(&gap(MonitorErrorKind::Transient));
+        now += Duration::from_secs(61);
+        assert!(engine.poll(now).is_empty());
+        // Once a step completes without gaps, the rollout proceeds.
+        engine.observe(&observation(Group::Experimental, 100, 1));
+        now += Duration::from_secs(61);
+        assert_eq!(
+            advance(&mut engine, now),
+            Some(RolloutStateType::PromoteCanary)
+        );
+    }
+
+    /// If every extension has gaps too, the canary is rolled back.
+    #[test]
+    fn persistent_gaps_roll_back() {
+        let mut engine = engine(vec![10]);
+        let mut now = Instant::now();
+        advance(&mut engine, now);
+        advance(&mut engine, now);
+        for _ in 0..MAX_STEP_EXTENSIONS {
+            engine.observe(&gap(MonitorErrorKind::Transient));
+            now += Duration::from_secs(61);
+            assert!(engine.poll(now).is_empty());
+        }
+        engine.observe(&gap(MonitorErrorKind::Transient));
+        now += Duration::from_secs(61);
+        assert_eq!(
+            advance(&mut engine, now),
+            Some(RolloutStateType::RollbackCanary)
+        );
+    }
+
+    /// A monitor that can't read its data can't vouch for the canary.
+    #[test]
+    fn auth_gaps_roll_back() {
+        let mut engine = engine(vec![10]);
+        let now = Instant::now();
+        advance(&mut engine, now);
+        advance(&mut engine, now);
+        engine.observe(&gap(MonitorErrorKind::Auth));
+        assert_eq!(
+            advance(&mut engine, now),
+            Some(RolloutStateType::RollbackCanary)
+        );
+    }
+}
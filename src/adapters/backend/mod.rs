@@ -1,28 +1,95 @@
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::{Arc, Once};
 
-use super::{BoxedIngress, BoxedMonitor, BoxedPlatform, StatusCode};
-use crate::fs::UserCreds;
-use crate::{fs::Session, metrics::ResponseStatusCode};
+use super::{BoxedIngress, BoxedMonitor, BoxedPlatform, Measurement};
+use crate::fs::{File as _, FileSystem, ObservationQueueFile, RejectedObservationsFile, UserCreds};
+use crate::{
+    fs::Session,
+    metrics::{LatencyBucket, ResponseStatusCode},
+};
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use miette::{IntoDiagnostic, Result, bail};
+use miette::{Result, bail};
 use multitool_sdk::apis::{Api, ApiClient, configuration::Configuration};
+use multitool_sdk::models::UpdateRolloutStateRequest;
 use multitool_sdk::models::{
     ApplicationDetails, ApplicationGroup, CreateResponseCodeMetricsRequest, LoginRequest,
-    LoginSuccess, RolloutStateStatus, StatusCodeMetrics, WorkspaceSummary,
+    LoginSuccess, RolloutState, RolloutStateStatus, RolloutStateType, StatusCodeMetrics,
+    WorkspaceSummary,
 };
-use multitool_sdk::models::{RolloutState, UpdateRolloutStateRequest};
 use tokio::sync::mpsc::Sender;
-use tokio::sync::oneshot;
+use tokio::sync::{Mutex, oneshot};
 use tokio::time::Duration;
 
 pub(crate) use deploy_meta::*;
-use tracing::trace;
+pub(crate) use local::{LocalBackend, PolicyConfig};
+pub(crate) use queue::PendingBatch;
+use queue::{LatencyMetrics, ObservationQueue};
+use retry::{Idempotency, Retrier};
+use status::outcome_state;
+pub(crate) use status::{RolloutStatus, canary_deployed, is_in_flight};
+use tracing::{error, info, trace, warn};
 
 /// Write the CLI's version to a
 const USER_AGENT: &str = concat!("multi/", env!("CARGO_PKG_VERSION"));
+/// How long we wait for the backend to respond to a single request.
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub mod deploy_meta;
+/// A backend that makes rollout decisions locally, without
+/// consulting the MultiTool SaaS.
+mod local;
+/// Buffers observations on disk until the backend acknowledges them.
+mod queue;
+/// Retries failed requests to the MultiTool SaaS, and stops
+/// sending them when the backend is down.
+mod retry;
+/// A summary of a rollout's progress, for `multi status`.
+mod status;
+
+/// Convenience alias since the backend is shared between
+/// the subsystems that drive a rollout.
+pub(crate) type SharedBackend = Arc<dyn Backend + Send + Sync>;
+
+/// The [Backend] decides which states a rollout moves through. It
+/// hands out states to be effected, tracks which states are locked
+/// and completed, and receives the observations that inform its decisions.
+/// Usually, this is the MultiTool SaaS, but decisions can also be made locally.
+#[async_trait]
+pub(crate) trait Backend {
+    /// Claim the given state so no one else attempts to effect it.
+    async fn lock_state(
+        &self,
+        meta: &RolloutMetadata,
+        state: &TargetState,
+        done_sender: Sender<oneshot::Sender<()>>,
+    ) -> Result<LockedState>;
+    /// Renew the lease on a state we've locked.
+    async fn refresh_lock(&self, meta: &RolloutMetadata, locked_state: &LockedState) -> Result<()>;
+    /// Release the lock on this state without completing it.
+    async fn abandon_lock(&self, meta: &RolloutMetadata, locked_state: &LockedState) -> Result<()>;
+    /// Poll for pending states that have not yet been
+    /// locked/claimed and thus are ready to be locked and processed.
+    async fn poll_for_state(&self, meta: &RolloutMetadata) -> Result<Vec<TargetState>>;
+    /// Report that the locked state has been effected.
+    async fn mark_state_completed(
+        &self,
+        meta: &RolloutMetadata,
+        locked_state: &LockedState,
+    ) -> Result<()>;
+    /// Upload a batch of observations.
+    async fn upload_observations(
+        &self,
+        meta: &RolloutMetadata,
+        data: Vec<Measurement>,
+    ) -> Result<()>;
+    /// Stop handing out states, because the runner rolled the
+    /// canary back on its own, e.g. when a hook failed.
+    async fn retire(&self, meta: &RolloutMetadata) -> Result<()>;
+}
+
+/// Whether we've warned that latency isn't uploaded. Once is enough.
+static LATENCY_DROPPED: Once = Once::new();
 
 // WARNING: This code seriously needs to be cleaned up.
 // I wrote this in a sloppy fit while trying to yak shave
@@ -36,6 +103,12 @@ pub struct BackendClient {
     conf: Configuration,
     client: ApiClient,
     session: Option<Session>,
+    /// Every request is made through the retrier, so one
+    /// transient failure doesn't interrupt the rollout.
+    retrier: Retrier,
+    /// Observations are buffered here until the backend accepts them.
+    /// It's opened for the first rollout we upload observations for.
+    queue: Arc<Mutex<Option<(RolloutId, ObservationQueue)>>>,
     // TODO: Add a method for updating the access token.
 }
 
@@ -46,6 +119,8 @@ impl Clone for BackendClient {
             conf: conf.clone(),
             client: ApiClient::new(Arc::new(conf)),
             session: self.session.clone(),
+            retrier: self.retrier.clone(),
+            queue: self.queue.clone(),
         }
     }
 }
@@ -62,6 +137,8 @@ impl BackendClient {
             conf: raw_conf,
             client,
             session,
+            retrier: Retrier::default(),
+            queue: Arc::default(),
         })
     }
 
@@ -73,142 +150,21 @@ impl BackendClient {
         }
     }
 
-    pub(crate) async fn lock_state(
-        &self,
-        meta: &RolloutMetadata,
-        state: &RolloutState,
-        done_sender: Sender<oneshot::Sender<()>>,
-    ) -> Result<LockedState> {
-        trace!("Locking state {}...", state.state_type);
-        self.client
-            .rollout_states_api()
-            .update_rollout_state(
-                *meta.workspace_id(),
-                *meta.application_id(),
-                *meta.rollout_id(),
-                state.id,
-                UpdateRolloutStateRequest {
-                    status: Some(Some(RolloutStateStatus::InProgress)),
-                },
-            )
-            .await
-            .into_diagnostic()?;
-
-        let locked_state = LockedState::builder()
-            .state(state.clone())
-            // TODO: we should return this from the API
-            .frequency(Duration::from_secs(30))
-            .task_done(done_sender)
-            .build();
-
-        trace!("State locked successfully");
-        Ok(locked_state)
-    }
-
-    pub(crate) async fn refresh_lock(
-        &self,
-        meta: &RolloutMetadata,
-        locked_state: &LockedState,
-    ) -> Result<()> {
-        trace!("Refreshing {} lock...", locked_state.state().state_type);
-        self.client
-            .rollout_states_api()
-            .refresh_rollout_state(
-                *meta.workspace_id(),
-                *meta.application_id(),
-                *meta.rollout_id(),
-                locked_state.state().id,
-            )
-            .await
-            .into_diagnostic()?;
-        trace!("Lock refreshed successfully");
-        Ok(())
-    }
-
-    /// Release the lock on this state without completing it.
-    pub(crate) async fn abandon_lock(
-        &self,
-        meta: &RolloutMetadata,
-        locked_state: &LockedState,
-    ) -> Result<()> {
-        trace!("Abandoning {} lock", locked_state.state().state_type);
-        self.client
-            .rollout_states_api()
-            .update_rollout_state(
-                *meta.workspace_id(),
-                *meta.application_id(),
-                *meta.rollout_id(),
-                locked_state.state().id,
-                UpdateRolloutStateRequest {
-                    status: Some(Some(RolloutStateStatus::Pending)),
-                },
-            )
-            .await
-            .into_diagnostic()?;
-
-        trace!("Lock abandoned successfully");
-        Ok(())
-    }
-
-    /// Poll the backend for pending states that have not yet been
-    /// locked/claimed and thus are ready to be locked and processed.
-    pub(crate) async fn poll_for_state(&self, meta: &RolloutMetadata) -> Result<Vec<RolloutState>> {
-        trace!("Polling for new states...");
-        let response = self
-            .client
-            .rollout_states_api()
-            .list_rollout_states(
-                *meta.workspace_id(),
-                *meta.application_id(),
-                *meta.rollout_id(),
-                Some(RolloutStateStatus::Pending),
-            )
-            .await
-            .into_diagnostic()?;
-
-        trace!("States polled successfully");
-        Ok(response.states)
-    }
-
-    pub(crate) async fn mark_state_completed(
-        &self,
-        meta: &RolloutMetadata,
-        locked_state: &LockedState,
-    ) -> Result<()> {
-        trace!(
-            "Marking state {} as completed...",
-            locked_state.state().state_type
-        );
-        self.client
-            .rollout_states_api()
-            .update_rollout_state(
-                *meta.workspace_id(),
-                *meta.application_id(),
-                *meta.rollout_id(),
-                locked_state.state().id,
-                UpdateRolloutStateRequest {
-                    status: Some(Some(RolloutStateStatus::Done)),
-                },
-            )
-            .await
-            .into_diagnostic()?;
-
-        trace!("State successfully marked as complete");
-        Ok(())
-    }
-
     pub async fn new_rollout(
         &self,
         workspace_id: WorkspaceId,
         application_id: ApplicationId,
     ) -> Result<RolloutId> {
         trace!("Creating a new rollout");
+        // Retrying could create two rollouts.
         let response = self
-            .client
-            .rollouts_api()
-            .create_rollout(workspace_id, application_id)
-            .await
-            .into_diagnostic()?;
+            .retrier
+            .call("create_rollout", Idempotency::NonIdempotent, || {
+                self.client
+                    .rollouts_api()
+                    .create_rollout(workspace_id, application_id)
+            })
+            .await?;
 
         trace!("Rollout created successfully");
         Ok(response.rollout.id)
@@ -225,69 +181,28 @@ impl BackendClient {
             password: password.to_owned(),
         };
         let creds: UserCreds = self
-            .client
-            .users_api()
-            .login(req)
-            .await
-            .into_diagnostic()?
+            .retrier
+            .call("login", Idempotency::NonIdempotent, || {
+                self.client.users_api().login(req.clone())
+            })
+            .await?
             .into();
 
         trace!("Creds exchanged, login success");
         Ok(Session::User(creds))
     }
 
-    /// Upload a batch of observations to the backend.
-    pub(crate) async fn upload_observations(
-        &self,
-        meta: &RolloutMetadata,
-        data: Vec<StatusCode>,
-    ) -> Result<()> {
-        trace!("Uploading observations to backend");
-        let mut status_codes = Vec::new();
-
-        for item in data {
-            let group = match item.group() {
-                crate::stats::Group::Control => ApplicationGroup::Baseline,
-                crate::stats::Group::Experimental => ApplicationGroup::Canary,
-            };
-            let metrics = StatusCodeMetrics {
-                app_group: group,
-                status_2xx_count: item.get_count(&ResponseStatusCode::_2XX) as u32,
-                status_4xx_count: item.get_count(&ResponseStatusCode::_4XX) as u32,
-                status_5xx_count: item.get_count(&ResponseStatusCode::_5XX) as u32,
-                created_at: Utc::now().to_rfc3339(),
-            };
-
-            status_codes.push(metrics);
-        }
-
-        let req_body = CreateResponseCodeMetricsRequest { status_codes };
-
-        let workspace_id = *meta.workspace_id();
-        let application_id = *meta.application_id();
-        let rollout_id = *meta.rollout_id();
-
-        self.client
-            .response_code_metrics_api()
-            .create_response_code_metrics(workspace_id, application_id, rollout_id, req_body)
-            .await
-            .into_diagnostic()?;
-
-        trace!("Observations uploaded successfully");
-        Ok(())
-    }
-
     /// Return information about the workspace given its name.
     pub(crate) async fn get_workspace_by_name(&self, name: &str) -> Result<WorkspaceSummary> {
         self.is_authenicated()?;
 
         trace!("Getting workspace id using its name");
         let mut workspaces: Vec<_> = self
-            .client
-            .workspaces_api()
-            .list_workspaces(Some(name))
-            .await
-            .into_diagnostic()?
+            .retrier
+            .call("list_workspaces", Idempotency::Idempotent, || {
+                self.client.workspaces_api().list_workspaces(Some(name))
+            })
+            .await?
             .workspaces
             .into_iter()
             .filter(|workspace| workspace.display_name == name)
@@ -317,11 +232,13 @@ impl BackendClient {
         trace!("Getting application id using its name");
 
         let mut applications: Vec<_> = self
-            .client
-            .applications_api()
-            .list_applications(workspace_id)
-            .await
-            .into_diagnostic()?
+            .retrier
+            .call("list_applications", Idempotency::Idempotent, || {
+                self.client
+                    .applications_api()
+                    .list_applications(workspace_id)
+            })
+            .await?
             .applications
             .into_iter()
             .filter(|elem| elem.display_name == name)
@@ -336,14 +253,397 @@ impl BackendClient {
             applications.pop().unwrap()
         };
 
-        self.client
-            .applications_api()
-            .get_application(workspace_id, application.id)
+        self.retrier
+            .call("get_application", Idempotency::Idempotent, || {
+                self.client
+                    .applications_api()
+                    .get_application(workspace_id, application.id)
+            })
             .await
             .map(|success| *success.application)
-            .into_diagnostic()
             .inspect(|_| trace!("Successfully acquired the workspace id"))
     }
+
+    /// List the ids of the application's rollouts, newest first.
+    pub(crate) async fn list_rollouts(
+        &self,
+        workspace_id: WorkspaceId,
+        application_id: ApplicationId,
+    ) -> Result<Vec<RolloutId>> {
+        trace!("Listing rollouts...");
+        let mut rollouts: Vec<_> = self
+            .retrier
+            .call("list_rollouts", Idempotency::Idempotent, || {
+                self.client
+                    .rollouts_api()
+                    .list_rollouts(workspace_id, application_id)
+            })
+            .await?
+            .rollouts
+            .into_iter()
+            .map(|rollout| rollout.id)
+            .collect();
+        rollouts.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(rollouts)
+    }
+
+    /// List every state of the rollout, whatever its status.
+    pub(crate) async fn rollout_states(&self, meta: &RolloutMetadata) -> Result<Vec<RolloutState>> {
+        trace!("Listing rollout states...");
+        let states = self
+            .retrier
+            .call("list_rollout_states", Idempotency::Idempotent, || {
+                // Without a status, every state is listed.
+                self.client.rollout_states_api().list_rollout_states(
+                    *meta.workspace_id(),
+                    *meta.application_id(),
+                    *meta.rollout_id(),
+                    None,
+                )
+            })
+            .await?
+            .states;
+        Ok(states)
+    }
+
+    /// Summarize the rollout's states and its latest observations.
+    pub(crate) async fn rollout_status(&self, meta: &RolloutMetadata) -> Result<RolloutStatus> {
+        trace!("Loading rollout status...");
+        let states = self.rollout_states(meta).await?;
+        let observations = self
+            .retrier
+            .call(
+                "list_response_code_metrics",
+                Idempotency::Idempotent,
+                || {
+                    self.client
+                        .response_code_metrics_api()
+                        .list_response_code_metrics(
+                            *meta.workspace_id(),
+                            *meta.application_id(),
+                            *meta.rollout_id(),
+                        )
+                },
+            )
+            .await?
+            .status_codes;
+        Ok(RolloutStatus::new(*meta.rollout_id(), states, observations))
+    }
+
+    /// Record the outcome the rollout was settled with, by marking the
+    /// outstanding state of that type as done. States that were never
+    /// effected are left as they are, so the rollout's history stays
+    /// accurate. Once the outcome is recorded, the rollout is settled,
+    /// so no runner picks up its other states. Returns whether the
+    /// backend had scheduled a state to record the outcome with.
+    pub(crate) async fn record_outcome(
+        &self,
+        meta: &RolloutMetadata,
+        outcome: RolloutStateType,
+    ) -> Result<bool> {
+        trace!("Recording the rollout's outcome...");
+        let states = self.rollout_states(meta).await?;
+        let Some(state_id) = outcome_state(&states, outcome) else {
+            return Ok(false);
+        };
+        self.retrier
+            .call("record_outcome", Idempotency::Idempotent, || {
+                self.client.rollout_states_api().update_rollout_state(
+                    *meta.workspace_id(),
+                    *meta.application_id(),
+                    *meta.rollout_id(),
+                    state_id,
+                    UpdateRolloutStateRequest {
+                        status: Some(Some(RolloutStateStatus::Done)),
+                    },
+                )
+            })
+            .await?;
+        trace!("Recorded the outcome in state {state_id}");
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl Backend for BackendClient {
+    async fn lock_state(
+        &self,
+        meta: &RolloutMetadata,
+        state: &TargetState,
+        done_sender: Sender<oneshot::Sender<()>>,
+    ) -> Result<LockedState> {
+        trace!("Locking state {}...", state.state_type());
+        // Setting a state's status is idempotent, so every status
+        // update can be retried.
+        self.retrier
+            .call("lock_state", Idempotency::Idempotent, || {
+                self.client.rollout_states_api().update_rollout_state(
+                    *meta.workspace_id(),
+                    *meta.application_id(),
+                    *meta.rollout_id(),
+                    *state.id(),
+                    UpdateRolloutStateRequest {
+                        status: Some(Some(RolloutStateStatus::InProgress)),
+                    },
+                )
+            })
+            .await?;
+
+        let locked_state = LockedState::builder()
+            .state(state.clone())
+            // TODO: we should return this from the API
+            .frequency(Duration::from_secs(30))
+            .task_done(done_sender)
+            .build();
+
+        trace!("State locked successfully");
+        Ok(locked_state)
+    }
+
+    async fn refresh_lock(&self, meta: &RolloutMetadata, locked_state: &LockedState) -> Result<()> {
+        trace!("Refreshing {} lock...", locked_state.state().state_type());
+        self.retrier
+            .call("refresh_lock", Idempotency::Idempotent, || {
+                self.client.rollout_states_api().refresh_rollout_state(
+                    *meta.workspace_id(),
+                    *meta.application_id(),
+                    *meta.rollout_id(),
+                    *locked_state.state().id(),
+                )
+            })
+            .await?;
+        trace!("Lock refreshed successfully");
+        Ok(())
+    }
+
+    async fn abandon_lock(&self, meta: &RolloutMetadata, locked_state: &LockedState) -> Result<()> {
+        trace!("Abandoning {} lock", locked_state.state().state_type());
+        self.retrier
+            .call("abandon_lock", Idempotency::Idempotent, || {
+                self.client.rollout_states_api().update_rollout_state(
+                    *meta.workspace_id(),
+                    *meta.application_id(),
+                    *meta.rollout_id(),
+                    *locked_state.state().id(),
+                    UpdateRolloutStateRequest {
+                        status: Some(Some(RolloutStateStatus::Pending)),
+                    },
+                )
+            })
+            .await?;
+
+        trace!("Lock abandoned successfully");
+        Ok(())
+    }
+
+    async fn poll_for_state(&self, meta: &RolloutMetadata) -> Result<Vec<TargetState>> {
+        trace!("Polling for new states...");
+        let states = self
+            .retrier
+            .call("poll_for_state", Idempotency::Idempotent, || {
+                self.client.rollout_states_api().list_rollout_states(
+                    *meta.workspace_id(),
+                    *meta.application_id(),
+                    *meta.rollout_id(),
+                    None,
+                )
+            })
+            .await?
+            .states;
+
+        trace!("States polled successfully");
+        // • A rollout settled by hand keeps the states it never
+        //   effected, but they mustn't be effected now.
+        if !is_in_flight(&states) {
+            return Ok(Vec::new());
+        }
+        states
+            .into_iter()
+            .filter(|state| state.status == RolloutStateStatus::Pending)
+            .map(TargetState::try_from)
+            .collect()
+    }
+
+    async fn mark_state_completed(
+        &self,
+        meta: &RolloutMetadata,
+        locked_state: &LockedState,
+    ) -> Result<()> {
+        trace!(
+            "Marking state {} as completed...",
+            locked_state.state().state_type()
+        );
+        self.retrier
+            .call("mark_state_completed", Idempotency::Idempotent, || {
+                self.client.rollout_states_api().update_rollout_state(
+                    *meta.workspace_id(),
+                    *meta.application_id(),
+                    *meta.rollout_id(),
+                    *locked_state.state().id(),
+                    UpdateRolloutStateRequest {
+                        status: Some(Some(RolloutStateStatus::Done)),
+                    },
+                )
+            })
+            .await?;
+
+        trace!("State successfully marked as complete");
+        Ok(())
+    }
+
+    async fn upload_observations(
+        &self,
+        meta: &RolloutMetadata,
+        data: Vec<Measurement>,
+    ) -> Result<()> {
+        trace!("Uploading observations to backend");
+        let mut status_codes = Vec::new();
+        let mut latency = Vec::new();
+
+        for measurement in data {
+            let group = match measurement.group() {
+                Some(crate::stats::Group::Control) => ApplicationGroup::Baseline,
+                Some(crate::stats::Group::Experimental) => ApplicationGroup::Canary,
+                None => {
+                    trace!("Skipping gap in the data: {measurement:?}");
+                    continue;
+                }
+            };
+            // Attribute the observation to the end of the window it
+            // covers, rather than when it happens to be uploaded.
+            let created_at = measurement
+                .window()
+                .map_or_else(Utc::now, |window| window.end())
+                .to_rfc3339();
+            let integration = matches!(measurement, Measurement::IntegrationLatency(_));
+            let item = match measurement {
+                Measurement::StatusCode(item) => item,
+                // • The backend doesn't take latency yet, so it's
+                //   buffered with the batch's status codes.
+                Measurement::Latency(item) | Measurement::IntegrationLatency(item) => {
+                    latency.push(LatencyMetrics {
+                        app_group: group,
+                        integration,
+                        bucket_counts: LatencyBucket::all()
+                            .map(|bucket| item.get_count(&bucket))
+                            .collect(),
+                        created_at,
+                    });
+                    continue;
+                }
+                _ => {
+                    trace!("Skipping observation the backend doesn't accept: {measurement:?}");
+                    continue;
+                }
+            };
+            let metrics = StatusCodeMetrics {
+                app_group: group,
+                status_2xx_count: item.get_count(&ResponseStatusCode::_2XX) as u32,
+                status_4xx_count: item.get_count(&ResponseStatusCode::_4XX) as u32,
+                status_5xx_count: item.get_count(&ResponseStatusCode::_5XX) as u32,
+                created_at,
+            };
+
+            status_codes.push(metrics);
+        }
+
+        let workspace_id = *meta.workspace_id();
+        let application_id = *meta.application_id();
+        let rollout_id = *meta.rollout_id();
+
+        let mut slot = self.queue.lock().await;
+        let queue = open_queue(&mut slot, rollout_id)?;
+        // • Write the batch to disk before uploading it, so it isn't
+        //   lost if the backend is unreachable or the CLI exits.
+        let batch = PendingBatch {
+            status_codes,
+            latency,
+        };
+        if !(batch.status_codes.is_empty() && batch.latency.is_empty()) && queue.push(batch)? {
+            error!(
+                "Too many observations are waiting for the backend, so the oldest were set aside in {}",
+                queue.rejected_path().display()
+            );
+        }
+        // Retrying could record the same observations twice.
+        queue
+            .drain(|batch| {
+                let req_body = CreateResponseCodeMetricsRequest {
+                    status_codes: batch.status_codes.clone(),
+                };
+                let has_latency = !batch.latency.is_empty();
+                async move {
+                    if !req_body.status_codes.is_empty() {
+                        self.retrier
+                            .call("upload_observations", Idempotency::NonIdempotent, || {
+                                self.client
+                                    .response_code_metrics_api()
+                                    .create_response_code_metrics(
+                                        workspace_id,
+                                        application_id,
+                                        rollout_id,
+                                        req_body.clone(),
+                                    )
+                            })
+                            .await?;
+                    }
+                    // • The batch is done with once its status codes are
+                    //   uploaded, so its latency goes with it.
+                    if has_latency {
+                        LATENCY_DROPPED.call_once(|| {
+                            warn!(
+                                "The MultiTool backend doesn't accept latency yet, so latency observations aren't uploaded. Latency is only judged by local rollouts (--local)."
+                            )
+                        });
+                    }
+                    Ok(())
+                }
+            })
+            .await?;
+
+        trace!("Observations uploaded successfully");
+        Ok(())
+    }
+
+    async fn retire(&self, meta: &RolloutMetadata) -> Result<()> {
+        if !self
+            .record_outcome(meta, RolloutStateType::RollbackCanary)
+            .await?
+        {
+            warn!(
+                "The backend hasn't scheduled a rollback of rollout {}, so it may still look unfinished there",
+                meta.rollout_id()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Returns the observation queue for the rollout, opening it if
+/// it isn't open already. Batches left behind by a previous run
+/// of the rollout are uploaded first.
+fn open_queue(
+    slot: &mut Option<(RolloutId, ObservationQueue)>,
+    rollout_id: RolloutId,
+) -> Result<&mut ObservationQueue> {
+    let (_, queue) = match slot.take() {
+        Some((id, queue)) if id == rollout_id => slot.insert((id, queue)),
+        _ => {
+            let fs = FileSystem::new()?;
+            let queue = ObservationQueue::open(
+                ObservationQueueFile(rollout_id).path(&fs)?,
+                RejectedObservationsFile(rollout_id).path(&fs)?,
+            )?;
+            if !queue.is_empty() {
+                info!(
+                    "Found {} batches of observations buffered by a previous run.",
+                    queue.len()
+                );
+            }
+            slot.insert((rollout_id, queue))
+        }
+    };
+    Ok(queue)
 }
 
 /// A parsed and configured set of adapters for interacting
@@ -356,7 +656,6 @@ pub struct ApplicationConfig {
 
 #[derive(Clone)]
 pub(super) struct BackendConfig {
-    // TODO: Add configuration for a timeout.
     // TODO: Add a way to update the access token.
     conf: Configuration,
 }
@@ -369,10 +668,17 @@ impl BackendConfig {
         let jwt = session.and_then(|session| match session {
             Session::User(creds) => Some(creds.jwt),
         });
+        // • Bound each request, so a hung connection is retried
+        //   instead of stalling the rollout.
+        let client = reqwest::Client::builder()
+            .timeout(DEFAULT_REQUEST_TIMEOUT)
+            .build()
+            .expect("Failed to build the HTTP client.");
         let conf = Configuration {
             base_path: origin.unwrap_or("https://api.multitool.run".to_string()),
             user_agent: Some(USER_AGENT.to_owned()),
             bearer_access_token: jwt,
+            client,
             ..Configuration::default()
         };
         Self { conf }
@@ -0,0 +1,363 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use miette::{IntoDiagnostic, Result};
+use multitool_sdk::models::{ApplicationGroup, StatusCodeMetrics};
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use super::retry::BackendError;
+
+/// The most batches we keep waiting for the backend. Beyond this,
+/// the oldest batch is set aside to make room, which keeps a long
+/// outage from growing the queue without bound.
+const MAX_PENDING_BATCHES: usize = 1 << 10;
+
+/// A batch of observations the backend hasn't acknowledged yet.
+/// Each observation keeps the timestamp it was recorded with,
+/// no matter how long it waits to be uploaded.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct PendingBatch {
+    pub(crate) status_codes: Vec<StatusCodeMetrics>,
+    /// Batches written before latency was kept don't have any.
+    #[serde(default)]
+    pub(crate) latency: Vec<LatencyMetrics>,
+}
+
+/// A latency histogram, recorded like the backend's status code
+/// metrics. The backend doesn't take latency yet, so it's kept with
+/// the batch it was observed in until the batch is uploaded.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct LatencyMetrics {
+    pub(crate) app_group: ApplicationGroup,
+    /// Whether only the time spent in the backing service was measured.
+    pub(crate) integration: bool,
+    /// The number of requests in each latency bucket, fastest first.
+    pub(crate) bucket_counts: Vec<u32>,
+    pub(crate) created_at: String,
+}
+
+/// The [ObservationQueue] is a write-ahead queue for observations.
+/// Every batch is written to disk before we attempt to upload it,
+/// and only removed once the backend acknowledges it, so batches
+/// are uploaded in order, at least once, even across restarts.
+///
+/// Batches that can't be uploaded, because the backend rejected them or
+/// the queue overflowed, are set aside in a separate file rather than
+/// dropped. Each is appended to it as a line of JSON, so they can be
+/// inspected or replayed by hand.
+pub(super) struct ObservationQueue {
+    path: PathBuf,
+    rejected_path: PathBuf,
+    batches: VecDeque<PendingBatch>,
+    capacity: usize,
+}
+
+impl ObservationQueue {
+    /// Open the queue stored at the given path, picking up any
+    /// batches left behind by a previous run.
+    pub(super) fn open(path: PathBuf, rejected_path: PathBuf) -> Result<Self> {
+        let batches = match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents).into_diagnostic()?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => VecDeque::new(),
+            Err(err) => return Err(err).into_diagnostic(),
+        };
+        Ok(Self {
+            path,
+            rejected_path,
+            batches,
+            capacity: MAX_PENDING_BATCHES,
+        })
+    }
+
+    /// Add a batch to the back of the queue. If the queue is full, the
+    /// oldest batch is set aside to make room. Returns whether it was.
+    pub(super) fn push(&mut self, batch: PendingBatch) -> Result<bool> {
+        let overflowed = self.batches.len() >= self.capacity;
+        if overflowed && let Some(oldest) = self.batches.pop_front() {
+            self.set_aside(&oldest)?;
+        }
+        self.batches.push_back(batch);
+        self.persist()?;
+        Ok(overflowed)
+    }
+
+    /// Remove the oldest batch and set it aside, because
+    /// the backend will never accept it.
+    pub(super) fn reject(&mut self) -> Result<()> {
+        if let Some(batch) = self.batches.pop_front() {
+            self.set_aside(&batch)?;
+        }
+        self.persist()
+    }
+
+    /// The file batches are set aside in.
+    pub(super) fn rejected_path(&self) -> &Path {
+        &self.rejected_path
+    }
+
+    /// Returns the oldest batch.
+    pub(super) fn front(&self) -> Option<&PendingBatch> {
+        self.batches.front()
+    }
+
+    /// Remove the oldest batch, once the backend has acknowledged it.
+    pub(super) fn pop(&mut self) -> Result<()> {
+        self.batches.pop_front();
+        self.persist()
+    }
+
+    /// Upload the buffered batches, oldest first. We stop at the first
+    /// failure that might succeed later, so the backend receives them in
+    /// order. Batches the backend rejects are set aside, so they don't
+    /// hold up the ones behind them.
+    pub(super) async fn drain<F, Fut>(&mut self, mut upload: F) -> Result<()>
+    where
+        F: FnMut(&PendingBatch) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        while let Some(batch) = self.front() {
+            let Err(err) = upload(batch).await else {
+                self.pop()?;
+                continue;
+            };
+            match err.downcast_ref::<BackendError>() {
+                Some(BackendError::Rejected { .. }) => {
+                    error!(
+                        "Observations were set aside in {}: {err}",
+                        self.rejected_path.display()
+                    );
+                    self.reject()?;
+                }
+                // • Nothing will be accepted until the user logs in
+                //   again, so the batches stay buffered for a later run.
+                Some(BackendError::Unauthorized { .. }) => return Err(err),
+                _ => {
+                    warn!(
+                        "Failed to upload observations. {} batches are buffered until the backend recovers: {err}",
+                        self.len()
+                    );
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.batches.len()
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    fn set_aside(&self, batch: &PendingBatch) -> Result<()> {
+        let mut line = serde_json::to_vec(batch).into_diagnostic()?;
+        line.push(b'\n');
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.rejected_path)
+            .into_diagnostic()?;
+        file.write_all(&line).into_diagnostic()?;
+        file.sync_all().into_diagnostic()
+    }
+
+    /// Write the queue to disk. We write to a temporary file and
+    /// rename it over the queue, so a crash mid-write can't
+    /// corrupt the batches we've already saved.
+    fn persist(&self) -> Result<()> {
+        if self.batches.is_empty() {
+            return match std::fs::remove_file(&self.path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    Err(err).into_diagnostic()
+                }
+                _ => Ok(()),
+            };
+        }
+        let marshalled = serde_json::to_vec(&self.batches).into_diagnostic()?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        let mut file = std::fs::File::create(&tmp_path).into_diagnostic()?;
+        file.write_all(&marshalled).into_diagnostic()?;
+        file.sync_all().into_diagnostic()?;
+        std::fs::rename(&tmp_path, &self.path).into_diagnostic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miette::Result;
+    use multitool_sdk::models::{ApplicationGroup, StatusCodeMetrics};
+    use pretty_assertions::assert_eq;
+
+    use super::{BackendError, LatencyMetrics, ObservationQueue, PendingBatch};
+
+    fn batch(status_2xx_count: u32) -> PendingBatch {
+        PendingBatch {
+            status_codes: vec![StatusCodeMetrics {
+                app_group: ApplicationGroup::Canary,
+                status_2xx_count,
+                status_4xx_count: 0,
+                status_5xx_count: 0,
+                created_at: "2025-01-01T00:00:00+00:00".to_owned(),
+            }],
+            latency: Vec::new(),
+        }
+    }
+
+    fn temp_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("multi-observations-{}.json", uuid::Uuid::new_v4()))
+    }
+
+    fn open(path: &std::path::Path) -> ObservationQueue {
+        ObservationQueue::open(path.to_owned(), path.with_extension("ndjson")).unwrap()
+    }
+
+    /// Read the batches that were set aside.
+    fn rejected(queue: &ObservationQueue) -> Vec<PendingBatch> {
+        std::fs::read_to_string(queue.rejected_path())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn batches_survive_reopening() {
+        let path = temp_path();
+        let mut queue = open(&path);
+        assert!(queue.is_empty());
+        queue.push(batch(1)).unwrap();
+        queue.push(batch(2)).unwrap();
+        queue.push(batch(3)).unwrap();
+        queue.pop().unwrap();
+        drop(queue);
+
+        // The unacknowledged batches are replayed in order.
+        let mut queue = open(&path);
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.front(), Some(&batch(2)));
+        queue.pop().unwrap();
+        assert_eq!(queue.front(), Some(&batch(3)));
+        queue.pop().unwrap();
+
+        // Once the queue drains, the file is removed.
+        assert!(!path.exists());
+    }
+
+    /// Rejected batches, and batches that don't fit, are set
+    /// aside instead of holding up the rest of the queue.
+    /// Latency is buffered with its batch, and batches buffered
+    /// before latency was kept can still be read.
+    #[test]
+    fn latency_is_buffered() {
+        let path = temp_path();
+        let mut queue = open(&path);
+        let mut with_latency = batch(1);
+        with_latency.latency.push(LatencyMetrics {
+            app_group: ApplicationGroup::Baseline,
+            integration: false,
+            bucket_counts: vec![3, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+            created_at: "2025-01-01T00:00:00+00:00".to_owned(),
+        });
+        queue.push(with_latency.clone()).unwrap();
+        drop(queue);
+        assert_eq!(open(&path).front(), Some(&with_latency));
+
+        let old = serde_json::json!([{ "status_codes": [] }]);
+        std::fs::write(&path, old.to_string()).unwrap();
+        let queue = open(&path);
+        assert_eq!(queue.front().map(|batch| batch.latency.len()), Some(0));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn batches_are_set_aside() {
+        let path = temp_path();
+        let mut queue = open(&path);
+        queue.capacity = 2;
+        assert!(!queue.push(batch(1)).unwrap());
+        assert!(!queue.push(batch(2)).unwrap());
+        assert!(queue.push(batch(3)).unwrap());
+        assert_eq!(queue.len(), 2);
+        queue.reject().unwrap();
+        assert_eq!(queue.front(), Some(&batch(3)));
+        assert_eq!(rejected(&queue), vec![batch(1), batch(2)]);
+
+        queue.pop().unwrap();
+        std::fs::remove_file(queue.rejected_path()).unwrap();
+    }
+
+    fn rejection(call: &'static str) -> BackendError {
+        BackendError::Rejected {
+            call,
+            source: "422 Unprocessable Entity".into(),
+        }
+    }
+
+    /// An expired session stops the upload without losing anything.
+    #[tokio::test]
+    async fn unauthorized_uploads_keep_the_queue() {
+        let path = temp_path();
+        let mut queue = open(&path);
+        queue.push(batch(1)).unwrap();
+        queue.push(batch(2)).unwrap();
+        let err = queue
+            .drain(|_| async {
+                Err(BackendError::Unauthorized {
+                    call: "test",
+                    source: "401 Unauthorized".into(),
+                }
+                .into())
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<BackendError>(),
+            Some(BackendError::Unauthorized { .. })
+        ));
+        assert_eq!(queue.len(), 2);
+        assert!(!queue.rejected_path().exists());
+
+        // Any other failure also leaves the queue for later.
+        queue
+            .drain(|_| async { Err(miette::miette!("404 Not Found")) })
+            .await
+            .unwrap();
+        assert_eq!(queue.len(), 2);
+        drop(queue);
+        assert_eq!(open(&path).len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Rejected batches are set aside, and the rest are uploaded.
+    #[tokio::test]
+    async fn rejected_uploads_are_set_aside() {
+        let path = temp_path();
+        let mut queue = open(&path);
+        queue.push(batch(1)).unwrap();
+        queue.push(batch(2)).unwrap();
+        let mut uploaded = Vec::new();
+        queue
+            .drain(|pending| {
+                let result: Result<()> = if *pending == batch(1) {
+                    Err(rejection("test").into())
+                } else {
+                    uploaded.push(pending.clone());
+                    Ok(())
+                };
+                async { result }
+            })
+            .await
+            .unwrap();
+        assert!(queue.is_empty());
+        assert_eq!(uploaded, vec![batch(2)]);
+        assert_eq!(rejected(&queue), vec![batch(1)]);
+        std::fs::remove_file(queue.rejected_path()).unwrap();
+    }
+}
@@ -0,0 +1,488 @@
+use std::{
+    error::Error,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use miette::{Diagnostic, IntoDiagnostic, Result};
+use multitool_sdk::apis::Error as ApiError;
+use rand::Rng as _;
+use tokio::time::{Instant, sleep, timeout};
+use tracing::warn;
+
+/// How many times a call is attempted before we give up on it.
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+/// How long we wait before the first retry. Each subsequent retry
+/// waits up to twice as long as the one before it.
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// The longest we'll wait between two attempts.
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(10);
+/// How long a call may take, including every retry.
+const DEFAULT_DEADLINE: Duration = Duration::from_secs(60);
+/// How many attempts in a row can fail before we stop sending
+/// requests to the backend for a while.
+const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+/// How long we stop sending requests for once the circuit opens.
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Whether making a call twice has the same effect as making it once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Idempotency {
+    Idempotent,
+    NonIdempotent,
+}
+
+/// Describes whether a failed attempt can be retried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Retry {
+    /// The backend never processed the request, e.g. because
+    /// we couldn't connect or it was rate limited.
+    Always,
+    /// The backend may have processed the request before failing,
+    /// so it's only safe to retry idempotent calls.
+    IfIdempotent,
+    /// The backend found the request invalid. Retrying won't help,
+    /// and neither will sending it again later.
+    Reject,
+    /// We aren't allowed to make the request, e.g. because the session
+    /// expired. Nothing will succeed until the user logs in again.
+    Unauthorized,
+    /// Retrying right away won't help, but the request might succeed
+    /// later, e.g. after a 404 while the backend is being deployed, or
+    /// a response we couldn't decode.
+    Never,
+}
+
+/// Errors that know whether the attempt that produced them can be retried.
+pub(super) trait Retryable {
+    fn retry(&self) -> Retry;
+}
+
+impl<T> Retryable for ApiError<T> {
+    fn retry(&self) -> Retry {
+        match self {
+            ApiError::ResponseError(response) => match response.status.as_u16() {
+                429 => Retry::Always,
+                500 | 502 | 503 | 504 => Retry::IfIdempotent,
+                400 | 409 | 422 => Retry::Reject,
+                401 | 403 => Retry::Unauthorized,
+                _ => Retry::Never,
+            },
+            ApiError::Reqwest(err) if err.is_connect() => Retry::Always,
+            ApiError::Reqwest(err) if err.is_timeout() || err.is_request() => Retry::IfIdempotent,
+            _ => Retry::Never,
+        }
+    }
+}
+
+/// The ways a call can fail without the backend responding.
+#[derive(thiserror::Error, Debug, Diagnostic)]
+pub(crate) enum BackendError {
+    #[error("Gave up on {call}: the MultiTool backend failed too many requests in a row")]
+    #[diagnostic(help("Check your network connection, then try again."))]
+    CircuitOpen { call: &'static str },
+    #[error("Gave up on {call}: it didn't complete within {deadline:?}")]
+    DeadlineExceeded {
+        call: &'static str,
+        deadline: Duration,
+    },
+    #[error("The MultiTool backend rejected {call}: {source}")]
+    Rejected {
+        call: &'static str,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    #[error("The MultiTool backend refused {call}: {source}")]
+    #[diagnostic(help("Your session may have expired. Run `multi login`, then try again."))]
+    Unauthorized {
+        call: &'static str,
+        source: Box<dyn Error + Send + Sync>,
+    },
+}
+
+/// [RetryPolicy] describes how often, and for how long, a call is retried.
+#[derive(Clone, Debug)]
+pub(super) struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    deadline: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            deadline: DEFAULT_DEADLINE,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait before the given retry, counting from zero.
+    /// The delay grows exponentially, and is jittered so that tasks
+    /// retrying at the same time don't all hit the backend at once.
+    fn delay(&self, retry: u32) -> Duration {
+        let ceiling = self
+            .initial_backoff
+            .saturating_mul(1 << retry.min(16))
+            .min(self.max_backoff);
+        let half = ceiling / 2;
+        half + rand::rng().random_range(Duration::ZERO..=half)
+    }
+}
+
+/// The [CircuitBreaker] stops us from sending requests to a backend
+/// that has failed several in a row, giving it time to recover. Once
+/// the cooldown elapses, a single failure reopens the circuit.
+struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+    threshold: u32,
+    cooldown: Duration,
+}
+
+enum BreakerState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+}
+
+impl CircuitBreaker {
+    fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            state: Mutex::new(BreakerState::Closed { failures: 0 }),
+            threshold: threshold.max(1),
+            cooldown,
+        }
+    }
+
+    /// Returns how long we must wait before making an attempt.
+    fn wait(&self, now: Instant) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            BreakerState::Open { until } if now < until => Some(until - now),
+            BreakerState::Open { .. } => {
+                *state = BreakerState::Closed {
+                    failures: self.threshold - 1,
+                };
+                None
+            }
+            BreakerState::Closed { .. } => None,
+        }
+    }
+
+    fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::Closed { failures: 0 };
+    }
+
+    fn record_failure(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        let failures = match *state {
+            BreakerState::Closed { failures } => failures + 1,
+            // Attempts that started before the circuit opened
+            // don't extend the cooldown.
+            BreakerState::Open { .. } => return,
+        };
+        *state = if failures >= self.threshold {
+            warn!(
+                "The MultiTool backend failed {failures} requests in a row. Pausing requests for {:?}.",
+                self.cooldown
+            );
+            BreakerState::Open {
+                until: now + self.cooldown,
+            }
+        } else {
+            BreakerState::Closed { failures }
+        };
+    }
+}
+
+/// The [Retrier] applies the retry policy to calls to the backend.
+/// Clones share the circuit breaker, since they share the backend.
+#[derive(Clone)]
+pub(super) struct Retrier {
+    policy: RetryPolicy,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl Default for Retrier {
+    fn default() -> Self {
+        Self::new(RetryPolicy::default())
+    }
+}
+
+impl Retrier {
+    fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            breaker: Arc::new(CircuitBreaker::new(
+                DEFAULT_FAILURE_THRESHOLD,
+                DEFAULT_COOLDOWN,
+            )),
+        }
+    }
+
+    /// Make the call described by `attempt`, retrying it until it
+    /// succeeds, fails in a way retrying won't fix, or runs out of
+    /// attempts or time.
+    pub(super) async fn call<T, E, F, Fut>(
+        &self,
+        call: &'static str,
+        idempotency: Idempotency,
+        mut attempt: F,
+    ) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Retryable + Error + Send + Sync + 'static,
+    {
+        let deadline = Instant::now() + self.policy.deadline;
+        let mut attempts = 0;
+        loop {
+            // • Don't bother the backend while the circuit is open,
+            //   unless it closes before our deadline.
+            if let Some(wait) = self.breaker.wait(Instant::now()) {
+                if Instant::now() + wait >= deadline {
+                    return Err(BackendError::CircuitOpen { call }.into());
+                }
+                sleep(wait).await;
+                continue;
+            }
+            attempts += 1;
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Ok(result) = timeout(remaining, attempt()).await else {
+                self.breaker.record_failure(Instant::now());
+                return Err(BackendError::DeadlineExceeded {
+                    call,
+                    deadline: self.policy.deadline,
+                }
+                .into());
+            };
+            let err = match result {
+                Ok(value) => {
+                    self.breaker.record_success();
+                    return Ok(value);
+                }
+                Err(err) => err,
+            };
+            let retry = err.retry();
+            // • Only the backend's failures count towards opening the
+            //   circuit. When it answers that we're at fault, the
+            //   backend is evidently up.
+            let retryable = match retry {
+                Retry::Always => true,
+                Retry::IfIdempotent => idempotency == Idempotency::Idempotent,
+                Retry::Reject | Retry::Unauthorized | Retry::Never => false,
+            };
+            if matches!(retry, Retry::Always | Retry::IfIdempotent) {
+                self.breaker.record_failure(Instant::now());
+            } else {
+                self.breaker.record_success();
+            }
+            match retry {
+                Retry::Reject => {
+                    return Err(BackendError::Rejected {
+                        call,
+                        source: Box::new(err),
+                    }
+                    .into());
+                }
+                Retry::Unauthorized => {
+                    return Err(BackendError::Unauthorized {
+                        call,
+                        source: Box::new(err),
+                    }
+                    .into());
+                }
+                Retry::Always | Retry::IfIdempotent | Retry::Never => (),
+            }
+            let delay = self.policy.delay(attempts - 1);
+            if !retryable
+                || attempts >= self.policy.max_attempts
+                || Instant::now() + delay >= deadline
+            {
+                return Err(err).into_diagnostic();
+            }
+            warn!("Retrying {call} in {delay:?} after it failed: {err}");
+            sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Duration,
+    };
+
+    use multitool_sdk::apis::{Error as ApiError, ResponseContent};
+    use pretty_assertions::assert_eq;
+    use reqwest::StatusCode;
+    use tokio::time::Instant;
+
+    use super::{
+        BackendError, CircuitBreaker, Idempotency, Retrier, Retry, RetryPolicy, Retryable,
+    };
+
+    #[derive(thiserror::Error, Debug)]
+    #[error("{0:?}")]
+    struct TestError(Retry);
+
+    impl Retryable for TestError {
+        fn retry(&self) -> Retry {
+            self.0
+        }
+    }
+
+    fn retrier() -> Retrier {
+        Retrier::new(RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+            deadline: Duration::from_secs(5),
+        })
+    }
+
+    /// Make a call that fails with the given error every time,
+    /// returning how many attempts were made.
+    async fn attempts(retrier: &Retrier, retry: Retry, idempotency: Idempotency) -> u32 {
+        let attempts = AtomicU32::new(0);
+        let result: miette::Result<()> = retrier
+            .call("test", idempotency, || {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                async move { Err(TestError(retry)) }
+            })
+            .await;
+        assert!(result.is_err());
+        attempts.into_inner()
+    }
+
+    #[test]
+    fn delays_are_jittered_and_bounded() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(10),
+            ..Default::default()
+        };
+        for retry in 0..10 {
+            let ceiling = Duration::from_secs(2 << retry.min(3)).min(Duration::from_secs(10));
+            let delay = policy.delay(retry);
+            assert!(ceiling / 2 <= delay && delay <= ceiling, "{delay:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_depend_on_idempotency() {
+        let test_cases = [
+            (Retry::Always, Idempotency::NonIdempotent, 3),
+            (Retry::IfIdempotent, Idempotency::Idempotent, 3),
+            (Retry::IfIdempotent, Idempotency::NonIdempotent, 1),
+            (Retry::Never, Idempotency::Idempotent, 1),
+        ];
+        for (retry, idempotency, expected) in test_cases {
+            let observed = attempts(&retrier(), retry, idempotency).await;
+            assert_eq!(observed, expected, "{retry:?} {idempotency:?}");
+        }
+    }
+
+    /// Callers can tell requests the backend will never accept
+    /// from ones that might succeed later.
+    #[tokio::test]
+    async fn rejections_are_distinguished() {
+        let rejected = |retry| async move {
+            let err = retrier()
+                .call("test", Idempotency::Idempotent, || async move {
+                    Err::<(), _>(TestError(retry))
+                })
+                .await
+                .unwrap_err();
+            matches!(
+                err.downcast_ref::<BackendError>(),
+                Some(BackendError::Rejected { .. })
+            )
+        };
+        assert!(rejected(Retry::Reject).await);
+        assert!(!rejected(Retry::Unauthorized).await);
+        assert!(!rejected(Retry::Never).await);
+        assert!(!rejected(Retry::Always).await);
+    }
+
+    /// Only validation failures are rejections. An expired session or
+    /// a backend that's being deployed may accept the request later.
+    #[test]
+    fn classify_response_statuses() {
+        let test_cases = [
+            (StatusCode::BAD_REQUEST, Retry::Reject),
+            (StatusCode::CONFLICT, Retry::Reject),
+            (StatusCode::UNPROCESSABLE_ENTITY, Retry::Reject),
+            (StatusCode::UNAUTHORIZED, Retry::Unauthorized),
+            (StatusCode::FORBIDDEN, Retry::Unauthorized),
+            (StatusCode::NOT_FOUND, Retry::Never),
+            (StatusCode::TOO_MANY_REQUESTS, Retry::Always),
+            (StatusCode::SERVICE_UNAVAILABLE, Retry::IfIdempotent),
+        ];
+        for (status, expected) in test_cases {
+            let err = ApiError::<()>::ResponseError(ResponseContent {
+                status,
+                content: String::new(),
+                entity: None,
+            });
+            assert_eq!(err.retry(), expected, "{status}");
+        }
+    }
+
+    #[tokio::test]
+    async fn recovers_from_transient_failures() {
+        let attempts = AtomicU32::new(0);
+        let result = retrier()
+            .call("test", Idempotency::Idempotent, || {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if attempt == 0 {
+                        Err(TestError(Retry::IfIdempotent))
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn open_circuit_fails_fast() {
+        let retrier = retrier();
+        // Each call fails three times, and five failures open the circuit.
+        attempts(&retrier, Retry::Always, Idempotency::Idempotent).await;
+        attempts(&retrier, Retry::Always, Idempotency::Idempotent).await;
+        let attempts = attempts(&retrier, Retry::Always, Idempotency::Idempotent).await;
+        assert_eq!(attempts, 0);
+        let err = retrier
+            .call("test", Idempotency::Idempotent, || async {
+                Ok::<_, TestError>(())
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<BackendError>(),
+            Some(BackendError::CircuitOpen { .. })
+        ));
+    }
+
+    #[test]
+    fn circuit_closes_after_cooldown() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(30));
+        let now = Instant::now();
+        breaker.record_failure(now);
+        assert_eq!(breaker.wait(now), None);
+        breaker.record_failure(now);
+        assert_eq!(breaker.wait(now), Some(Duration::from_secs(30)));
+        let later = now + Duration::from_secs(31);
+        assert_eq!(breaker.wait(later), None);
+        // A single failure reopens the circuit.
+        breaker.record_failure(later);
+        assert!(breaker.wait(later).is_some());
+    }
+}
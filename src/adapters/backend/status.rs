@@ -0,0 +1,335 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use multitool_sdk::models::{
+    ApplicationGroup, RolloutState, RolloutStateData, RolloutStateStatus, RolloutStateType,
+    StatusCodeMetrics,
+};
+use serde::Serialize;
+
+use super::{RolloutId, StateId};
+
+/// A [RolloutStatus] is a snapshot of a rollout, summarizing the states
+/// it's been through and the latest observations of each group.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub(crate) struct RolloutStatus {
+    rollout_id: RolloutId,
+    /// The percentage of traffic the canary receives, according to the
+    /// last state that was effected. `None` until the canary is deployed.
+    canary_traffic: Option<i64>,
+    /// The state that's locked by a runner, if any. The backend doesn't
+    /// say which runner holds the lock, only that the state is in progress.
+    locked_state: Option<StateId>,
+    /// The most recent observation of the baseline.
+    baseline: Option<ObservationCounts>,
+    /// The most recent observation of the canary.
+    canary: Option<ObservationCounts>,
+    /// Every state of the rollout, oldest first.
+    history: Vec<StateSummary>,
+}
+
+/// One entry in the rollout's history.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub(crate) struct StateSummary {
+    id: StateId,
+    state_type: RolloutStateType,
+    status: RolloutStateStatus,
+    /// Only present for `SetCanaryTraffic` states.
+    percent_traffic: Option<i64>,
+}
+
+/// The response codes counted in a single observation.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub(crate) struct ObservationCounts {
+    status_2xx: u32,
+    status_4xx: u32,
+    status_5xx: u32,
+    recorded_at: String,
+}
+
+impl RolloutStatus {
+    pub(crate) fn new(
+        rollout_id: RolloutId,
+        states: Vec<RolloutState>,
+        observations: Vec<StatusCodeMetrics>,
+    ) -> Self {
+        let mut history: Vec<_> = states.iter().map(StateSummary::from).collect();
+        history.sort_by_key(|state| state.id);
+
+        // • The canary's traffic is set by the last state that was effected.
+        let canary_traffic = history
+            .iter()
+            .filter(|state| state.status == RolloutStateStatus::Done)
+            .filter_map(|state| match state.state_type {
+                RolloutStateType::DeployCanary | RolloutStateType::RollbackCanary => Some(0),
+                RolloutStateType::SetCanaryTraffic => state.percent_traffic,
+                RolloutStateType::PromoteCanary => Some(100),
+            })
+            .next_back();
+        let locked_state = history
+            .iter()
+            .find(|state| state.status == RolloutStateStatus::InProgress)
+            .map(|state| state.id);
+
+        // • Keep the most recent observation of each group.
+        let mut baseline: Option<(Option<DateTime<Utc>>, ObservationCounts)> = None;
+        let mut canary: Option<(Option<DateTime<Utc>>, ObservationCounts)> = None;
+        for metrics in observations {
+            let recorded_at = DateTime::parse_from_rfc3339(&metrics.created_at)
+                .ok()
+                .map(|time| time.to_utc());
+            let slot = match metrics.app_group {
+                ApplicationGroup::Baseline => &mut baseline,
+                ApplicationGroup::Canary => &mut canary,
+            };
+            if slot
+                .as_ref()
+                .is_none_or(|(latest, _)| recorded_at >= *latest)
+            {
+                *slot = Some((recorded_at, ObservationCounts::from(metrics)));
+            }
+        }
+
+        Self {
+            rollout_id,
+            canary_traffic,
+            locked_state,
+            baseline: baseline.map(|(_, counts)| counts),
+            canary: canary.map(|(_, counts)| counts),
+            history,
+        }
+    }
+}
+
+/// Whether the rollout still has work to do. It's in flight while some of
+/// its states haven't been effected, unless the canary was already promoted
+/// or rolled back.
+pub(crate) fn is_in_flight(states: &[RolloutState]) -> bool {
+    let settled = states.iter().any(|state| {
+        state.status == RolloutStateStatus::Done
+            && matches!(
+                state.state_type,
+                RolloutStateType::PromoteCanary | RolloutStateType::RollbackCanary
+            )
+    });
+    let unfinished = states
+        .iter()
+        .any(|state| state.status != RolloutStateStatus::Done);
+    unfinished && !settled
+}
+
+/// The outstanding state that settles the rollout with the given
+/// outcome, if the backend has scheduled one.
+pub(crate) fn outcome_state(states: &[RolloutState], outcome: RolloutStateType) -> Option<StateId> {
+    states
+        .iter()
+        .find(|state| state.state_type == outcome && state.status != RolloutStateStatus::Done)
+        .map(|state| state.id)
+}
+
+/// Whether the rollout's canary was deployed, so there's
+/// a canary somewhere to reattach to.
+pub(crate) fn canary_deployed(states: &[RolloutState]) -> bool {
+    states.iter().any(|state| {
+        state.state_type == RolloutStateType::DeployCanary
+            && state.status == RolloutStateStatus::Done
+    })
+}
+
+impl From<&RolloutState> for StateSummary {
+    fn from(state: &RolloutState) -> Self {
+        let percent_traffic = match state.state_type {
+            RolloutStateType::SetCanaryTraffic => state.data.clone().flatten().map(|data| {
+                let RolloutStateData::RolloutStateDataOneOf(data) = *data;
+                i64::from(data.set_canary_traffic.percent_traffic)
+            }),
+            _ => None,
+        };
+        Self {
+            id: state.id,
+            state_type: state.state_type,
+            status: state.status,
+            percent_traffic,
+        }
+    }
+}
+
+impl From<StatusCodeMetrics> for ObservationCounts {
+    fn from(metrics: StatusCodeMetrics) -> Self {
+        Self {
+            status_2xx: metrics.status_2xx_count,
+            status_4xx: metrics.status_4xx_count,
+            status_5xx: metrics.status_5xx_count,
+            recorded_at: metrics.created_at,
+        }
+    }
+}
+
+impl fmt::Display for RolloutStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Rollout {}", self.rollout_id)?;
+        match self.canary_traffic {
+            Some(percent) => writeln!(f, "  Canary traffic: {percent}%")?,
+            None => writeln!(f, "  Canary traffic: not deployed")?,
+        }
+        match self.locked_state {
+            Some(id) => writeln!(f, "  Locked state:   {id}")?,
+            None => writeln!(f, "  Locked state:   none")?,
+        }
+        for (name, counts) in [("Baseline", &self.baseline), ("Canary", &self.canary)] {
+            match counts {
+                Some(counts) => writeln!(f, "  {name:<8}        {counts}")?,
+                None => writeln!(f, "  {name:<8}        no observations")?,
+            }
+        }
+        writeln!(f, "  History:")?;
+        for state in &self.history {
+            write!(
+                f,
+                "    {:>6}  {:<20} {}",
+                state.id,
+                state.state_type.to_string(),
+                state.status
+            )?;
+            if let Some(percent) = state.percent_traffic {
+                write!(f, " ({percent}%)")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ObservationCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "2XX: {}, 4XX: {}, 5XX: {} (at {})",
+            self.status_2xx, self.status_4xx, self.status_5xx, self.recorded_at
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use multitool_sdk::models::{
+        ApplicationGroup, RolloutState, RolloutStateData, RolloutStateDataOneOf,
+        RolloutStateStatus, RolloutStateType, SetCanaryTrafficData, StatusCodeMetrics,
+    };
+    use pretty_assertions::assert_eq;
+
+    use super::{RolloutStatus, is_in_flight, outcome_state};
+
+    fn state(id: u64, state_type: RolloutStateType, status: RolloutStateStatus) -> RolloutState {
+        RolloutState {
+            id,
+            state_type,
+            status,
+            ..Default::default()
+        }
+    }
+
+    fn traffic(id: u64, percent: i32, status: RolloutStateStatus) -> RolloutState {
+        let data = RolloutStateData::RolloutStateDataOneOf(Box::new(RolloutStateDataOneOf {
+            set_canary_traffic: Box::new(SetCanaryTrafficData {
+                percent_traffic: percent,
+            }),
+        }));
+        RolloutState {
+            data: Some(Some(Box::new(data))),
+            ..state(id, RolloutStateType::SetCanaryTraffic, status)
+        }
+    }
+
+    fn metrics(app_group: ApplicationGroup, status_2xx_count: u32, at: &str) -> StatusCodeMetrics {
+        StatusCodeMetrics {
+            app_group,
+            status_2xx_count,
+            status_4xx_count: 0,
+            status_5xx_count: 0,
+            created_at: at.to_owned(),
+        }
+    }
+
+    #[test]
+    fn summarize_rollout() {
+        let states = vec![
+            traffic(3, 50, RolloutStateStatus::InProgress),
+            state(1, RolloutStateType::DeployCanary, RolloutStateStatus::Done),
+            traffic(2, 25, RolloutStateStatus::Done),
+        ];
+        let observations = vec![
+            metrics(ApplicationGroup::Canary, 7, "2025-01-01T00:01:00Z"),
+            metrics(ApplicationGroup::Baseline, 10, "2025-01-01T00:00:00Z"),
+            metrics(ApplicationGroup::Canary, 3, "2025-01-01T00:00:00Z"),
+        ];
+        let status = RolloutStatus::new(9, states, observations);
+        // The traffic comes from the last state that was effected,
+        // not the one that's still in progress.
+        assert_eq!(status.canary_traffic, Some(25));
+        assert_eq!(status.locked_state, Some(3));
+        let ids: Vec<_> = status.history.iter().map(|state| state.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(status.canary.map(|counts| counts.status_2xx), Some(7));
+        assert_eq!(status.baseline.map(|counts| counts.status_2xx), Some(10));
+    }
+
+    #[test]
+    fn summarize_settled_rollout() {
+        let states = vec![
+            state(1, RolloutStateType::DeployCanary, RolloutStateStatus::Done),
+            traffic(2, 25, RolloutStateStatus::Done),
+            state(3, RolloutStateType::PromoteCanary, RolloutStateStatus::Done),
+        ];
+        let status = RolloutStatus::new(9, states, Vec::new());
+        assert_eq!(status.canary_traffic, Some(100));
+        assert_eq!(status.locked_state, None);
+        assert_eq!(status.baseline, None);
+
+        let status = RolloutStatus::new(9, Vec::new(), Vec::new());
+        assert_eq!(status.canary_traffic, None);
+    }
+
+    #[test]
+    fn rollouts_in_flight() {
+        use RolloutStateStatus::{Done, InProgress, Pending};
+        use RolloutStateType::{DeployCanary, PromoteCanary, RollbackCanary};
+        // A state is waiting to be effected.
+        assert!(is_in_flight(&[
+            state(1, DeployCanary, Done),
+            traffic(2, 25, Pending),
+        ]));
+        assert!(is_in_flight(&[state(1, DeployCanary, InProgress)]));
+        // Every state was effected.
+        assert!(!is_in_flight(&[
+            state(1, DeployCanary, Done),
+            traffic(2, 25, Done),
+        ]));
+        assert!(!is_in_flight(&[]));
+        // The canary was settled, even if a state was left behind.
+        assert!(!is_in_flight(&[
+            state(1, DeployCanary, Done),
+            state(2, RollbackCanary, Done),
+            traffic(3, 50, Pending),
+        ]));
+        assert!(!is_in_flight(&[
+            state(1, DeployCanary, Done),
+            state(2, PromoteCanary, Done),
+        ]));
+    }
+
+    /// Only an outstanding state of the outcome's type can record it.
+    #[test]
+    fn find_the_outcome_state() {
+        use RolloutStateStatus::*;
+        use RolloutStateType::*;
+        let states = [
+            state(1, DeployCanary, Done),
+            traffic(2, 50, Pending),
+            state(3, RollbackCanary, Pending),
+        ];
+        assert_eq!(outcome_state(&states, RollbackCanary), Some(3));
+        assert_eq!(outcome_state(&states, PromoteCanary), None);
+        assert_eq!(outcome_state(&states, DeployCanary), None);
+    }
+}
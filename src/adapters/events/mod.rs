@@ -0,0 +1,175 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use miette::Result;
+use multitool_sdk::models::RolloutStateType;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use super::{Measurement, ResponseTally, backend::RolloutId, backend::StateId};
+use crate::stats::Group;
+
+pub(crate) use ndjson::EventSinkConfig;
+pub(crate) use webhook::WebhookConfig;
+
+/// Convenience alias since sinks are dynamically dispatched.
+pub(crate) type BoxedEventSink = Box<dyn EventSink + Send + Sync>;
+
+/// How many events can be waiting for the sinks before the
+/// oldest are dropped.
+const EVENT_BUFFER_SIZE: usize = 1 << 8;
+
+/// A [RolloutEvent] is a transition in a rollout that wrappers and
+/// CI steps may want to react to. Events are serialized with their
+/// name in the `event` field, e.g. `{"event": "traffic_set", "percent": 25}`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum RolloutEvent {
+    /// The rollout was created, or an earlier run of it was resumed.
+    RolloutCreated { resumed: bool },
+    /// We claimed a state from the backend and are about to effect it.
+    StateLocked {
+        state_id: StateId,
+        state_type: RolloutStateType,
+    },
+    /// The canary was deployed and released with no traffic.
+    CanaryDeployed { platform_id: String },
+    /// The canary now receives this percentage of traffic.
+    TrafficSet { percent: i32 },
+    /// The monitor observed the baseline and the canary.
+    ObservationBatch {
+        measurements: usize,
+        baseline: ResponseTally,
+        canary: ResponseTally,
+    },
+    /// The canary is the new baseline.
+    Promoted,
+    /// The canary was rolled back.
+    RolledBack,
+    /// The monitor couldn't collect data. The rollout carries on,
+    /// but the gap may hold up its next decision.
+    MonitorFailed { message: String },
+    /// The rollout stopped because of an error.
+    Error { message: String },
+}
+
+impl RolloutEvent {
+    /// Tally a batch of observations for each group.
+    pub(crate) fn observation_batch(batch: &[Measurement]) -> Self {
+        Self::ObservationBatch {
+            measurements: batch.len(),
+            baseline: ResponseTally::new(batch, Group::Control),
+            canary: ResponseTally::new(batch, Group::Experimental),
+        }
+    }
+}
+
+/// An [EventRecord] is what's written to the sinks: the event,
+/// plus when it happened and which rollout it belongs to.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub(crate) struct EventRecord {
+    at: DateTime<Utc>,
+    rollout_id: RolloutId,
+    #[serde(flatten)]
+    event: RolloutEvent,
+}
+
+/// [Events] is a cheap handle for emitting events. Every subscriber
+/// receives every event emitted after it subscribed.
+#[derive(Clone)]
+pub(crate) struct Events {
+    rollout_id: RolloutId,
+    sender: broadcast::Sender<EventRecord>,
+}
+
+impl Events {
+    pub(crate) fn new(rollout_id: RolloutId) -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER_SIZE);
+        Self { rollout_id, sender }
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<EventRecord> {
+        self.sender.subscribe()
+    }
+
+    /// Emitting never blocks the rollout. If no one is
+    /// subscribed, the event is dropped.
+    pub(crate) fn emit(&self, event: RolloutEvent) {
+        let record = EventRecord {
+            at: Utc::now(),
+            rollout_id: self.rollout_id,
+            event,
+        };
+        let _ = self.sender.send(record);
+    }
+}
+
+/// An [EventSink] is somewhere events are delivered, like a file.
+#[async_trait]
+pub(crate) trait EventSink {
+    /// Deliver a single event.
+    async fn send(&mut self, record: &EventRecord) -> Result<()>;
+    /// Make sure every event sent so far has been delivered.
+    async fn flush(&mut self) -> Result<()>;
+}
+
+/// Sinks that write newline-delimited JSON.
+mod ndjson;
+/// Notifies webhooks when the rollout changes state.
+mod webhook;
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use static_assertions::assert_obj_safe;
+
+    use super::{EventSink, Events, RolloutEvent};
+    use crate::{
+        adapters::{Measurement, StatusCode},
+        metrics::ResponseStatusCode,
+        stats::{CategoricalObservation, Group},
+    };
+
+    assert_obj_safe!(EventSink);
+
+    #[test]
+    fn events_reach_subscribers() {
+        let events = Events::new(7);
+        // Nobody hears events emitted before they subscribe.
+        events.emit(RolloutEvent::Promoted);
+        let mut receiver = events.subscribe();
+        events.emit(RolloutEvent::TrafficSet { percent: 25 });
+        let record = receiver.try_recv().unwrap();
+        let mut value = serde_json::to_value(&record).unwrap();
+        value.as_object_mut().unwrap().remove("at");
+        assert_eq!(
+            value,
+            json!({"event": "traffic_set", "rollout_id": 7, "percent": 25})
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    /// Batches are tallied for each group, like on the dashboard.
+    #[test]
+    fn observation_batches_are_tallied() {
+        let status_codes = |group, ok, errors| {
+            let mut observation: StatusCode = CategoricalObservation::new(group);
+            observation.increment_by(&ResponseStatusCode::_2XX, ok);
+            observation.increment_by(&ResponseStatusCode::_5XX, errors);
+            Measurement::StatusCode(observation)
+        };
+        let batch = vec![
+            status_codes(Group::Control, 98, 2),
+            status_codes(Group::Experimental, 45, 5),
+        ];
+        assert_eq!(
+            serde_json::to_value(RolloutEvent::observation_batch(&batch)).unwrap(),
+            json!({
+                "event": "observation_batch",
+                "measurements": 2,
+                "baseline": {"errors": 2, "total": 100},
+                "canary": {"errors": 5, "total": 50},
+            })
+        );
+    }
+}
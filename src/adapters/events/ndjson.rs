@@ -0,0 +1,136 @@
+use std::{path::PathBuf, str::FromStr};
+
+use async_trait::async_trait;
+use miette::{IntoDiagnostic, Result};
+use tokio::{
+    fs::OpenOptions,
+    io::{AsyncWrite, AsyncWriteExt},
+    net::UnixStream,
+};
+
+use super::{BoxedEventSink, EventRecord, EventSink};
+
+/// Where to send rollout events, as given on the command line:
+/// `-` is stdout, `unix:<path>` is a Unix socket, and anything
+/// else is a file path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum EventSinkConfig {
+    Stdout,
+    /// Events are appended to the file, which is created if needed.
+    File(PathBuf),
+    /// Events are written to a socket that's already listening.
+    Unix(PathBuf),
+}
+
+impl FromStr for EventSinkConfig {
+    type Err = miette::Report;
+
+    fn from_str(sink: &str) -> Result<Self> {
+        let parsed = match sink {
+            "-" => Self::Stdout,
+            _ => match sink.strip_prefix("unix:") {
+                Some(path) => Self::Unix(path.into()),
+                None => Self::File(sink.into()),
+            },
+        };
+        Ok(parsed)
+    }
+}
+
+impl EventSinkConfig {
+    pub(crate) async fn build(&self) -> Result<BoxedEventSink> {
+        let sink = match self {
+            Self::Stdout => NdjsonSink::new(tokio::io::stdout()),
+            Self::File(path) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .into_diagnostic()?;
+                NdjsonSink::new(file)
+            }
+            Self::Unix(path) => NdjsonSink::new(UnixStream::connect(path).await.into_diagnostic()?),
+        };
+        Ok(Box::new(sink))
+    }
+}
+
+/// An [NdjsonSink] writes each event as a line of JSON.
+pub(crate) struct NdjsonSink {
+    writer: Box<dyn AsyncWrite + Send + Sync + Unpin>,
+}
+
+impl NdjsonSink {
+    pub(crate) fn new(writer: impl AsyncWrite + Send + Sync + Unpin + 'static) -> Self {
+        Self {
+            writer: Box::new(writer),
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for NdjsonSink {
+    async fn send(&mut self, record: &EventRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record).into_diagnostic()?;
+        line.push(b'\n');
+        self.writer.write_all(&line).await.into_diagnostic()?;
+        // Whoever is reading wants to react to each event as it happens.
+        self.writer.flush().await.into_diagnostic()
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.writer.flush().await.into_diagnostic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::EventSinkConfig;
+    use crate::adapters::events::{Events, RolloutEvent};
+
+    #[test]
+    fn parse_sinks() {
+        assert_eq!(
+            "-".parse::<EventSinkConfig>().unwrap(),
+            EventSinkConfig::Stdout
+        );
+        assert_eq!(
+            "unix:/tmp/multi.sock".parse::<EventSinkConfig>().unwrap(),
+            EventSinkConfig::Unix("/tmp/multi.sock".into())
+        );
+        assert_eq!(
+            "events.ndjson".parse::<EventSinkConfig>().unwrap(),
+            EventSinkConfig::File("events.ndjson".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn file_sink_appends_lines() {
+        let path =
+            std::env::temp_dir().join(format!("multi-events-{}.ndjson", uuid::Uuid::new_v4()));
+        let events = Events::new(7);
+        let mut receiver = events.subscribe();
+        events.emit(RolloutEvent::RolloutCreated { resumed: false });
+        events.emit(RolloutEvent::Promoted);
+
+        let mut sink = EventSinkConfig::File(path.clone()).build().await.unwrap();
+        while let Ok(record) = receiver.try_recv() {
+            sink.send(&record).await.unwrap();
+        }
+        sink.flush().await.unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let names: Vec<_> = contents
+            .lines()
+            .map(|line| {
+                let value: serde_json::Value = serde_json::from_str(line).unwrap();
+                value["event"].as_str().unwrap().to_owned()
+            })
+            .collect();
+        assert_eq!(names, vec!["rollout_created", "promoted"]);
+    }
+}
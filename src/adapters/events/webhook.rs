@@ -0,0 +1,349 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use miette::{IntoDiagnostic, Result, miette};
+use multitool_sdk::models::RolloutStateType;
+use reqwest::{Client, StatusCode, Url, header::CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use sha2::Sha256;
+use tokio::time::sleep;
+use tracing::debug;
+
+use super::{BoxedEventSink, EventRecord, EventSink, RolloutEvent};
+
+/// The header carrying the signature of the request body.
+const SIGNATURE_HEADER: &str = "X-MultiTool-Signature-256";
+/// How long a single request to a webhook may take.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many times a notification is attempted before we give up on it.
+const DEFAULT_MAX_ATTEMPTS: u32 = 4;
+/// How long we wait before the first retry. Each subsequent retry
+/// waits twice as long as the one before it.
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// A [WebhookConfig] describes one webhook to notify when the
+/// rollout changes state, or fails.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct WebhookConfig {
+    url: String,
+    /// The name of the environment variable holding the secret used to
+    /// sign each request. Requests aren't signed if this is omitted.
+    #[serde(default)]
+    secret_env: Option<String>,
+    /// The JSON body to send. Strings may refer to the notification's
+    /// details with `{{placeholders}}`. See [WebhookSink].
+    #[serde(default = "default_payload")]
+    payload: Value,
+}
+
+/// Without a payload, we send something chat services understand.
+fn default_payload() -> Value {
+    json!({
+        "text": "{{summary}}",
+        "event": "{{event}}",
+        "rollout_id": "{{rollout_id}}",
+        "state_type": "{{state_type}}",
+    })
+}
+
+impl WebhookConfig {
+    pub(crate) fn build(&self) -> Result<BoxedEventSink> {
+        let url = Url::parse(&self.url).into_diagnostic()?;
+        let secret = match &self.secret_env {
+            Some(name) => Some(
+                std::env::var(name)
+                    .map_err(|_| miette!("The webhook secret variable {name} isn't set"))?
+                    .into_bytes(),
+            ),
+            None => None,
+        };
+        Ok(Box::new(WebhookSink::new(
+            url,
+            secret,
+            self.payload.clone(),
+        )?))
+    }
+}
+
+/// A [WebhookSink] POSTs a notification when the canary is deployed,
+/// its traffic changes, it's promoted or rolled back, and when the
+/// rollout fails. Other events are ignored.
+///
+/// The payload may use these placeholders: `event`, `rollout_id`, `at`,
+/// `state_type`, and `summary`, plus the fields of the event itself,
+/// like `percent` or `message`. A string that's only a placeholder is
+/// replaced by the value, keeping its JSON type. Otherwise, the value
+/// is spliced into the string.
+pub(crate) struct WebhookSink {
+    client: Client,
+    url: Url,
+    secret: Option<Vec<u8>>,
+    payload: Value,
+    max_attempts: u32,
+    initial_backoff: Duration,
+}
+
+impl WebhookSink {
+    fn new(url: Url, secret: Option<Vec<u8>>, payload: Value) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .into_diagnostic()?;
+        Ok(Self {
+            client,
+            url,
+            secret,
+            payload,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+        })
+    }
+
+    /// Send the body, retrying if the webhook couldn't be reached
+    /// or failed in a way that might not happen again.
+    async fn post(&self, body: Vec<u8>) -> Result<()> {
+        let signature = self.secret.as_deref().map(|secret| sign(secret, &body));
+        let mut attempt = 1;
+        loop {
+            let mut request = self
+                .client
+                .post(self.url.clone())
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            if let Some(signature) = &signature {
+                request = request.header(SIGNATURE_HEADER, signature);
+            }
+            let (retry, err) = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    let retry = status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+                    let err = miette!("The webhook at {} responded with {status}", self.url);
+                    (retry, err)
+                }
+                Err(err) => {
+                    let retry = err.is_connect() || err.is_timeout() || err.is_request();
+                    let err = miette!("Couldn't notify the webhook at {}: {err}", self.url);
+                    (retry, err)
+                }
+            };
+            if !retry || attempt >= self.max_attempts {
+                return Err(err);
+            }
+            let delay = self.initial_backoff.saturating_mul(1 << (attempt - 1));
+            debug!("{err}. Retrying in {delay:?}...");
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookSink {
+    async fn send(&mut self, record: &EventRecord) -> Result<()> {
+        let Some(variables) = record.notification()? else {
+            return Ok(());
+        };
+        let body = serde_json::to_vec(&render(&self.payload, &variables)).into_diagnostic()?;
+        self.post(body).await
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        // Each notification is delivered before `send` returns.
+        Ok(())
+    }
+}
+
+impl EventRecord {
+    /// The details a payload can refer to, or `None` if we don't
+    /// notify anyone about this event.
+    fn notification(&self) -> Result<Option<Map<String, Value>>> {
+        let rollout_id = self.rollout_id;
+        let (state_type, summary) = match &self.event {
+            RolloutEvent::CanaryDeployed { .. } => (
+                Some(RolloutStateType::DeployCanary),
+                format!("Rollout {rollout_id}: the canary was deployed."),
+            ),
+            RolloutEvent::TrafficSet { percent } => (
+                Some(RolloutStateType::SetCanaryTraffic),
+                format!("Rollout {rollout_id}: the canary now receives {percent}% of traffic."),
+            ),
+            RolloutEvent::Promoted => (
+                Some(RolloutStateType::PromoteCanary),
+                format!("Rollout {rollout_id}: the canary was promoted."),
+            ),
+            RolloutEvent::RolledBack => (
+                Some(RolloutStateType::RollbackCanary),
+                format!("Rollout {rollout_id}: the canary was rolled back."),
+            ),
+            RolloutEvent::Error { message } => {
+                (None, format!("Rollout {rollout_id} failed: {message}"))
+            }
+            _ => return Ok(None),
+        };
+        let Value::Object(mut variables) = serde_json::to_value(self).into_diagnostic()? else {
+            return Err(miette!("Rollout events must serialize to JSON objects"));
+        };
+        variables.insert(
+            "state_type".to_owned(),
+            serde_json::to_value(state_type).into_diagnostic()?,
+        );
+        variables.insert("summary".to_owned(), Value::String(summary));
+        Ok(Some(variables))
+    }
+}
+
+/// Replace the `{{placeholders}}` in the template's strings with the
+/// given variables. Unknown placeholders become `null`, or nothing
+/// when they're spliced into a string.
+fn render(template: &Value, variables: &Map<String, Value>) -> Value {
+    match template {
+        Value::String(text) => {
+            if let Some(name) = text
+                .strip_prefix("{{")
+                .and_then(|rest| rest.strip_suffix("}}"))
+                .filter(|name| !name.contains("{{"))
+            {
+                return variables.get(name.trim()).cloned().unwrap_or(Value::Null);
+            }
+            let mut rendered = String::with_capacity(text.len());
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find("{{") {
+                let Some(end) = rest[start..].find("}}") else {
+                    break;
+                };
+                rendered.push_str(&rest[..start]);
+                let name = rest[start + 2..start + end].trim();
+                match variables.get(name) {
+                    Some(Value::String(value)) => rendered.push_str(value),
+                    Some(Value::Null) | None => {}
+                    Some(value) => rendered.push_str(&value.to_string()),
+                }
+                rest = &rest[start + end + 2..];
+            }
+            rendered.push_str(rest);
+            Value::String(rendered)
+        }
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|item| render(item, variables)).collect())
+        }
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), render(value, variables)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Sign the body the way GitHub signs its webhooks, so receivers
+/// can reuse their verification code.
+fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+    use serde_json::{Value, json};
+
+    use super::{WebhookConfig, WebhookSink, render, sign};
+    use crate::{
+        adapters::events::{EventSink, Events, RolloutEvent},
+        utils::StubServer,
+    };
+
+    #[test]
+    fn render_payloads() {
+        let variables = json!({"rollout_id": 7, "event": "promoted", "message": null});
+        let variables = variables.as_object().unwrap();
+        let template = json!({
+            "id": "{{rollout_id}}",
+            "text": "Rollout {{ rollout_id }} was {{event}}{{message}}{{unknown}}.",
+            "tags": ["{{event}}", "{{unknown}}", 3],
+        });
+        assert_eq!(
+            render(&template, variables),
+            json!({
+                "id": 7,
+                "text": "Rollout 7 was promoted.",
+                "tags": ["promoted", null, 3],
+            })
+        );
+    }
+
+    #[test]
+    fn parse_config() {
+        let config: WebhookConfig = toml::from_str(
+            r#"
+            url = "https://hooks.example.com/multitool"
+            secret_env = "HOOK_SECRET"
+            payload = { text = "{{summary}}", channel = "deploys" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.secret_env.as_deref(), Some("HOOK_SECRET"));
+        assert_eq!(
+            config.payload,
+            json!({"text": "{{summary}}", "channel": "deploys"})
+        );
+        let config: WebhookConfig = toml::from_str(r#"url = "https://example.com""#).unwrap();
+        assert_eq!(config.payload["text"], "{{summary}}");
+    }
+
+    #[tokio::test]
+    async fn notify_local_listener() {
+        // The first attempt fails with a server error, so it's retried.
+        // The last notification is rejected, which isn't retried.
+        let server = StubServer::replying(vec![(503, ""), (200, ""), (400, "")]).await;
+        let mut sink = WebhookSink::new(
+            format!("{}/hook", server.origin).parse().unwrap(),
+            Some(b"hunter2".to_vec()),
+            json!({"text": "{{summary}}", "rollout": "{{rollout_id}}"}),
+        )
+        .unwrap();
+        sink.initial_backoff = Duration::from_millis(10);
+
+        let events = Events::new(7);
+        let mut receiver = events.subscribe();
+        events.emit(RolloutEvent::RolloutCreated { resumed: false });
+        events.emit(RolloutEvent::Promoted);
+        events.emit(RolloutEvent::Error {
+            message: "boom".to_owned(),
+        });
+        let mut results = Vec::new();
+        while let Ok(record) = receiver.try_recv() {
+            results.push(sink.send(&record).await.is_ok());
+        }
+        // Only the creation is skipped, since it isn't a transition.
+        assert_eq!(results, vec![true, true, false]);
+
+        let received = server.requests();
+        assert_eq!(received.len(), 3);
+        let bodies: Vec<Value> = received
+            .iter()
+            .map(|request| serde_json::from_slice(&request.body).unwrap())
+            .collect();
+        let promoted = json!({"text": "Rollout 7: the canary was promoted.", "rollout": 7});
+        assert_eq!(bodies[0], promoted);
+        assert_eq!(bodies[1], promoted);
+        assert_eq!(
+            bodies[2],
+            json!({"text": "Rollout 7 failed: boom", "rollout": 7})
+        );
+        for request in received.iter() {
+            let signature = format!(
+                "x-multitool-signature-256: {}",
+                sign(b"hunter2", &request.body)
+            );
+            assert!(request.head.lines().any(|line| line == signature));
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use std::{fmt, process::Stdio, time::Duration};
+
+use miette::{Diagnostic, Result};
+use multitool_sdk::models::RolloutStateType;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::AsyncReadExt as _,
+    process::{Child, Command},
+    time::timeout,
+};
+use tracing::{debug, info};
+
+use super::{RolloutMetadata, TargetState};
+
+/// How long a hook may run before it's killed, unless configured otherwise.
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+/// A [HooksConfig] lists shell commands to run before and after each
+/// state of the rollout is effected, e.g. smoke tests once the canary
+/// is deployed, or warming caches before it receives traffic.
+///
+/// Each command is run with `sh -c`, and learns about the rollout from
+/// these environment variables:
+/// - `MULTI_WORKSPACE_ID`, `MULTI_APPLICATION_ID`, and `MULTI_ROLLOUT_ID`.
+/// - `MULTI_STATE_ID` and `MULTI_STATE_TYPE`, e.g. `SetCanaryTraffic`.
+/// - `MULTI_HOOK_STAGE`, either `before` or `after`.
+/// - `MULTI_CANARY_TRAFFIC`, the percentage of traffic the state sends
+///   to the canary. Only set for `SetCanaryTraffic` states.
+/// - `MULTI_CANARY_ID`, the platform's identifier for the canary. Set
+///   for every state once the canary is deployed, including after a resume.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct HooksConfig {
+    #[serde(default)]
+    deploy_canary: StateHooks,
+    #[serde(default)]
+    set_canary_traffic: StateHooks,
+    #[serde(default)]
+    promote_canary: StateHooks,
+    #[serde(default)]
+    rollback_canary: StateHooks,
+    /// How long each command may run, in seconds.
+    #[serde(default = "default_timeout_secs")]
+    timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+/// The commands run around a single type of state.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+struct StateHooks {
+    /// Run before the state is effected. If one fails, the state
+    /// is left for a later run to effect.
+    #[serde(default)]
+    before: Vec<String>,
+    /// Run once the state has been effected.
+    #[serde(default)]
+    after: Vec<String>,
+}
+
+/// Whether a hook runs before or after its state is effected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum HookStage {
+    Before,
+    After,
+}
+
+impl fmt::Display for HookStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Before => write!(f, "before"),
+            Self::After => write!(f, "after"),
+        }
+    }
+}
+
+/// The reasons a hook can fail.
+#[derive(thiserror::Error, Debug, Diagnostic)]
+pub(crate) enum HookError {
+    #[error("The {stage} hook `{command}` for {state_type} couldn't be started: {source}")]
+    Spawn {
+        stage: HookStage,
+        state_type: RolloutStateType,
+        command: String,
+        source: std::io::Error,
+    },
+    #[error("The {stage} hook `{command}` for {state_type} failed with {status}")]
+    #[diagnostic(help("The hook's output is logged above."))]
+    Failed {
+        stage: HookStage,
+        state_type: RolloutStateType,
+        command: String,
+        status: std::process::ExitStatus,
+    },
+    #[error("The {stage} hook `{command}` for {state_type} didn't finish within {timeout:?}")]
+    TimedOut {
+        stage: HookStage,
+        state_type: RolloutStateType,
+        command: String,
+        timeout: Duration,
+    },
+}
+
+/// The rollout context passed to hooks through their environment.
+#[derive(Clone, Debug)]
+pub(crate) struct HookContext {
+    meta: RolloutMetadata,
+    state: TargetState,
+    canary_id: Option<String>,
+}
+
+impl HookContext {
+    pub(crate) fn new(
+        meta: &RolloutMetadata,
+        state: &TargetState,
+        canary_id: Option<String>,
+    ) -> Self {
+        Self {
+            meta: meta.clone(),
+            state: state.clone(),
+            canary_id,
+        }
+    }
+
+    /// Tell later hooks which canary was deployed.
+    pub(crate) fn set_canary_id(&mut self, canary_id: String) {
+        self.canary_id = Some(canary_id);
+    }
+
+    fn env(&self, stage: HookStage) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("MULTI_WORKSPACE_ID", self.meta.workspace_id().to_string()),
+            (
+                "MULTI_APPLICATION_ID",
+                self.meta.application_id().to_string(),
+            ),
+            ("MULTI_ROLLOUT_ID", self.meta.rollout_id().to_string()),
+            ("MULTI_STATE_ID", self.state.id().to_string()),
+            ("MULTI_STATE_TYPE", self.state.state_type().to_string()),
+            ("MULTI_HOOK_STAGE", stage.to_string()),
+        ];
+        if let Some(percent) = self.state.percent_traffic() {
+            env.push(("MULTI_CANARY_TRAFFIC", percent.as_whole().to_string()));
+        }
+        if let Some(canary_id) = &self.canary_id {
+            env.push(("MULTI_CANARY_ID", canary_id.clone()));
+        }
+        env
+    }
+}
+
+impl HooksConfig {
+    fn hooks(&self, state_type: RolloutStateType) -> &StateHooks {
+        match state_type {
+            RolloutStateType::DeployCanary => &self.deploy_canary,
+            RolloutStateType::SetCanaryTraffic => &self.set_canary_traffic,
+            RolloutStateType::PromoteCanary => &self.promote_canary,
+            RolloutStateType::RollbackCanary => &self.rollback_canary,
+        }
+    }
+
+    /// Run the hooks for the context's state in order, stopping
+    /// at the first one that fails.
+    pub(crate) async fn run(&self, stage: HookStage, context: &HookContext) -> Result<()> {
+        let state_type = *context.state.state_type();
+        let hooks = self.hooks(state_type);
+        let commands = match stage {
+            HookStage::Before => &hooks.before,
+            HookStage::After => &hooks.after,
+        };
+        let limit = Duration::from_secs(self.timeout_secs);
+        for command in commands {
+            info!("Running the {stage} hook `{command}` for {state_type}...");
+            let spawn_error = |source| HookError::Spawn {
+                stage,
+                state_type,
+                command: command.clone(),
+                source,
+            };
+            // The hook gets a process group of its own, so if it times
+            // out, we can kill any processes it started along with it.
+            let mut child = Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .envs(context.env(stage))
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .process_group(0)
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(spawn_error)?;
+            let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+            let mut stdout_pipe = child.stdout.take().expect("stdout is piped");
+            let mut stderr_pipe = child.stderr.take().expect("stderr is piped");
+            // Whatever the hook wrote is kept in the buffers,
+            // even if it doesn't finish in time.
+            let finished = timeout(limit, async {
+                let (status, _, _) = tokio::join!(
+                    child.wait(),
+                    stdout_pipe.read_to_end(&mut stdout),
+                    stderr_pipe.read_to_end(&mut stderr),
+                );
+                status
+            })
+            .await;
+            let status = match finished {
+                Ok(status) => status.map_err(spawn_error)?,
+                Err(_) => {
+                    kill_group(&mut child).await;
+                    log_output(command, &stdout, &stderr);
+                    return Err(HookError::TimedOut {
+                        stage,
+                        state_type,
+                        command: command.clone(),
+                        timeout: limit,
+                    }
+                    .into());
+                }
+            };
+            log_output(command, &stdout, &stderr);
+            if !status.success() {
+                return Err(HookError::Failed {
+                    stage,
+                    state_type,
+                    command: command.clone(),
+                    status,
+                }
+                .into());
+            }
+            debug!("The {stage} hook `{command}` succeeded");
+        }
+        Ok(())
+    }
+}
+
+/// Kill the hook, along with the processes it started, which
+/// share its process group unless they left it.
+async fn kill_group(child: &mut Child) {
+    if let Some(pid) = child.id() {
+        // SAFETY: `killpg` only sends a signal. The group is the
+        // hook's own, since it was spawned as the group's leader
+        // and hasn't been reaped yet.
+        unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) };
+    }
+    // Reap the hook, so it doesn't linger as a zombie.
+    let _ = child.kill().await;
+}
+
+/// The hook's output goes through our logger, so it
+/// doesn't clobber the dashboard.
+fn log_output(command: &str, stdout: &[u8], stderr: &[u8]) {
+    for line in String::from_utf8_lossy(stdout)
+        .lines()
+        .chain(String::from_utf8_lossy(stderr).lines())
+    {
+        info!("[{command}] {line}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use multitool_sdk::models::RolloutStateType;
+    use pretty_assertions::assert_eq;
+
+    use super::{HookContext, HookStage, HooksConfig};
+    use crate::{
+        WholePercent,
+        adapters::{RolloutMetadata, TargetState},
+    };
+
+    fn context() -> HookContext {
+        let meta = RolloutMetadata::builder()
+            .workspace_id(1)
+            .application_id(2)
+            .rollout_id(3)
+            .build();
+        let state = TargetState::builder()
+            .id(4)
+            .state_type(RolloutStateType::SetCanaryTraffic)
+            .percent_traffic(WholePercent::try_from(25).unwrap())
+            .build();
+        HookContext::new(&meta, &state, Some("arn:fn:7".to_owned()))
+    }
+
+    /// Traffic steps' hooks know which canary the traffic goes to.
+    #[tokio::test]
+    async fn hooks_see_the_rollout() {
+        let path = std::env::temp_dir().join(format!("multi-hook-{}", uuid::Uuid::new_v4()));
+        let config: HooksConfig = toml::from_str(&format!(
+            r#"
+            [set_canary_traffic]
+            before = ["echo $MULTI_HOOK_STAGE $MULTI_ROLLOUT_ID $MULTI_STATE_ID $MULTI_CANARY_TRAFFIC $MULTI_CANARY_ID > {}"]
+            "#,
+            path.display()
+        ))
+        .unwrap();
+        config.run(HookStage::Before, &context()).await.unwrap();
+        // There are no hooks after the state, or for other states.
+        config.run(HookStage::After, &context()).await.unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(written, "before 3 4 25 arn:fn:7\n");
+    }
+
+    #[tokio::test]
+    async fn failing_hooks_stop_the_rest() {
+        let config: HooksConfig = toml::from_str(
+            r#"
+            timeout_secs = 1
+            [set_canary_traffic]
+            before = ["exit 3", "this hook never runs"]
+            after = ["sleep 5"]
+            "#,
+        )
+        .unwrap();
+        let err = config.run(HookStage::Before, &context()).await.unwrap_err();
+        assert!(err.to_string().contains("`exit 3`"));
+        let err = config.run(HookStage::After, &context()).await.unwrap_err();
+        assert!(err.to_string().contains("didn't finish within 1s"));
+    }
+
+    /// A hook that times out is killed along with the processes it started.
+    #[tokio::test]
+    async fn timed_out_hooks_are_killed() {
+        let path = std::env::temp_dir().join(format!("multi-hook-{}", uuid::Uuid::new_v4()));
+        let config: HooksConfig = toml::from_str(&format!(
+            r#"
+            timeout_secs = 1
+            [set_canary_traffic]
+            before = ["(sleep 2; touch {}) & echo started; wait"]
+            "#,
+            path.display()
+        ))
+        .unwrap();
+        let err = config.run(HookStage::Before, &context()).await.unwrap_err();
+        assert!(err.to_string().contains("didn't finish within 1s"));
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        assert!(!path.exists());
+    }
+}
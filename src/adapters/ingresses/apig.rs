@@ -124,6 +124,21 @@ impl AwsApiGateway {
     }
 }
 
+/// The URI API Gateway invokes the Lambda through. When the ARN is
+/// qualified with a version, requests go to exactly that version.
+fn integration_uri(region: &str, function_arn: &str) -> String {
+    format!(
+        "arn:aws:apigateway:{region}:lambda:path/2015-03-31/functions/{function_arn}/invocations"
+    )
+}
+
+/// The inverse of [integration_uri]: the ARN of the Lambda
+/// API Gateway invokes through the given URI.
+fn function_arn(uri: &str) -> Option<&str> {
+    let (_, path) = uri.split_once("/functions/")?;
+    path.strip_suffix("/invocations")
+}
+
 #[async_trait]
 impl Ingress for AwsApiGateway {
     async fn release_canary(&mut self, platform_id: String) -> Result<()> {
@@ -140,7 +155,9 @@ impl Ingress for AwsApiGateway {
             .ok_or(miette!("Couldn't get ID of API Gateway Resource"))?;
 
         // Ensure we add invoke permissions to the new version of the lambda
-        // NOTE: All calls to invoke the function will fail unless this is explicitly added
+        // NOTE: All calls to invoke the function will fail unless this is explicitly added.
+        // The platform id is a version-qualified ARN, so the permission is
+        // added to that version's policy, not the function's.
         self.lambda_client
             .add_permission()
             .function_name(platform_id.clone())
@@ -155,10 +172,7 @@ impl Ingress for AwsApiGateway {
         let patch_op = PatchOperation::builder()
             .op(Op::Replace)
             .path("/uri")
-            .value(format!(
-                "arn:aws:apigateway:{}:lambda:path/2015-03-31/functions/{}/invocations",
-                self.region, platform_id
-            ))
+            .value(integration_uri(&self.region, &platform_id))
             .build();
 
         self.apig_client
@@ -254,6 +268,44 @@ impl Ingress for AwsApiGateway {
 
         Ok(())
     }
+
+    /// The stage only has canary settings while a canary is released,
+    /// and the canary is whichever version the integration invokes.
+    async fn discover_canary(&self) -> Result<Option<String>> {
+        let api = self.get_api_id_by_name(&self.gateway_name).await?;
+        let api_id = api.id().ok_or(miette!("Couldn't get ID of API Gateway"))?;
+        let stage = self
+            .apig_client
+            .get_stage()
+            .rest_api_id(api_id)
+            .stage_name(&self.stage_name)
+            .send()
+            .await
+            .into_diagnostic()?;
+        if stage.canary_settings().is_none() {
+            return Ok(None);
+        }
+
+        let resource = self
+            .get_resource_id_by_path(api_id, &self.resource_path)
+            .await?;
+        let resource_id = resource
+            .id()
+            .ok_or(miette!("Couldn't get ID of API Gateway Resource"))?;
+        let integration = self
+            .apig_client
+            .get_integration()
+            .rest_api_id(api_id)
+            .resource_id(resource_id)
+            .http_method(&self.resource_method)
+            .send()
+            .await
+            .into_diagnostic()?;
+        Ok(integration
+            .uri()
+            .and_then(function_arn)
+            .map(ToOwned::to_owned))
+    }
 }
 
 #[async_trait]
@@ -264,3 +316,29 @@ impl Shutdownable for AwsApiGateway {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{function_arn, integration_uri};
+
+    /// The Lambda platform hands us a version-qualified ARN, which
+    /// must survive intact in the integration URI.
+    #[test]
+    fn integrate_qualified_arns() {
+        let arn = "arn:aws:lambda:us-east-2:123456789012:function:my-function:7";
+        assert_eq!(
+            integration_uri("us-east-2", arn),
+            "arn:aws:apigateway:us-east-2:lambda:path/2015-03-31/functions/arn:aws:lambda:us-east-2:123456789012:function:my-function:7/invocations"
+        );
+    }
+
+    /// The canary's ARN can be read back out of the integration URI.
+    #[test]
+    fn read_function_arns_from_uris() {
+        let arn = "arn:aws:lambda:us-east-2:123456789012:function:my-function:7";
+        assert_eq!(function_arn(&integration_uri("us-east-2", arn)), Some(arn));
+        assert_eq!(function_arn("http://example.com/invocations"), None);
+    }
+}
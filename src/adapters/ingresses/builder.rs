@@ -1,10 +1,39 @@
 use async_trait::async_trait;
 use multitool_sdk::models::{IngressConfig, IngressConfigOneOfAwsRestApiGateway};
+use serde::{Deserialize, Serialize};
 
 use crate::adapters::ingresses::apig::AwsApiGateway;
+use crate::adapters::ingresses::lambda_alias::{LambdaAlias, LambdaAliasConfig};
+use crate::adapters::ingresses::proxy::{ProxyControl, ProxyControlConfig};
 
 use super::BoxedIngress;
 
+/// The ingresses that can be configured locally. This is a superset
+/// of the ingresses the backend knows about.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub(crate) enum LocalIngressConfig {
+    LambdaAlias { lambda_alias: LambdaAliasConfig },
+    Proxy { proxy: ProxyControlConfig },
+    Backend(IngressConfig),
+}
+
+impl LocalIngressConfig {
+    /// The alias the ingress routes through, if it's a Lambda alias.
+    pub(crate) fn lambda_alias(&self) -> Option<&str> {
+        match self {
+            Self::LambdaAlias { lambda_alias } => Some(&lambda_alias.alias_name),
+            Self::Proxy { .. } | Self::Backend(_) => None,
+        }
+    }
+}
+
+impl From<IngressConfig> for LocalIngressConfig {
+    fn from(config: IngressConfig) -> Self {
+        Self::Backend(config)
+    }
+}
+
 /// Private trait we use locally to unify the API of the many
 /// helper structs.&
 /// This is basically the Visitor pattern, walking the config
@@ -16,12 +45,14 @@ trait Builder {
 }
 
 pub(crate) struct IngressBuilder {
-    config: IngressConfig,
+    config: LocalIngressConfig,
 }
 
 impl IngressBuilder {
-    pub(crate) fn new(config: IngressConfig) -> Self {
-        Self { config }
+    pub(crate) fn new(config: impl Into<LocalIngressConfig>) -> Self {
+        Self {
+            config: config.into(),
+        }
     }
 
     pub async fn build(self) -> BoxedIngress {
@@ -33,11 +64,17 @@ impl IngressBuilder {
 impl Builder for IngressBuilder {
     async fn build(self) -> BoxedIngress {
         match self.config {
-            IngressConfig::IngressConfigOneOf(ingress_conf) => {
+            LocalIngressConfig::Backend(IngressConfig::IngressConfigOneOf(ingress_conf)) => {
                 AwsGatewayIngressBuilder::new(*ingress_conf.aws_rest_api_gateway)
                     .build()
                     .await
             }
+            LocalIngressConfig::LambdaAlias { lambda_alias } => {
+                LambdaAliasIngressBuilder::new(lambda_alias).build().await
+            }
+            LocalIngressConfig::Proxy { proxy } => {
+                ProxyControlIngressBuilder::new(proxy).build().await
+            }
         }
     }
 }
@@ -67,6 +104,46 @@ impl Builder for AwsGatewayIngressBuilder {
     }
 }
 
+struct LambdaAliasIngressBuilder {
+    conf: LambdaAliasConfig,
+}
+
+impl LambdaAliasIngressBuilder {
+    fn new(conf: LambdaAliasConfig) -> Self {
+        Self { conf }
+    }
+}
+
+#[async_trait]
+impl Builder for LambdaAliasIngressBuilder {
+    async fn build(self) -> BoxedIngress {
+        let ingress = LambdaAlias::builder()
+            .function_name(self.conf.function_name)
+            .alias_name(self.conf.alias_name)
+            .region(self.conf.region)
+            .build()
+            .await;
+        Box::new(ingress)
+    }
+}
+
+struct ProxyControlIngressBuilder {
+    conf: ProxyControlConfig,
+}
+
+impl ProxyControlIngressBuilder {
+    fn new(conf: ProxyControlConfig) -> Self {
+        Self { conf }
+    }
+}
+
+#[async_trait]
+impl Builder for ProxyControlIngressBuilder {
+    async fn build(self) -> BoxedIngress {
+        Box::new(ProxyControl::from(self.conf))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::adapters::BoxedIngress;
@@ -74,7 +151,7 @@ mod tests {
     use multitool_sdk::models::IngressConfig;
     use serde_json::{Value, json};
 
-    use super::IngressBuilder;
+    use super::{IngressBuilder, LocalIngressConfig};
 
     fn ingress_json() -> Value {
         json!({
@@ -98,4 +175,38 @@ mod tests {
         let _: BoxedIngress = IngressBuilder::new(config_object).build().await;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn parse_local_ingress_config() -> Result<()> {
+        let lambda_alias = json!({
+            "lambda_alias": {
+                "function_name": "my-function",
+                "alias_name": "live",
+                "region": "us-east-2",
+            }
+        });
+        let config: LocalIngressConfig = serde_json::from_value(lambda_alias).into_diagnostic()?;
+        assert!(matches!(config, LocalIngressConfig::LambdaAlias { .. }));
+        assert_eq!(config.lambda_alias(), Some("live"));
+        let gateway: LocalIngressConfig =
+            serde_json::from_value(ingress_json()).into_diagnostic()?;
+        assert!(matches!(gateway, LocalIngressConfig::Backend(_)));
+        assert_eq!(gateway.lambda_alias(), None);
+        let _: BoxedIngress = IngressBuilder::new(config).build().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parse_proxy_ingress_config() -> Result<()> {
+        let proxy = json!({
+            "proxy": {
+                "control_socket": "/tmp/multi-proxy.sock",
+                "canary": "127.0.0.1:9090",
+            }
+        });
+        let config: LocalIngressConfig = serde_json::from_value(proxy).into_diagnostic()?;
+        assert!(matches!(config, LocalIngressConfig::Proxy { .. }));
+        let _: BoxedIngress = IngressBuilder::new(config).build().await;
+        Ok(())
+    }
 }
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use aws_sdk_lambda::{client::Client as LambdaClient, types::AliasRoutingConfiguration};
+use bon::bon;
+use miette::{IntoDiagnostic as _, Result, bail, miette};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::{
+    Shutdownable, WholePercent,
+    subsystems::ShutdownResult,
+    utils::{alias_version, load_default_aws_config, version_from_arn},
+};
+
+use super::Ingress;
+
+/// The user-facing configuration for the [LambdaAlias] ingress.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct LambdaAliasConfig {
+    /// The name of the Lambda function.
+    pub(super) function_name: String,
+    /// The alias that receives traffic, e.g. `live`.
+    pub(super) alias_name: String,
+    pub(super) region: String,
+}
+
+/// [LambdaAlias] is the Ingress implementation for Lambda functions
+/// invoked through an alias. Instead of relying on a load balancer,
+/// it uses the alias' weighted routing to send a percentage of
+/// invocations to the canary version, so functions triggered by SQS,
+/// EventBridge, function URLs, etc. can be canaried too.
+pub struct LambdaAlias {
+    client: LambdaClient,
+    region: String,
+    function_name: String,
+    alias_name: String,
+    /// The version the alias pointed to before the canary was released.
+    baseline_version: Option<String>,
+    /// The published version being canaried.
+    canary_version: Option<String>,
+}
+
+#[bon]
+impl LambdaAlias {
+    #[builder]
+    pub async fn new(function_name: String, alias_name: String, region: String) -> Self {
+        let config = load_default_aws_config().await;
+        let client = LambdaClient::new(config);
+        Self {
+            client,
+            region,
+            function_name,
+            alias_name,
+            baseline_version: None,
+            canary_version: None,
+        }
+    }
+}
+
+impl LambdaAlias {
+    /// Returns the version the alias points to.
+    async fn current_version(&self) -> Result<String> {
+        alias_version(&self.client, &self.function_name, &self.alias_name)
+            .await
+            .into_diagnostic()?
+            .ok_or(miette!("Alias {} doesn't exist", self.alias_name))
+    }
+
+    /// Point the alias at the given version, routing the given fraction
+    /// of traffic to the additional versions.
+    async fn update_alias(
+        &self,
+        function_version: Option<&str>,
+        weights: HashMap<String, f64>,
+    ) -> Result<()> {
+        let routing_config = AliasRoutingConfiguration::builder()
+            .set_additional_version_weights(Some(weights))
+            .build();
+        self.client
+            .update_alias()
+            .function_name(&self.function_name)
+            .name(&self.alias_name)
+            .set_function_version(function_version.map(ToOwned::to_owned))
+            .routing_config(routing_config)
+            .send()
+            .await
+            .into_diagnostic()?;
+        Ok(())
+    }
+
+    /// Send the given percentage of traffic to the canary.
+    async fn route_to_canary(&self, percent: &WholePercent) -> Result<()> {
+        let canary = self
+            .canary_version
+            .clone()
+            .ok_or(miette!("The canary hasn't been released yet"))?;
+        let weights = HashMap::from([(canary, percent.as_fraction())]);
+        self.update_alias(None, weights).await
+    }
+
+    /// Remove all weighted routing from the alias, so it only serves
+    /// the version it points to.
+    async fn clear_routing(&self) -> Result<()> {
+        self.update_alias(None, HashMap::new()).await
+    }
+}
+
+#[async_trait]
+impl Ingress for LambdaAlias {
+    async fn release_canary(&mut self, platform_id: String) -> Result<()> {
+        debug!(
+            "Releasing canary on Lambda alias {} in {}",
+            self.alias_name, self.region
+        );
+        let canary_version = version_from_arn(&platform_id)?;
+        // Remember which version is currently live, so we know
+        // what the canary is being compared against.
+        let baseline_version = self.current_version().await?;
+        if baseline_version == canary_version {
+            bail!(
+                "The alias {} already points to version {canary_version}",
+                self.alias_name
+            );
+        }
+        self.baseline_version = Some(baseline_version.to_owned());
+        self.canary_version = Some(canary_version);
+        // The first step of the pipeline is to collect baseline
+        // traffic, so the canary starts without any.
+        self.route_to_canary(&WholePercent::try_from(0).unwrap())
+            .await
+    }
+
+    async fn set_canary_traffic(&mut self, percent: WholePercent) -> Result<()> {
+        info!("Setting Lambda alias canary traffic to {percent}.");
+        self.route_to_canary(&percent).await
+    }
+
+    async fn rollback_canary(&mut self) -> Result<()> {
+        info!(
+            "Rolling back canary on Lambda alias to version {}.",
+            self.baseline_version.as_deref().unwrap_or("unknown")
+        );
+        // The alias still points to the baseline, so we just
+        // stop sending traffic to the canary.
+        self.clear_routing().await?;
+        self.canary_version = None;
+        Ok(())
+    }
+
+    async fn promote_canary(&mut self) -> Result<()> {
+        info!("Promoting canary on Lambda alias!");
+        let canary = self
+            .canary_version
+            .clone()
+            .ok_or(miette!("The canary hasn't been released yet"))?;
+        // Point the alias at the canary and remove the weights in one step.
+        self.update_alias(Some(&canary), HashMap::new()).await?;
+        self.baseline_version = Some(canary);
+        self.canary_version = None;
+        Ok(())
+    }
+
+    async fn reattach(&mut self, platform_id: String) -> Result<()> {
+        let canary_version = version_from_arn(&platform_id)?;
+        let baseline_version = self.current_version().await?;
+        debug!(
+            "Reattaching to canary version {canary_version} on Lambda alias {}, with baseline version {baseline_version}",
+            self.alias_name
+        );
+        self.baseline_version = Some(baseline_version.to_owned());
+        // If the alias already points to the canary, it's been promoted.
+        if baseline_version != canary_version {
+            self.canary_version = Some(canary_version);
+        }
+        Ok(())
+    }
+
+    /// The canary is the version the alias routes additional traffic to.
+    /// It's weighted from the moment it's released, even at 0%.
+    async fn discover_canary(&self) -> Result<Option<String>> {
+        let alias = self
+            .client
+            .get_alias()
+            .function_name(&self.function_name)
+            .name(&self.alias_name)
+            .send()
+            .await
+            .into_diagnostic()?;
+        let canary_version = alias
+            .routing_config()
+            .and_then(|routing| routing.additional_version_weights())
+            .and_then(|weights| weights.keys().next());
+        match (alias.alias_arn(), canary_version) {
+            (Some(alias_arn), Some(version)) => Ok(Some(canary_arn(alias_arn, version)?)),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Qualify the function's ARN with the canary's version, in place of
+/// the alias, so it matches the id the platform gave the canary.
+fn canary_arn(alias_arn: &str, version: &str) -> Result<String> {
+    let (function_arn, _) = alias_arn
+        .rsplit_once(':')
+        .ok_or(miette!("Expected an alias ARN, but found {alias_arn}"))?;
+    Ok(format!("{function_arn}:{version}"))
+}
+
+#[async_trait]
+impl Shutdownable for LambdaAlias {
+    async fn shutdown(&mut self) -> ShutdownResult {
+        // When we get the shutdown signal, stop sending traffic to
+        // the canary, if there is one.
+        if self.canary_version.is_some() {
+            self.clear_routing().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::canary_arn;
+    use crate::utils::version_from_arn;
+
+    /// The discovered canary can be reattached like a recorded one.
+    #[test]
+    fn canary_arns_are_qualified_with_the_version() {
+        let alias_arn = "arn:aws:lambda:us-east-2:123456789012:function:my-function:live";
+        let arn = canary_arn(alias_arn, "7").unwrap();
+        assert_eq!(
+            arn,
+            "arn:aws:lambda:us-east-2:123456789012:function:my-function:7"
+        );
+        assert_eq!(version_from_arn(&arn).unwrap(), "7");
+    }
+}
@@ -1,17 +1,23 @@
 use async_trait::async_trait;
-use miette::Result;
+use miette::{Result, bail};
+use mockall::automock;
+use tracing::info;
 
-use crate::{Shutdownable, WholePercent};
+use crate::{Shutdownable, WholePercent, adapters::backend::RolloutId, subsystems::ShutdownResult};
 
 /// Convenience alias since this type is often dynamically
 /// dispatched.
 pub type BoxedIngress = Box<dyn Ingress + Send + Sync>;
 
-pub(crate) use builder::IngressBuilder;
+pub(crate) use builder::{IngressBuilder, LocalIngressConfig};
+pub(crate) use proxy::ControlClient;
+#[cfg(feature = "proxy")]
+pub(crate) use proxy::{ControlServer, ProxyObservations, ProxyRoutes};
 
 /// Ingresses are responsible for (1) controlling how much traffic the canary
 /// gets (hence the name ingress, since it functions like a virtual LB) and
 /// (2) deploying, yanking, and promoting both the canary and the baseline.
+#[automock]
 #[async_trait]
 pub trait Ingress: Shutdownable {
     /// Given a deployed platform, release the canary in the ingress.
@@ -33,15 +39,100 @@ pub trait Ingress: Shutdownable {
     /// the context of the ingress. It does not affect the underlying
     /// rollout.
     async fn promote_canary(&mut self) -> Result<()>;
+    /// Pick up where an earlier run of the rollout left off, given the
+    /// id of the canary it deployed. Ingresses that look up the canary
+    /// on each request have nothing to restore.
+    async fn reattach(&mut self, _platform_id: String) -> Result<()> {
+        Ok(())
+    }
+    /// Look up the id of the canary released in the ingress, for when
+    /// it wasn't recorded by the run that deployed it. Returns `None`
+    /// if there's no canary, or the ingress can't tell which it is.
+    async fn discover_canary(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+#[async_trait]
+impl Shutdownable for MockIngress {
+    async fn shutdown(&mut self) -> ShutdownResult {
+        Ok(())
+    }
+}
+
+/// Find the canary an earlier run deployed for the rollout. Its id is
+/// recorded on the machine that deployed it, but anywhere else, we ask
+/// the ingress. If the backend says the canary was deployed and we
+/// can't find it, we refuse to go on, since it couldn't be promoted
+/// or rolled back.
+pub(crate) async fn find_canary(
+    ingress: &BoxedIngress,
+    rollout_id: RolloutId,
+    recorded: Option<String>,
+    deployed: bool,
+) -> Result<Option<String>> {
+    // • Without a deployed canary, whatever the ingress is
+    //   routing to belongs to some other rollout.
+    if recorded.is_some() || !deployed {
+        return Ok(recorded);
+    }
+    match ingress.discover_canary().await? {
+        Some(canary) => {
+            info!("Found canary {canary} for rollout {rollout_id} in the ingress.");
+            Ok(Some(canary))
+        }
+        None => bail!(
+            "Rollout {rollout_id} deployed a canary, but it isn't recorded on this machine and the ingress isn't routing to one"
+        ),
+    }
 }
 
 mod apig;
 mod builder;
+/// An ingress that shifts traffic using a Lambda alias' weighted routing.
+mod lambda_alias;
+/// The routing table behind `multi proxy`, and an ingress that
+/// controls a running proxy over its control socket.
+mod proxy;
 
 #[cfg(test)]
 mod tests {
-    use super::Ingress;
+    use super::{BoxedIngress, Ingress, MockIngress, find_canary};
+    use pretty_assertions::assert_eq;
     use static_assertions::assert_obj_safe;
 
     assert_obj_safe!(Ingress);
+
+    fn routing_to(canary: Option<&'static str>) -> BoxedIngress {
+        let mut ingress = MockIngress::new();
+        ingress
+            .expect_discover_canary()
+            .returning(move || Ok(canary.map(ToOwned::to_owned)));
+        Box::new(ingress)
+    }
+
+    /// The recorded canary wins, but the ingress knows
+    /// which canary was deployed from another machine.
+    #[tokio::test]
+    async fn find_canaries_in_the_ingress() {
+        let recorded = Some("arn:fn:7".to_owned());
+        let ingress = routing_to(Some("arn:fn:8"));
+        assert_eq!(
+            find_canary(&ingress, 3, recorded.clone(), true)
+                .await
+                .unwrap(),
+            recorded
+        );
+        assert_eq!(
+            find_canary(&ingress, 3, None, true).await.unwrap(),
+            Some("arn:fn:8".to_owned())
+        );
+        // An undeployed rollout has no canary, whatever the ingress says.
+        assert_eq!(find_canary(&ingress, 3, None, false).await.unwrap(), None);
+
+        let err = find_canary(&routing_to(None), 3, None, true)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Rollout 3 deployed a canary"));
+    }
 }
@@ -0,0 +1,321 @@
+use std::{fmt, io, path::PathBuf, str::FromStr};
+
+use async_trait::async_trait;
+use miette::{IntoDiagnostic, Report, Result, bail};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::UnixStream,
+};
+
+use crate::{
+    Shutdownable, WholePercent,
+    adapters::{Ingress, MonitorError, StatusCode},
+    metrics::ResponseStatusCode,
+    stats::Group,
+    subsystems::ShutdownResult,
+};
+
+/// The reply sent when a command succeeds. Failures are
+/// reported as `ERR` followed by the error message.
+pub(super) const OK: &str = "OK";
+pub(super) const ERR: &str = "ERR";
+
+/// The commands accepted over a proxy's control socket, one per line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ControlCommand {
+    /// `RELEASE <addr>`: add the canary upstream, with no traffic.
+    Release(String),
+    /// `TRAFFIC <percent>`: send this percentage of requests to the canary.
+    Traffic(u32),
+    /// `PROMOTE`: make the canary the new baseline.
+    Promote,
+    /// `ROLLBACK`: remove the canary.
+    Rollback,
+    /// `OBSERVE`: reply with the status codes recorded since the
+    /// last `OBSERVE`, baseline first, then canary.
+    Observe,
+}
+
+impl FromStr for ControlCommand {
+    type Err = miette::Report;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let parsed = match (command.to_ascii_uppercase().as_str(), words.next()) {
+            ("RELEASE", Some(addr)) => Self::Release(addr.to_owned()),
+            ("TRAFFIC", Some(percent)) => Self::Traffic(percent.parse().into_diagnostic()?),
+            ("PROMOTE", None) => Self::Promote,
+            ("ROLLBACK", None) => Self::Rollback,
+            ("OBSERVE", None) => Self::Observe,
+            _ => bail!("Unknown command: {line}"),
+        };
+        if words.next().is_some() {
+            bail!("Too many arguments: {line}");
+        }
+        Ok(parsed)
+    }
+}
+
+impl fmt::Display for ControlCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Release(addr) => write!(f, "RELEASE {addr}"),
+            Self::Traffic(percent) => write!(f, "TRAFFIC {percent}"),
+            Self::Promote => write!(f, "PROMOTE"),
+            Self::Rollback => write!(f, "ROLLBACK"),
+            Self::Observe => write!(f, "OBSERVE"),
+        }
+    }
+}
+
+/// The user-facing configuration for the [ProxyControl] ingress.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ProxyControlConfig {
+    /// The path to the control socket of a running `multi proxy`.
+    pub(crate) control_socket: PathBuf,
+    /// The address of the canary upstream. It's required to release
+    /// the canary, since the platform's id for it isn't an address.
+    pub(crate) canary: Option<String>,
+}
+
+/// Format an observation as its counts, separated by commas,
+/// in the order of [ResponseStatusCode::ALL].
+pub(super) fn encode_counts(observation: &StatusCode) -> String {
+    ResponseStatusCode::ALL
+        .iter()
+        .map(|code| observation.get_count(code).to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The inverse of [encode_counts].
+fn decode_counts(group: Group, counts: &str) -> Result<StatusCode> {
+    let counts: Vec<u32> = counts
+        .split(',')
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .into_diagnostic()?;
+    if counts.len() != ResponseStatusCode::ALL.len() {
+        bail!("Expected a count for each status code, but found {counts:?}");
+    }
+    let mut observation = StatusCode::new(group);
+    for (code, count) in ResponseStatusCode::ALL.iter().zip(counts) {
+        observation.increment_by(code, count);
+    }
+    Ok(observation)
+}
+
+/// A [ControlClient] sends commands to a running `multi proxy`.
+/// Each command is sent over its own connection.
+#[derive(Clone, Debug)]
+pub(crate) struct ControlClient {
+    socket: PathBuf,
+}
+
+impl ControlClient {
+    pub(crate) fn new(socket: PathBuf) -> Self {
+        Self { socket }
+    }
+
+    /// Send the command and return the proxy's reply.
+    async fn exchange(&self, command: &ControlCommand) -> io::Result<String> {
+        let stream = UnixStream::connect(&self.socket).await?;
+        let (reader, mut writer) = stream.into_split();
+        writer.write_all(format!("{command}\n").as_bytes()).await?;
+        BufReader::new(reader)
+            .lines()
+            .next_line()
+            .await?
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "The proxy closed the connection without replying",
+                )
+            })
+    }
+
+    /// Send the command and return the body of the reply, which is
+    /// empty unless the command asks for data.
+    async fn send(&self, command: ControlCommand) -> Result<String> {
+        let reply = self.exchange(&command).await.into_diagnostic()?;
+        reply_body(&command, reply)
+    }
+
+    /// Collect the status codes the proxy recorded since the last call,
+    /// for the baseline and the canary. If the proxy can't be reached,
+    /// e.g. while it restarts, the error is [MonitorError::Transient].
+    pub(crate) async fn observe(&self) -> Result<Vec<StatusCode>> {
+        let command = ControlCommand::Observe;
+        let reply = self
+            .exchange(&command)
+            .await
+            .map_err(|err| MonitorError::Transient(format!("Couldn't reach the proxy: {err}")))?;
+        // Once the proxy replies, retrying won't change its answer.
+        let permanent = |err: Report| MonitorError::Permanent(err.to_string());
+        let body = reply_body(&command, reply).map_err(permanent)?;
+        let Some((baseline, canary)) = body.split_once(' ') else {
+            return Err(MonitorError::Permanent(format!(
+                "Expected baseline and canary counts, but found `{body}`"
+            ))
+            .into());
+        };
+        Ok(vec![
+            decode_counts(Group::Control, baseline).map_err(permanent)?,
+            decode_counts(Group::Experimental, canary).map_err(permanent)?,
+        ])
+    }
+}
+
+/// Read the body of the proxy's reply to the command,
+/// or the reason it rejected the command.
+fn reply_body(command: &ControlCommand, reply: String) -> Result<String> {
+    if reply == OK {
+        return Ok(String::new());
+    }
+    match reply.split_once(' ') {
+        Some((OK, body)) => Ok(body.to_owned()),
+        Some((ERR, message)) => bail!("The proxy rejected `{command}`: {message}"),
+        _ => bail!("Unexpected reply from the proxy: {reply}"),
+    }
+}
+
+/// [ProxyControl] is an Ingress that drives a running `multi proxy`
+/// through its control socket.
+pub struct ProxyControl {
+    client: ControlClient,
+    canary: Option<String>,
+}
+
+impl ProxyControl {
+    pub fn new(socket: PathBuf, canary: Option<String>) -> Self {
+        Self {
+            client: ControlClient::new(socket),
+            canary,
+        }
+    }
+
+    async fn send(&self, command: ControlCommand) -> Result<()> {
+        self.client.send(command).await?;
+        Ok(())
+    }
+}
+
+impl From<ProxyControlConfig> for ProxyControl {
+    fn from(config: ProxyControlConfig) -> Self {
+        Self::new(config.control_socket, config.canary)
+    }
+}
+
+#[async_trait]
+impl Ingress for ProxyControl {
+    async fn release_canary(&mut self, platform_id: String) -> Result<()> {
+        let Some(addr) = self.canary.clone() else {
+            bail!(
+                "Can't release canary {platform_id} through the proxy, because its address \
+                isn't known. Set `canary` in the proxy ingress' configuration."
+            );
+        };
+        self.send(ControlCommand::Release(addr)).await
+    }
+
+    async fn set_canary_traffic(&mut self, percent: WholePercent) -> Result<()> {
+        let percent = (percent.as_fraction() * 100.0).round() as u32;
+        self.send(ControlCommand::Traffic(percent)).await
+    }
+
+    async fn rollback_canary(&mut self) -> Result<()> {
+        self.send(ControlCommand::Rollback).await
+    }
+
+    async fn promote_canary(&mut self) -> Result<()> {
+        self.send(ControlCommand::Promote).await
+    }
+}
+
+#[async_trait]
+impl Shutdownable for ProxyControl {
+    async fn shutdown(&mut self) -> ShutdownResult {
+        // The proxy outlives us, so there's nothing to clean up.
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{ControlClient, ControlCommand, ProxyControl, decode_counts, encode_counts};
+    use crate::{
+        adapters::{Ingress, MonitorError, MonitorErrorKind, StatusCode},
+        metrics::ResponseStatusCode,
+        stats::Group,
+    };
+
+    #[test]
+    fn parse_commands() {
+        let test_cases = [
+            (
+                "RELEASE 127.0.0.1:9090",
+                ControlCommand::Release("127.0.0.1:9090".to_owned()),
+            ),
+            ("traffic 25", ControlCommand::Traffic(25)),
+            ("PROMOTE", ControlCommand::Promote),
+            ("ROLLBACK", ControlCommand::Rollback),
+            ("observe", ControlCommand::Observe),
+        ];
+        for (input, expected) in test_cases {
+            let parsed: ControlCommand = input.parse().unwrap();
+            assert_eq!(parsed, expected);
+            // Formatting and parsing round trip.
+            assert_eq!(
+                parsed.to_string().parse::<ControlCommand>().unwrap(),
+                expected
+            );
+        }
+        for input in ["", "TRAFFIC", "TRAFFIC ten", "PROMOTE now", "RESTART"] {
+            assert!(input.parse::<ControlCommand>().is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn counts_round_trip() {
+        let mut observation = StatusCode::new(Group::Experimental);
+        observation.increment_by(&ResponseStatusCode::_2XX, 120);
+        observation.increment_by(&ResponseStatusCode::_5XX, 3);
+        let encoded = encode_counts(&observation);
+        assert_eq!(encoded, "0,120,0,0,3");
+        let decoded = decode_counts(Group::Experimental, &encoded).unwrap();
+        assert_eq!(decoded.group(), Group::Experimental);
+        assert_eq!(decoded.get_count(&ResponseStatusCode::_2XX), 120);
+        assert_eq!(decoded.get_count(&ResponseStatusCode::_5XX), 3);
+        assert_eq!(decoded.total(), 123);
+        for input in ["", "1,2,3,4", "1,2,3,4,5,6", "1,2,three,4,5"] {
+            assert!(decode_counts(Group::Control, input).is_err(), "{input}");
+        }
+    }
+
+    /// Without a configured address, the canary can't be released.
+    #[tokio::test]
+    async fn release_requires_an_address() {
+        let socket = std::env::temp_dir().join("multi-proxy-missing.sock");
+        let mut control = ProxyControl::new(socket, None);
+        let err = control
+            .release_canary(
+                "arn:aws:lambda:us-east-2:123456789012:function:my-function:7".to_owned(),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Set `canary`"));
+    }
+
+    /// A proxy that's down, e.g. while it restarts, is worth retrying.
+    #[tokio::test]
+    async fn unreachable_proxy_is_transient() {
+        let socket = std::env::temp_dir().join("multi-proxy-unreachable.sock");
+        let err = ControlClient::new(socket).observe().await.unwrap_err();
+        assert_eq!(MonitorError::kind_of(&err), MonitorErrorKind::Transient);
+        assert!(err.downcast_ref::<MonitorError>().is_some());
+    }
+}
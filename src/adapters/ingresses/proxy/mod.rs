@@ -0,0 +1,20 @@
+pub(crate) use control::{ControlClient, ProxyControl, ProxyControlConfig};
+#[cfg(feature = "proxy")]
+pub(crate) use observations::ProxyObservations;
+#[cfg(feature = "proxy")]
+pub(crate) use routes::ProxyRoutes;
+#[cfg(feature = "proxy")]
+pub(crate) use server::ControlServer;
+
+/// The line-based protocol for controlling a running proxy over
+/// a Unix socket, and the [Ingress](super::Ingress) that speaks it.
+mod control;
+/// The status codes of the responses sent by the proxy.
+#[cfg(feature = "proxy")]
+mod observations;
+/// The proxy's routing table.
+#[cfg(feature = "proxy")]
+mod routes;
+/// The proxy's end of the control socket.
+#[cfg(feature = "proxy")]
+mod server;
@@ -0,0 +1,95 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{adapters::StatusCode, metrics::ResponseStatusCode, stats::Group};
+
+/// [ProxyObservations] tallies the status codes of the responses the
+/// proxy sends, separately for the baseline and the canary. Like
+/// [ProxyRoutes](super::ProxyRoutes), every clone shares the same tally.
+#[derive(Clone)]
+pub struct ProxyObservations {
+    inner: Arc<Mutex<Tally>>,
+}
+
+struct Tally {
+    baseline: StatusCode,
+    canary: StatusCode,
+}
+
+impl Tally {
+    fn new() -> Self {
+        Self {
+            baseline: StatusCode::new(Group::Control),
+            canary: StatusCode::new(Group::Experimental),
+        }
+    }
+}
+
+impl ProxyObservations {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Tally::new())),
+        }
+    }
+
+    /// Count a response sent by the given group's upstream.
+    /// Codes outside of the 100-599 range are ignored.
+    pub fn record(&self, group: Group, status: u16) {
+        let Some(code) = ResponseStatusCode::from_status(status) else {
+            return;
+        };
+        let mut tally = self.tally();
+        match group {
+            Group::Control => tally.baseline.increment_by(&code, 1),
+            Group::Experimental => tally.canary.increment_by(&code, 1),
+        }
+    }
+
+    /// Return everything recorded so far, baseline first, and start
+    /// counting from zero again.
+    pub fn take(&self) -> (StatusCode, StatusCode) {
+        let tally = std::mem::replace(&mut *self.tally(), Tally::new());
+        (tally.baseline, tally.canary)
+    }
+
+    fn tally(&self) -> std::sync::MutexGuard<'_, Tally> {
+        // Each count is a single increment, so the tally is
+        // consistent even if a holder of the lock panicked.
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Default for ProxyObservations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::ProxyObservations;
+    use crate::{metrics::ResponseStatusCode, stats::Group};
+
+    #[test]
+    fn record_and_take() {
+        let observations = ProxyObservations::new();
+        let recorder = observations.clone();
+        recorder.record(Group::Control, 200);
+        recorder.record(Group::Control, 204);
+        recorder.record(Group::Experimental, 503);
+        recorder.record(Group::Experimental, 999);
+
+        let (baseline, canary) = observations.take();
+        assert_eq!(baseline.group(), Group::Control);
+        assert_eq!(baseline.get_count(&ResponseStatusCode::_2XX), 2);
+        assert_eq!(baseline.total(), 2);
+        assert_eq!(canary.group(), Group::Experimental);
+        assert_eq!(canary.get_count(&ResponseStatusCode::_5XX), 1);
+        assert_eq!(canary.total(), 1);
+
+        // Taking the observations resets the tally.
+        let (baseline, canary) = observations.take();
+        assert_eq!(baseline.total() + canary.total(), 0);
+    }
+}
@@ -0,0 +1,220 @@
+use std::sync::{
+    Arc, RwLock,
+    atomic::{AtomicU32, Ordering},
+};
+
+use async_trait::async_trait;
+use miette::{Result, miette};
+use tracing::info;
+
+use crate::{Shutdownable, WholePercent, stats::Group, subsystems::ShutdownResult};
+
+use crate::adapters::Ingress;
+
+/// [ProxyRoutes] is the routing table for `multi proxy`. It decides which
+/// upstream serves each request. Cloning it is cheap, and every clone
+/// shares the same table, so the proxy's request handlers see changes
+/// made through the [Ingress] trait immediately.
+#[derive(Clone)]
+pub struct ProxyRoutes {
+    inner: Arc<RoutesInner>,
+}
+
+struct RoutesInner {
+    /// The percentage of requests sent to the canary, between 0 and 100.
+    canary_percent: AtomicU32,
+    upstreams: RwLock<Upstreams>,
+}
+
+#[derive(Clone)]
+struct Upstreams {
+    baseline: String,
+    canary: Option<String>,
+}
+
+impl ProxyRoutes {
+    pub fn new(baseline: String, canary: Option<String>, canary_percent: WholePercent) -> Self {
+        let upstreams = RwLock::new(Upstreams { baseline, canary });
+        Self {
+            inner: Arc::new(RoutesInner {
+                canary_percent: AtomicU32::new(whole_percent_to_u32(&canary_percent)),
+                upstreams,
+            }),
+        }
+    }
+
+    /// Pick the upstream for a request. `draw` must be uniformly
+    /// distributed between 0 and 99. The request goes to the canary
+    /// if `draw` falls below the canary's share of traffic.
+    pub fn choose(&self, draw: u32) -> (String, Group) {
+        let upstreams = self.upstreams();
+        let percent = self.inner.canary_percent.load(Ordering::Relaxed);
+        match upstreams.canary {
+            Some(canary) if draw < percent => (canary, Group::Experimental),
+            _ => (upstreams.baseline, Group::Control),
+        }
+    }
+
+    /// The percentage of requests currently sent to the canary.
+    pub fn canary_percent(&self) -> WholePercent {
+        WholePercent::try_from(self.inner.canary_percent.load(Ordering::Relaxed)).unwrap()
+    }
+
+    fn upstreams(&self) -> Upstreams {
+        // If a writer panicked, the table is still usable, since
+        // every write replaces a field wholesale.
+        self.inner
+            .upstreams
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Upstreams)) {
+        let mut upstreams = self
+            .inner
+            .upstreams
+            .write()
+            .unwrap_or_else(|err| err.into_inner());
+        f(&mut upstreams);
+    }
+
+    fn set_percent(&self, percent: &WholePercent) {
+        self.inner
+            .canary_percent
+            .store(whole_percent_to_u32(percent), Ordering::Relaxed);
+    }
+}
+
+fn whole_percent_to_u32(percent: &WholePercent) -> u32 {
+    (percent.as_fraction() * 100.0).round() as u32
+}
+
+#[async_trait]
+impl Ingress for ProxyRoutes {
+    /// The platform id is the address of the canary upstream.
+    async fn release_canary(&mut self, platform_id: String) -> Result<()> {
+        info!("Releasing canary at {platform_id} with no traffic.");
+        self.set_percent(&WholePercent::try_from(0).unwrap());
+        self.update(|upstreams| upstreams.canary = Some(platform_id));
+        Ok(())
+    }
+
+    async fn set_canary_traffic(&mut self, percent: WholePercent) -> Result<()> {
+        if self.upstreams().canary.is_none() {
+            return Err(miette!("There's no canary to send traffic to"));
+        }
+        info!("Setting proxy canary traffic to {percent}.");
+        self.set_percent(&percent);
+        Ok(())
+    }
+
+    async fn rollback_canary(&mut self) -> Result<()> {
+        info!("Rolling back the proxy's canary.");
+        self.set_percent(&WholePercent::try_from(0).unwrap());
+        self.update(|upstreams| upstreams.canary = None);
+        Ok(())
+    }
+
+    async fn promote_canary(&mut self) -> Result<()> {
+        let mut promoted = Err(miette!("There's no canary to promote"));
+        // Stop splitting traffic before the canary becomes the
+        // baseline, so no requests are routed to a missing canary.
+        self.set_percent(&WholePercent::try_from(0).unwrap());
+        self.update(|upstreams| {
+            if let Some(canary) = upstreams.canary.take() {
+                info!("Promoting canary at {canary} to baseline.");
+                upstreams.baseline = canary;
+                promoted = Ok(());
+            }
+        });
+        promoted
+    }
+}
+
+#[async_trait]
+impl Shutdownable for ProxyRoutes {
+    async fn shutdown(&mut self) -> ShutdownResult {
+        // The routes live as long as the proxy does.
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::ProxyRoutes;
+    use crate::{WholePercent, adapters::Ingress, stats::Group};
+
+    fn percent(value: u32) -> WholePercent {
+        WholePercent::try_from(value).unwrap()
+    }
+
+    fn routes() -> ProxyRoutes {
+        ProxyRoutes::new("127.0.0.1:8080".to_owned(), None, percent(0))
+    }
+
+    #[test]
+    fn choose_respects_canary_percent() {
+        let routes = ProxyRoutes::new(
+            "baseline:80".to_owned(),
+            Some("canary:80".to_owned()),
+            percent(25),
+        );
+        assert_eq!(
+            routes.choose(0),
+            ("canary:80".to_owned(), Group::Experimental)
+        );
+        assert_eq!(
+            routes.choose(24),
+            ("canary:80".to_owned(), Group::Experimental)
+        );
+        assert_eq!(
+            routes.choose(25),
+            ("baseline:80".to_owned(), Group::Control)
+        );
+        assert_eq!(
+            routes.choose(99),
+            ("baseline:80".to_owned(), Group::Control)
+        );
+    }
+
+    #[tokio::test]
+    async fn release_and_promote() {
+        let mut routes = routes();
+        // Every clone shares the same table.
+        let observer = routes.clone();
+        assert!(routes.set_canary_traffic(percent(10)).await.is_err());
+        routes
+            .release_canary("127.0.0.1:9090".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(observer.choose(0).1, Group::Control);
+        routes.set_canary_traffic(percent(50)).await.unwrap();
+        assert_eq!(observer.canary_percent().to_string(), "50%");
+        assert_eq!(observer.choose(49).0, "127.0.0.1:9090");
+        routes.promote_canary().await.unwrap();
+        assert_eq!(
+            observer.choose(0),
+            ("127.0.0.1:9090".to_owned(), Group::Control)
+        );
+        assert!(routes.promote_canary().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rollback_removes_canary() {
+        let mut routes = routes();
+        routes
+            .release_canary("127.0.0.1:9090".to_owned())
+            .await
+            .unwrap();
+        routes.set_canary_traffic(percent(100)).await.unwrap();
+        routes.rollback_canary().await.unwrap();
+        assert_eq!(
+            routes.choose(0),
+            ("127.0.0.1:8080".to_owned(), Group::Control)
+        );
+        assert_eq!(routes.canary_percent().to_string(), "0%");
+    }
+}
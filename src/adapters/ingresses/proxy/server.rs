@@ -0,0 +1,173 @@
+use std::path::PathBuf;
+
+use miette::{IntoDiagnostic, Result};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+use tracing::{debug, info, warn};
+
+use crate::{WholePercent, adapters::Ingress};
+
+use super::{
+    ProxyObservations, ProxyRoutes,
+    control::{ControlCommand, ERR, OK, encode_counts},
+};
+
+/// The [ControlServer] listens on a Unix socket and applies the
+/// commands it receives to the proxy's routes. It also reports
+/// the proxy's observations to anyone who asks.
+pub(crate) struct ControlServer {
+    routes: ProxyRoutes,
+    observations: ProxyObservations,
+    path: PathBuf,
+}
+
+impl ControlServer {
+    pub(crate) fn new(routes: ProxyRoutes, observations: ProxyObservations, path: PathBuf) -> Self {
+        Self {
+            routes,
+            observations,
+            path,
+        }
+    }
+
+    /// Accept connections until an error occurs.
+    pub(crate) async fn serve(self) -> Result<()> {
+        // Clean up the socket left behind by a previous run.
+        match std::fs::remove_file(&self.path) {
+            Ok(_) => debug!("Removed stale control socket"),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+            Err(err) => return Err(err).into_diagnostic(),
+        }
+        let listener = UnixListener::bind(&self.path).into_diagnostic()?;
+        info!("Listening for commands on {}", self.path.display());
+        loop {
+            let (stream, _) = listener.accept().await.into_diagnostic()?;
+            let routes = self.routes.clone();
+            let observations = self.observations.clone();
+            tokio::spawn(async move {
+                if let Err(err) = handle_connection(routes, observations, stream).await {
+                    warn!("Control connection failed: {err}");
+                }
+            });
+        }
+    }
+}
+
+async fn handle_connection(
+    mut routes: ProxyRoutes,
+    observations: ProxyObservations,
+    stream: UnixStream,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await.into_diagnostic()? {
+        let reply = match apply(&mut routes, &observations, &line).await {
+            Ok(None) => OK.to_owned(),
+            Ok(Some(body)) => format!("{OK} {body}"),
+            Err(err) => format!("{ERR} {err}"),
+        };
+        writer
+            .write_all(format!("{reply}\n").as_bytes())
+            .await
+            .into_diagnostic()?;
+    }
+    Ok(())
+}
+
+/// Apply the command, returning the body of the reply, if any.
+async fn apply(
+    routes: &mut ProxyRoutes,
+    observations: &ProxyObservations,
+    line: &str,
+) -> Result<Option<String>> {
+    match line.parse()? {
+        ControlCommand::Release(addr) => routes.release_canary(addr).await?,
+        ControlCommand::Traffic(percent) => {
+            let percent = WholePercent::try_from(percent).into_diagnostic()?;
+            routes.set_canary_traffic(percent).await?
+        }
+        ControlCommand::Promote => routes.promote_canary().await?,
+        ControlCommand::Rollback => routes.rollback_canary().await?,
+        ControlCommand::Observe => {
+            let (baseline, canary) = observations.take();
+            let body = format!("{} {}", encode_counts(&baseline), encode_counts(&canary));
+            return Ok(Some(body));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use super::ControlServer;
+    use crate::{
+        WholePercent,
+        adapters::{
+            Ingress,
+            ingresses::proxy::{ControlClient, ProxyControl, ProxyObservations, ProxyRoutes},
+        },
+        metrics::ResponseStatusCode,
+        stats::Group,
+    };
+
+    #[tokio::test]
+    async fn control_proxy_over_socket() {
+        let socket = std::env::temp_dir().join(format!("multi-proxy-{}.sock", std::process::id()));
+        let routes = ProxyRoutes::new(
+            "127.0.0.1:8080".to_owned(),
+            None,
+            WholePercent::try_from(0).unwrap(),
+        );
+        let observations = ProxyObservations::new();
+        let server = ControlServer::new(routes.clone(), observations.clone(), socket.clone());
+        let server = tokio::spawn(server.serve());
+        // Wait for the server to bind the socket.
+        while !socket.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let mut control = ProxyControl::new(socket.clone(), Some("127.0.0.1:9090".to_owned()));
+        control
+            .release_canary("my-canary".to_owned())
+            .await
+            .unwrap();
+        control
+            .set_canary_traffic(WholePercent::try_from(30).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            routes.choose(29),
+            ("127.0.0.1:9090".to_owned(), Group::Experimental)
+        );
+        assert_eq!(routes.choose(30).1, Group::Control);
+        control.promote_canary().await.unwrap();
+        assert_eq!(
+            routes.choose(0),
+            ("127.0.0.1:9090".to_owned(), Group::Control)
+        );
+        // Errors are reported back to the client.
+        assert!(control.promote_canary().await.is_err());
+
+        // Observations are drained by each request.
+        observations.record(Group::Control, 200);
+        observations.record(Group::Experimental, 500);
+        let client = ControlClient::new(socket.clone());
+        let observed = client.observe().await.unwrap();
+        assert_eq!(observed.len(), 2);
+        assert_eq!(observed[0].group(), Group::Control);
+        assert_eq!(observed[0].get_count(&ResponseStatusCode::_2XX), 1);
+        assert_eq!(observed[1].group(), Group::Experimental);
+        assert_eq!(observed[1].get_count(&ResponseStatusCode::_5XX), 1);
+        let observed = client.observe().await.unwrap();
+        assert_eq!(observed[0].total() + observed[1].total(), 0);
+
+        server.abort();
+        let _ = std::fs::remove_file(socket);
+    }
+}
@@ -1,14 +1,28 @@
 pub use backend::{ApplicationConfig, BackendClient};
-pub(crate) use backend::{LockedState, RolloutMetadata};
+pub(crate) use backend::{
+    LocalBackend, LockedState, PolicyConfig, RolloutMetadata, SharedBackend, TargetState,
+};
+pub(crate) use events::{
+    BoxedEventSink, EventRecord, EventSinkConfig, Events, RolloutEvent, WebhookConfig,
+};
+pub(crate) use hooks::{HookContext, HookStage, HooksConfig};
+pub(crate) use probes::ProbeConfig;
 
 pub use ingresses::*;
 pub use monitors::*;
 pub use platforms::*;
 
 pub mod backend;
+/// Typed events describing a rollout's progress, and the sinks
+/// they're delivered to.
+mod events;
+/// Shell commands the user runs around each state of a rollout.
+mod hooks;
 /// Contains the trait definition and ingress implementations. Ingresses are responsible
 /// for actuating changes to traffic.
 mod ingresses;
 /// Contains the trait definition for gathering monitoring data.
 mod monitors;
 mod platforms;
+/// Synthetic requests that check the canary before it receives traffic.
+mod probes;
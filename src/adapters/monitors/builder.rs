@@ -1,8 +1,33 @@
 use super::cloudwatch::CloudWatch;
+use super::cloudwatch_query::{CloudWatchQuery, CloudWatchQueryConfig};
+use super::lambda::{LambdaMonitor, LambdaMonitorConfig};
+use super::prometheus::{Prometheus, PrometheusConfig};
+use super::proxy::{ProxyMonitor, ProxyMonitorConfig};
 use async_trait::async_trait;
 use multitool_sdk::models::{MonitorConfig, MonitorConfigOneOfAwsCloudwatchMetrics};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
 
-use super::BoxedMonitor;
+use super::{BoxedMonitor, CanaryWatch};
+
+/// The monitors that can be configured locally. This is a superset
+/// of the monitors the backend knows about, since some monitors, like
+/// Prometheus, are only available when running without the backend.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub(crate) enum LocalMonitorConfig {
+    Prometheus { prometheus: PrometheusConfig },
+    Proxy { proxy: ProxyMonitorConfig },
+    CloudWatchQuery { cloudwatch: CloudWatchQueryConfig },
+    Lambda { lambda: LambdaMonitorConfig },
+    Backend(MonitorConfig),
+}
+
+impl From<MonitorConfig> for LocalMonitorConfig {
+    fn from(config: MonitorConfig) -> Self {
+        Self::Backend(config)
+    }
+}
 
 #[async_trait]
 trait Builder {
@@ -10,12 +35,23 @@ trait Builder {
 }
 
 pub(crate) struct MonitorBuilder {
-    config: MonitorConfig,
+    config: LocalMonitorConfig,
+    canary: Option<CanaryWatch>,
 }
 
 impl MonitorBuilder {
-    pub(crate) fn new(config: MonitorConfig) -> Self {
-        Self { config }
+    pub(crate) fn new(config: impl Into<LocalMonitorConfig>) -> Self {
+        Self {
+            config: config.into(),
+            canary: None,
+        }
+    }
+
+    /// Tell the monitor which canary was deployed, for monitors
+    /// that can't tell it apart from the baseline on their own.
+    pub(crate) fn with_canary(mut self, canary: CanaryWatch) -> Self {
+        self.canary = Some(canary);
+        self
     }
 
     pub async fn build(self) -> BoxedMonitor {
@@ -27,11 +63,23 @@ impl MonitorBuilder {
 impl Builder for MonitorBuilder {
     async fn build(self) -> BoxedMonitor {
         match self.config {
-            MonitorConfig::MonitorConfigOneOf(monitor_config) => {
+            LocalMonitorConfig::Backend(MonitorConfig::MonitorConfigOneOf(monitor_config)) => {
                 AwsCloudwatchMetricsMonitorBuilder::new(*monitor_config.aws_cloudwatch_metrics)
                     .build()
                     .await
             }
+            LocalMonitorConfig::Prometheus { prometheus } => {
+                PrometheusMonitorBuilder::new(prometheus).build().await
+            }
+            LocalMonitorConfig::Proxy { proxy } => ProxyMonitorBuilder::new(proxy).build().await,
+            LocalMonitorConfig::CloudWatchQuery { cloudwatch } => {
+                CloudWatchQueryMonitorBuilder::new(cloudwatch).build().await
+            }
+            LocalMonitorConfig::Lambda { lambda } => {
+                // Without being told, the monitor never sees a canary.
+                let canary = self.canary.unwrap_or_else(|| watch::channel(None).1);
+                LambdaMonitorBuilder::new(lambda, canary).build().await
+            }
         }
     }
 }
@@ -60,13 +108,82 @@ impl Builder for AwsCloudwatchMetricsMonitorBuilder {
     }
 }
 
+struct PrometheusMonitorBuilder {
+    conf: PrometheusConfig,
+}
+
+impl PrometheusMonitorBuilder {
+    fn new(conf: PrometheusConfig) -> Self {
+        Self { conf }
+    }
+}
+
+#[async_trait]
+impl Builder for PrometheusMonitorBuilder {
+    async fn build(self) -> BoxedMonitor {
+        Box::new(Prometheus::from(self.conf))
+    }
+}
+
+struct ProxyMonitorBuilder {
+    conf: ProxyMonitorConfig,
+}
+
+impl ProxyMonitorBuilder {
+    fn new(conf: ProxyMonitorConfig) -> Self {
+        Self { conf }
+    }
+}
+
+#[async_trait]
+impl Builder for ProxyMonitorBuilder {
+    async fn build(self) -> BoxedMonitor {
+        Box::new(ProxyMonitor::from(self.conf))
+    }
+}
+
+struct CloudWatchQueryMonitorBuilder {
+    conf: CloudWatchQueryConfig,
+}
+
+impl CloudWatchQueryMonitorBuilder {
+    fn new(conf: CloudWatchQueryConfig) -> Self {
+        Self { conf }
+    }
+}
+
+#[async_trait]
+impl Builder for CloudWatchQueryMonitorBuilder {
+    async fn build(self) -> BoxedMonitor {
+        Box::new(CloudWatchQuery::new(self.conf).await)
+    }
+}
+
+struct LambdaMonitorBuilder {
+    conf: LambdaMonitorConfig,
+    canary: CanaryWatch,
+}
+
+impl LambdaMonitorBuilder {
+    fn new(conf: LambdaMonitorConfig, canary: CanaryWatch) -> Self {
+        Self { conf, canary }
+    }
+}
+
+#[async_trait]
+impl Builder for LambdaMonitorBuilder {
+    async fn build(self) -> BoxedMonitor {
+        Box::new(LambdaMonitor::new(self.conf, self.canary).await)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use miette::{IntoDiagnostic, Result};
     use multitool_sdk::models::MonitorConfig;
     use serde_json::{Value, json};
 
-    use super::MonitorBuilder;
+    use super::{LocalMonitorConfig, MonitorBuilder};
     use crate::adapters::BoxedMonitor;
 
     // TODO: I think we're going to need a LogGroup here.
@@ -89,4 +206,75 @@ mod tests {
         let _: BoxedMonitor = MonitorBuilder::new(config_object).build().await;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn parse_local_monitor_config() -> Result<()> {
+        let prometheus = json!({
+            "prometheus": {
+                "url": "http://localhost:9090",
+                "baseline_selector": "track=\"stable\"",
+                "canary_selector": "track=\"canary\"",
+            }
+        });
+        let config: LocalMonitorConfig = serde_json::from_value(prometheus).into_diagnostic()?;
+        assert!(matches!(config, LocalMonitorConfig::Prometheus { .. }));
+        let cloudwatch: LocalMonitorConfig =
+            serde_json::from_value(monitor_json()).into_diagnostic()?;
+        assert!(matches!(cloudwatch, LocalMonitorConfig::Backend(_)));
+        let _: BoxedMonitor = MonitorBuilder::new(config).build().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parse_proxy_monitor_config() -> Result<()> {
+        let proxy = json!({
+            "proxy": {
+                "control_socket": "/tmp/multi-proxy.sock",
+            }
+        });
+        let config: LocalMonitorConfig = serde_json::from_value(proxy).into_diagnostic()?;
+        assert!(matches!(config, LocalMonitorConfig::Proxy { .. }));
+        let _: BoxedMonitor = MonitorBuilder::new(config).build().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parse_cloudwatch_query_monitor_config() -> Result<()> {
+        let cloudwatch = json!({
+            "cloudwatch": {
+                "region": "us-east-2",
+                "baseline": [{
+                    "status": "5XX",
+                    "namespace": "AWS/ApplicationELB",
+                    "metric": "HTTPCode_Target_5XX_Count",
+                    "dimensions": { "TargetGroup": "targetgroup/blue/1234" },
+                }],
+                "canary": [{
+                    "status": "5XX",
+                    "namespace": "AWS/ApplicationELB",
+                    "metric": "HTTPCode_Target_5XX_Count",
+                    "dimensions": { "TargetGroup": "targetgroup/green/5678" },
+                }],
+            }
+        });
+        let config: LocalMonitorConfig = serde_json::from_value(cloudwatch).into_diagnostic()?;
+        assert!(matches!(config, LocalMonitorConfig::CloudWatchQuery { .. }));
+        let _: BoxedMonitor = MonitorBuilder::new(config).build().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn parse_lambda_monitor_config() -> Result<()> {
+        let lambda = json!({
+            "lambda": {
+                "region": "us-east-2",
+                "name": "api",
+                "keyed_by": "resource",
+            }
+        });
+        let config: LocalMonitorConfig = serde_json::from_value(lambda).into_diagnostic()?;
+        assert!(matches!(config, LocalMonitorConfig::Lambda { .. }));
+        let _: BoxedMonitor = MonitorBuilder::new(config).build().await;
+        Ok(())
+    }
 }
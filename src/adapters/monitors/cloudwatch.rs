@@ -7,8 +7,8 @@ use tracing::{debug, info, warn};
 
 use crate::{
     Shutdownable,
-    metrics::ResponseStatusCode,
-    stats::{CategoricalObservation, Group},
+    metrics::{LatencyBucket, ResponseStatusCode},
+    stats::{Categorical, CategoricalObservation, Group},
     subsystems::ShutdownResult,
     utils::load_default_aws_config,
 };
@@ -20,7 +20,7 @@ use aws_smithy_types::DateTime as AwsDateTime;
 use chrono::{DateTime, Duration, TimeDelta, Utc};
 use miette::Result;
 
-use super::Monitor;
+use super::{Latency, Measurement, Monitor, MonitorError, query_window};
 
 pub struct CloudWatch {
     client: AwsClient,
@@ -76,6 +76,51 @@ impl ApiMetric {
     }
 }
 
+/// API Gateway's latency metrics, both reported in milliseconds.
+#[derive(Debug, Clone, Copy)]
+pub enum LatencyMetric {
+    /// The time between receiving a request and responding to it.
+    Latency,
+    /// The time spent waiting on the integration, e.g. the Lambda.
+    IntegrationLatency,
+}
+
+impl LatencyMetric {
+    // Returns the value as a valid AWS query id prefix.
+    pub fn to_id(&self) -> &'static str {
+        match self {
+            LatencyMetric::Latency => "latency",
+            LatencyMetric::IntegrationLatency => "integrationlatency",
+        }
+    }
+
+    pub fn to_metric_name(&self) -> &'static str {
+        match self {
+            LatencyMetric::Latency => "Latency",
+            LatencyMetric::IntegrationLatency => "IntegrationLatency",
+        }
+    }
+
+    fn into_measurement(self, observation: Latency) -> Measurement {
+        match self {
+            LatencyMetric::Latency => Measurement::Latency(observation),
+            LatencyMetric::IntegrationLatency => Measurement::IntegrationLatency(observation),
+        }
+    }
+}
+
+/// The CloudWatch statistic counting the requests whose latency
+/// falls within the bucket, e.g. `TC(10:25)`. Missing bounds are
+/// left empty, so the first and last buckets are open-ended.
+fn trimmed_count(bucket: &LatencyBucket) -> String {
+    let bound = |bound: Option<u32>| bound.map(|ms| ms.to_string()).unwrap_or_default();
+    format!(
+        "TC({}:{})",
+        bound(bucket.lower_ms()),
+        bound(bucket.upper_ms())
+    )
+}
+
 impl CloudWatch {
     // The default name AWS currently uses for canary stages in APIGs
     const CANARY_STAGE_SUFFIX: &'static str = "/Canary";
@@ -89,6 +134,91 @@ impl CloudWatch {
         }
     }
 
+    /// The given API Gateway metric for the group's stage.
+    fn api_metric(
+        metric_name: &str,
+        api_gateway_name: &str,
+        stage_name: &str,
+        group: Group,
+    ) -> Metric {
+        Metric::builder()
+            .namespace("AWS/ApiGateway")
+            .metric_name(metric_name)
+            .dimensions(
+                Dimension::builder()
+                    .name("ApiName")
+                    .value(api_gateway_name)
+                    .build(),
+            )
+            .dimensions(
+                Dimension::builder()
+                    .name("Stage")
+                    .value(Self::get_stage_name(stage_name, group))
+                    .build(),
+            )
+            .build()
+    }
+
+    /// Count how many of the group's requests fell into each latency
+    /// bucket, using one query per bucket.
+    async fn query_latency(
+        &self,
+        metric_name: LatencyMetric,
+        api_gateway_name: &str,
+        stage_name: &str,
+        group: Group,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Latency> {
+        let metric = Self::api_metric(
+            metric_name.to_metric_name(),
+            api_gateway_name,
+            stage_name,
+            group,
+        );
+        let buckets: Vec<_> = LatencyBucket::all().collect();
+        let queries = buckets
+            .iter()
+            .map(|bucket| {
+                MetricDataQuery::builder()
+                    .id(format!("{}{}", metric_name.to_id(), bucket.category()))
+                    .metric_stat(
+                        MetricStat::builder()
+                            .metric(metric.clone())
+                            .period(60)
+                            .stat(trimmed_count(bucket))
+                            .build(),
+                    )
+                    .build()
+            })
+            .collect();
+
+        let response = self
+            .client
+            .get_metric_data()
+            .start_time(AwsDateTime::from_secs(start.timestamp()))
+            .end_time(AwsDateTime::from_secs(end.timestamp()))
+            .set_metric_data_queries(Some(queries))
+            .send()
+            .await;
+
+        let response = response.map_err(MonitorError::from_sdk)?;
+        let mut observation = Latency::new(group);
+        for result in response.metric_data_results() {
+            let Some(bucket) = result
+                .id()
+                .and_then(|id| id.strip_prefix(metric_name.to_id()))
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| buckets.get(index))
+            else {
+                continue;
+            };
+            let count = result.values().iter().sum::<f64>();
+            observation.increment_by(bucket, count.round() as u32);
+        }
+        Ok(observation)
+    }
+
     async fn query_cloudwatch(
         &self,
         metric_name: ApiMetric,
@@ -108,24 +238,12 @@ impl CloudWatch {
             .id(metric_name.to_id())
             .metric_stat(
                 MetricStat::builder()
-                    .metric(
-                        Metric::builder()
-                            .namespace("AWS/ApiGateway")
-                            .metric_name(metric_name.to_metric_name())
-                            .dimensions(
-                                Dimension::builder()
-                                    .name("ApiName")
-                                    .value(api_gateway_name)
-                                    .build(),
-                            )
-                            .dimensions(
-                                Dimension::builder()
-                                    .name("Stage")
-                                    .value(Self::get_stage_name(stage_name, group))
-                                    .build(),
-                            )
-                            .build(),
-                    )
+                    .metric(Self::api_metric(
+                        metric_name.to_metric_name(),
+                        api_gateway_name,
+                        stage_name,
+                        group,
+                    ))
                     .period(60)
                     .stat("Sum")
                     .build(),
@@ -143,25 +261,16 @@ impl CloudWatch {
             .send()
             .await;
 
-        // If there's an error, just return 0 results
-        match response {
-            Ok(response) => {
-                // We need to sum all values provided since we have a period of 60s and time window of 5 mins,
-                // so, in the worst case we get 0 values and in the best case we get 5 values
-                Ok(response
-                    .metric_data_results()
-                    .iter()
-                    .flat_map(|result| result.values())
-                    .sum::<f64>() as u32)
-            }
-            Err(err) => {
-                println!(
-                    "Error querying cloudwatch metrics for {:?} {:?}: {:?}",
-                    metric_name, group, err
-                );
-                Ok(0)
-            }
-        }
+        // An error is reported as such, rather than as zero requests,
+        // which would make a broken query look like a healthy canary.
+        let response = response.map_err(MonitorError::from_sdk)?;
+        // We need to sum all values provided since we have a period of 60s and time window of 5 mins,
+        // so, in the worst case we get 0 values and in the best case we get 5 values
+        Ok(response
+            .metric_data_results()
+            .iter()
+            .flat_map(|result| result.values())
+            .sum::<f64>() as u32)
     }
 
     /// Checks if the number of metrics collected is low (< 20 per given period) and warn the user
@@ -186,27 +295,16 @@ impl CloudWatch {
             );
         }
     }
-}
-
-#[async_trait]
-impl Shutdownable for CloudWatch {
-    async fn shutdown(&mut self) -> ShutdownResult {
-        // When we get the shutdown signal, all we need to do is not query CloudWatch
-        Ok(())
-    }
-}
-
-#[async_trait]
-impl Monitor for CloudWatch {
-    type Item = CategoricalObservation<5, ResponseStatusCode>;
-    async fn query(&mut self) -> Result<Vec<Self::Item>> {
-        info!("Querying CloudWatch for new metrics.");
-        // This function queries the metrics that we care most about (2xx, 4xx, and 5xx errors),
-        // compiles them into a list, then generates the correct number of
-        // CategoricalObservations for each response code
-        let end_query_time: DateTime<Utc> = Utc::now();
-        let start_query_time = self.last_query_time;
 
+    // This function queries the metrics that we care most about (2xx, 4xx, and 5xx errors),
+    // compiles them into a list, then generates the correct number of
+    // CategoricalObservations for each response code. Alongside,
+    // it counts the requests in each latency bucket.
+    async fn collect(
+        &self,
+        start_query_time: DateTime<Utc>,
+        end_query_time: DateTime<Utc>,
+    ) -> Result<Vec<Measurement>> {
         let control_count_future = self.query_cloudwatch(
             ApiMetric::Count,
             self.dimensions[0].value.as_ref(),
@@ -261,6 +359,20 @@ impl Monitor for CloudWatch {
             end_query_time,
         );
 
+        let latency_futures = [Group::Control, Group::Experimental].map(|group| {
+            [LatencyMetric::Latency, LatencyMetric::IntegrationLatency].map(|metric_name| {
+                self.query_latency(
+                    metric_name,
+                    self.dimensions[0].value.as_ref(),
+                    self.dimensions[1].value.as_ref(),
+                    group,
+                    start_query_time,
+                    end_query_time,
+                )
+            })
+        });
+        let latency_future = futures_util::future::join_all(latency_futures.into_iter().flatten());
+
         let (
             control_count_result,
             control_4xx_result,
@@ -268,19 +380,17 @@ impl Monitor for CloudWatch {
             canary_count_result,
             canary_4xx_result,
             canary_5xx_result,
+            latency_results,
         ) = tokio::join!(
             control_count_future,
             control_4xx_future,
             control_5xx_future,
             canary_count_future,
             canary_4xx_future,
-            canary_5xx_future
+            canary_5xx_future,
+            latency_future,
         );
 
-        // Update the timer to skip old values. This has to occur
-        // before the ? in the next block, or else we might
-        // never advance our timer.
-        self.last_query_time = end_query_time;
         let control_4xx = control_4xx_result?;
         let control_5xx = control_5xx_result?;
         let control_count = control_count_result?;
@@ -289,9 +399,9 @@ impl Monitor for CloudWatch {
         let canary_count = canary_count_result?;
 
         // Collate all of our control metrics
-        let control_2xx = control_count - (control_4xx + control_5xx);
+        let control_2xx = successes(Group::Control, control_count, control_4xx, control_5xx);
         // Collate all of our canary/experimental metrics
-        let canary_2xx = canary_count - (canary_4xx + canary_5xx);
+        let canary_2xx = successes(Group::Experimental, canary_count, canary_4xx, canary_5xx);
 
         // Print a warning message if we have low metrics, but only if it's been 3 minutes since we started
         if (Utc::now() - self.start_time) > TimeDelta::minutes(3) {
@@ -318,6 +428,68 @@ impl Monitor for CloudWatch {
         canary.increment_by(&ResponseStatusCode::_4XX, canary_4xx);
         canary.increment_by(&ResponseStatusCode::_5XX, canary_5xx);
 
-        Ok(vec![baseline, canary])
+        let mut measurements = vec![baseline.into(), canary.into()];
+        // The latency results are in the order they were queried.
+        let latency_metrics = [LatencyMetric::Latency, LatencyMetric::IntegrationLatency];
+        for (metric_name, result) in latency_metrics.iter().cycle().zip(latency_results) {
+            measurements.push(metric_name.into_measurement(result?));
+        }
+        Ok(measurements)
+    }
+}
+
+/// The number of requests that didn't fail. CloudWatch aggregates each
+/// metric separately, so within a window the errors can outnumber the
+/// requests. Rather than underflowing, we count no successes.
+fn successes(group: Group, count: u32, errors_4xx: u32, errors_5xx: u32) -> u32 {
+    let errors = errors_4xx.saturating_add(errors_5xx);
+    if errors > count {
+        warn!("{group:?} reported {errors} errors out of only {count} requests.");
+    }
+    count.saturating_sub(errors)
+}
+
+#[async_trait]
+impl Shutdownable for CloudWatch {
+    async fn shutdown(&mut self) -> ShutdownResult {
+        // When we get the shutdown signal, all we need to do is not query CloudWatch
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Monitor for CloudWatch {
+    async fn query(&mut self) -> Result<Vec<Measurement>> {
+        info!("Querying CloudWatch for new metrics.");
+        let mut last = self.last_query_time;
+        let result =
+            query_window(&mut last, Utc::now(), |start, end| self.collect(start, end)).await;
+        self.last_query_time = last;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::{assert_eq, assert_str_eq};
+
+    use super::{successes, trimmed_count};
+    use crate::{metrics::LatencyBucket, stats::Group};
+
+    #[test]
+    fn bucket_statistics() {
+        let stats: Vec<_> = LatencyBucket::all()
+            .map(|bucket| trimmed_count(&bucket))
+            .collect();
+        assert_str_eq!(stats[0], "TC(:10)");
+        assert_str_eq!(stats[1], "TC(10:25)");
+        assert_str_eq!(stats[stats.len() - 1], "TC(5000:)");
+    }
+
+    #[test]
+    fn successes_never_underflow() {
+        assert_eq!(successes(Group::Control, 10, 2, 3), 5);
+        assert_eq!(successes(Group::Control, 4, 2, 3), 0);
+        assert_eq!(successes(Group::Control, 0, u32::MAX, 1), 0);
     }
 }
@@ -0,0 +1,355 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use aws_sdk_cloudwatch::{
+    client::Client as AwsClient,
+    config::Region,
+    types::{Dimension, Metric, MetricDataQuery, MetricStat},
+};
+use aws_smithy_types::DateTime as AwsDateTime;
+use chrono::{DateTime, Duration, Utc};
+use futures_util::future::join_all;
+use miette::Result;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::{
+    Shutdownable,
+    metrics::ResponseStatusCode,
+    stats::{CategoricalObservation, Group},
+    subsystems::ShutdownResult,
+    utils::load_default_aws_config,
+};
+
+use super::{Measurement, Monitor, StatusCode, query_window};
+
+/// The statistic used when a metric doesn't name one. Since each
+/// query counts requests, we almost always want their sum.
+const DEFAULT_STAT: &str = "Sum";
+/// The period of each datapoint, in seconds.
+const DEFAULT_PERIOD_SECS: i32 = 60;
+/// The id of the query whose values are counted. Metrics referenced
+/// by an expression can use any other id.
+const RESULT_ID: &str = "result";
+
+/// The user-facing configuration for the [CloudWatchQuery] monitor.
+/// Each group is described by a list of queries, and each query's
+/// values are counted towards a status code category.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct CloudWatchQueryConfig {
+    /// The region to query. Defaults to the region in the environment.
+    region: Option<String>,
+    /// The period of each datapoint, in seconds.
+    period_secs: Option<i32>,
+    /// The queries counting the baseline's requests.
+    baseline: Vec<StatusQuery>,
+    /// The queries counting the canary's requests.
+    canary: Vec<StatusQuery>,
+}
+
+/// A query whose values count requests in the given status category.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct StatusQuery {
+    /// The category the query's values are counted towards, e.g. `5XX`.
+    status: ResponseStatusCode,
+    #[serde(flatten)]
+    source: QuerySource,
+}
+
+/// Where a query's values come from: either a single metric, or a
+/// metric math expression over one or more metrics.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub(crate) enum QuerySource {
+    Expression {
+        /// A metric math expression, like `invocations - errors`.
+        expression: String,
+        /// The metrics the expression refers to by id.
+        #[serde(default)]
+        metrics: Vec<NamedMetric>,
+    },
+    Metric(MetricConfig),
+}
+
+/// A single CloudWatch metric, like ALB's `HTTPCode_Target_5XX_Count`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct MetricConfig {
+    /// The metric's namespace, e.g. `AWS/ApplicationELB`.
+    namespace: String,
+    /// The metric's name.
+    metric: String,
+    /// The dimensions selecting the group's series, by name.
+    #[serde(default)]
+    dimensions: BTreeMap<String, String>,
+    /// The statistic to request. Defaults to `Sum`.
+    stat: Option<String>,
+}
+
+/// A metric referenced by a metric math expression.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct NamedMetric {
+    /// The id the expression uses to refer to this metric. It must
+    /// start with a lowercase letter, and can't be `result`.
+    id: String,
+    #[serde(flatten)]
+    metric: MetricConfig,
+}
+
+impl MetricConfig {
+    pub(super) fn new(
+        namespace: impl Into<String>,
+        metric: impl Into<String>,
+        dimensions: BTreeMap<String, String>,
+    ) -> Self {
+        Self {
+            namespace: namespace.into(),
+            metric: metric.into(),
+            dimensions,
+            stat: None,
+        }
+    }
+
+    pub(super) fn to_query(&self, id: &str, period: i32, return_data: bool) -> MetricDataQuery {
+        let dimensions = self
+            .dimensions
+            .iter()
+            .map(|(name, value)| Dimension::builder().name(name).value(value).build())
+            .collect();
+        let metric = Metric::builder()
+            .namespace(&self.namespace)
+            .metric_name(&self.metric)
+            .set_dimensions(Some(dimensions))
+            .build();
+        MetricDataQuery::builder()
+            .id(id)
+            .metric_stat(
+                MetricStat::builder()
+                    .metric(metric)
+                    .period(period)
+                    .stat(self.stat.as_deref().unwrap_or(DEFAULT_STAT))
+                    .build(),
+            )
+            .return_data(return_data)
+            .build()
+    }
+}
+
+impl QuerySource {
+    /// Build the CloudWatch queries for this source. Only the query
+    /// with the id [RESULT_ID] returns data; the metrics referenced by
+    /// an expression are only used to compute it.
+    fn to_queries(&self, period: i32) -> Vec<MetricDataQuery> {
+        match self {
+            Self::Metric(metric) => vec![metric.to_query(RESULT_ID, period, true)],
+            Self::Expression {
+                expression,
+                metrics,
+            } => {
+                let result = MetricDataQuery::builder()
+                    .id(RESULT_ID)
+                    .expression(expression)
+                    .period(period)
+                    .return_data(true)
+                    .build();
+                std::iter::once(result)
+                    .chain(
+                        metrics
+                            .iter()
+                            .map(|named| named.metric.to_query(&named.id, period, false)),
+                    )
+                    .collect()
+            }
+        }
+    }
+}
+
+/// [CloudWatchQuery] counts each group's requests using arbitrary
+/// CloudWatch metrics, so anything publishing to CloudWatch, like
+/// ALBs, Lambdas, CloudFront or the application itself, can drive a
+/// rollout.
+pub struct CloudWatchQuery {
+    client: AwsClient,
+    period: i32,
+    baseline: Vec<StatusQuery>,
+    canary: Vec<StatusQuery>,
+    // The time we last queried CloudWatch
+    last_query_time: DateTime<Utc>,
+}
+
+impl CloudWatchQuery {
+    pub async fn new(config: CloudWatchQueryConfig) -> Self {
+        let aws_config = load_default_aws_config().await;
+        let mut client_config = aws_sdk_cloudwatch::config::Builder::from(aws_config);
+        if let Some(region) = config.region {
+            client_config = client_config.region(Region::new(region));
+        }
+        Self {
+            client: AwsClient::from_conf(client_config.build()),
+            period: config.period_secs.unwrap_or(DEFAULT_PERIOD_SECS).max(1),
+            baseline: config.baseline,
+            canary: config.canary,
+            last_query_time: Utc::now() - Duration::minutes(5),
+        }
+    }
+
+    fn queries(&self, group: Group) -> &[StatusQuery] {
+        match group {
+            Group::Control => &self.baseline,
+            Group::Experimental => &self.canary,
+        }
+    }
+
+    /// Sum the values of the query's result over the given window.
+    async fn query_count(
+        &self,
+        query: &StatusQuery,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<u32> {
+        // Each query is sent on its own, so the ids of the metrics
+        // referenced by expressions can't collide across queries.
+        let response = self
+            .client
+            .get_metric_data()
+            // AWS has custom DateTime formats, so we need to do a conversion first
+            .start_time(AwsDateTime::from_secs(start.timestamp()))
+            .end_time(AwsDateTime::from_secs(end.timestamp()))
+            .set_metric_data_queries(Some(query.source.to_queries(self.period)))
+            .send()
+            .await
+            .map_err(MonitorError::from_sdk)?;
+        let count: f64 = response
+            .metric_data_results()
+            .iter()
+            .filter(|result| result.id() == Some(RESULT_ID))
+            .flat_map(|result| result.values())
+            .sum();
+        Ok(count.max(0.0).round() as u32)
+    }
+
+    async fn query_group(
+        &self,
+        group: Group,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<StatusCode> {
+        let queries = self.queries(group);
+        let counts = join_all(
+            queries
+                .iter()
+                .map(|query| self.query_count(query, start, end)),
+        )
+        .await;
+        let mut observation = CategoricalObservation::new(group);
+        for (query, count) in queries.iter().zip(counts) {
+            observation.increment_by(&query.status, count?);
+        }
+        Ok(observation)
+    }
+
+    /// Count each group's requests within the window.
+    async fn collect(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Measurement>> {
+        let (baseline, canary) = tokio::join!(
+            self.query_group(Group::Control, start, end),
+            self.query_group(Group::Experimental, start, end),
+        );
+        let (baseline, canary) = (baseline?, canary?);
+        debug!("Control: {baseline:?}");
+        debug!("Canary: {canary:?}");
+        Ok(vec![baseline.into(), canary.into()])
+    }
+}
+
+#[async_trait]
+impl Shutdownable for CloudWatchQuery {
+    async fn shutdown(&mut self) -> ShutdownResult {
+        // When we get the shutdown signal, all we need to do is not query CloudWatch
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Monitor for CloudWatchQuery {
+    async fn query(&mut self) -> Result<Vec<Measurement>> {
+        info!("Querying CloudWatch for new metrics.");
+        let mut last = self.last_query_time;
+        let result =
+            query_window(&mut last, Utc::now(), |start, end| self.collect(start, end)).await;
+        self.last_query_time = last;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{CloudWatchQueryConfig, QuerySource, RESULT_ID};
+
+    fn config() -> CloudWatchQueryConfig {
+        serde_json::from_value(json!({
+            "baseline": [{
+                "status": "5XX",
+                "namespace": "AWS/ApplicationELB",
+                "metric": "HTTPCode_Target_5XX_Count",
+                "dimensions": {
+                    "LoadBalancer": "app/web/1234",
+                    "TargetGroup": "targetgroup/blue/5678",
+                },
+            }],
+            "canary": [{
+                "status": "2XX",
+                "expression": "invocations - errors",
+                "metrics": [{
+                    "id": "invocations",
+                    "namespace": "AWS/Lambda",
+                    "metric": "Invocations",
+                    "dimensions": { "FunctionName": "api", "Resource": "api:canary" },
+                }, {
+                    "id": "errors",
+                    "namespace": "AWS/Lambda",
+                    "metric": "Errors",
+                    "stat": "SampleCount",
+                }],
+            }],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn metric_queries() {
+        let config = config();
+        let source = &config.baseline[0].source;
+        assert!(matches!(source, QuerySource::Metric(_)));
+        let queries = source.to_queries(60);
+        assert_eq!(queries.len(), 1);
+        let query = &queries[0];
+        assert_eq!(query.id(), Some(RESULT_ID));
+        assert_eq!(query.return_data(), Some(true));
+        let stat = query.metric_stat().unwrap();
+        assert_eq!(stat.stat(), Some("Sum"));
+        let metric = stat.metric().unwrap();
+        assert_eq!(metric.namespace(), Some("AWS/ApplicationELB"));
+        let dimensions: Vec<_> = metric
+            .dimensions()
+            .iter()
+            .map(|dimension| dimension.name().unwrap())
+            .collect();
+        assert_eq!(dimensions, vec!["LoadBalancer", "TargetGroup"]);
+    }
+
+    #[test]
+    fn expression_queries() {
+        let config = config();
+        let queries = config.canary[0].source.to_queries(60);
+        let ids: Vec<_> = queries.iter().filter_map(|query| query.id()).collect();
+        assert_eq!(ids, vec![RESULT_ID, "invocations", "errors"]);
+        assert_eq!(queries[0].expression(), Some("invocations - errors"));
+        // Only the expression's result is returned.
+        let returned: Vec<_> = queries.iter().map(|query| query.return_data()).collect();
+        assert_eq!(returned, vec![Some(true), Some(false), Some(false)]);
+        let stat = queries[2].metric_stat().unwrap();
+        assert_eq!(stat.stat(), Some("SampleCount"));
+    }
+}
@@ -0,0 +1,205 @@
+use std::{error::Error, fmt};
+
+use aws_sdk_cloudwatch::error::{ProvideErrorMetadata, SdkError};
+use aws_smithy_types::error::display::DisplayErrorContext;
+use miette::{Diagnostic, Report};
+
+/// AWS error codes for requests that were throttled.
+const THROTTLING_CODES: [&str; 6] = [
+    "Throttling",
+    "ThrottlingException",
+    "ThrottledException",
+    "TooManyRequestsException",
+    "RequestLimitExceeded",
+    "LimitExceeded",
+];
+/// AWS error codes for requests that failed on AWS's side.
+const SERVER_CODES: [&str; 5] = [
+    "InternalFailure",
+    "InternalServiceError",
+    "InternalServiceFault",
+    "ServiceException",
+    "ServiceUnavailable",
+];
+/// AWS error codes for requests whose credentials were missing,
+/// invalid, or not allowed to make the request.
+const AUTH_CODES: [&str; 8] = [
+    "AccessDenied",
+    "AccessDeniedException",
+    "ExpiredToken",
+    "ExpiredTokenException",
+    "InvalidClientTokenId",
+    "MissingAuthenticationToken",
+    "SignatureDoesNotMatch",
+    "UnrecognizedClientException",
+];
+
+/// Describes whether a failed query is worth retrying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonitorErrorKind {
+    /// The query may succeed if retried, e.g. it was throttled.
+    Transient,
+    /// The query can never succeed as configured.
+    Permanent,
+    /// The monitor's credentials can't be used to read the data.
+    Auth,
+}
+
+impl fmt::Display for MonitorErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transient => write!(f, "transient"),
+            Self::Permanent => write!(f, "permanent"),
+            Self::Auth => write!(f, "authorization"),
+        }
+    }
+}
+
+/// The ways a monitor can fail to collect its data.
+#[derive(thiserror::Error, Debug, Diagnostic)]
+pub enum MonitorError {
+    #[error("Monitoring data is temporarily unavailable: {0}")]
+    Transient(String),
+    #[error("The monitor's query failed: {0}")]
+    Permanent(String),
+    #[error("The monitor isn't allowed to read its data: {0}")]
+    #[diagnostic(help(
+        "Check that MultiTool's credentials are valid and permitted to read the monitor's metrics."
+    ))]
+    Auth(String),
+}
+
+impl MonitorError {
+    pub fn kind(&self) -> MonitorErrorKind {
+        match self {
+            Self::Transient(_) => MonitorErrorKind::Transient,
+            Self::Permanent(_) => MonitorErrorKind::Permanent,
+            Self::Auth(_) => MonitorErrorKind::Auth,
+        }
+    }
+
+    fn new(kind: MonitorErrorKind, message: String) -> Self {
+        match kind {
+            MonitorErrorKind::Transient => Self::Transient(message),
+            MonitorErrorKind::Permanent => Self::Permanent(message),
+            MonitorErrorKind::Auth => Self::Auth(message),
+        }
+    }
+
+    /// Classify an error response by its HTTP status code.
+    pub fn from_status(status: u16, message: impl Into<String>) -> Self {
+        let kind = match status {
+            401 | 403 => MonitorErrorKind::Auth,
+            408 | 429 | 500..=599 => MonitorErrorKind::Transient,
+            _ => MonitorErrorKind::Permanent,
+        };
+        Self::new(kind, message.into())
+    }
+
+    /// Classify an error returned by an AWS SDK. Errors that never
+    /// reached AWS, like timeouts, are considered transient.
+    pub fn from_sdk<E, R>(err: SdkError<E, R>) -> Self
+    where
+        E: ProvideErrorMetadata + Error + 'static,
+        R: fmt::Debug,
+    {
+        let kind = match &err {
+            SdkError::TimeoutError(_)
+            | SdkError::DispatchFailure(_)
+            | SdkError::ResponseError(_) => MonitorErrorKind::Transient,
+            SdkError::ServiceError(_) => {
+                err.code().map_or(MonitorErrorKind::Permanent, kind_of_code)
+            }
+            _ => MonitorErrorKind::Permanent,
+        };
+        Self::new(kind, DisplayErrorContext(&err).to_string())
+    }
+
+    /// Returns the kind of error the report holds. Errors that weren't
+    /// classified by the monitor are assumed to be transient, since
+    /// retrying them is harmless.
+    pub fn kind_of(report: &Report) -> MonitorErrorKind {
+        report
+            .downcast_ref::<Self>()
+            .map_or(MonitorErrorKind::Transient, Self::kind)
+    }
+
+    /// Whether the query that failed with the given error should be
+    /// retried over the same window.
+    pub fn is_retryable(report: &Report) -> bool {
+        Self::kind_of(report) == MonitorErrorKind::Transient
+    }
+}
+
+impl From<reqwest::Error> for MonitorError {
+    fn from(err: reqwest::Error) -> Self {
+        if let Some(status) = err.status() {
+            return Self::from_status(status.as_u16(), err.to_string());
+        }
+        // Without a status, the request either never completed,
+        // or we couldn't make sense of the response.
+        let kind = if err.is_builder() || err.is_decode() {
+            MonitorErrorKind::Permanent
+        } else {
+            MonitorErrorKind::Transient
+        };
+        Self::new(kind, err.to_string())
+    }
+}
+
+/// Classify an AWS error code.
+fn kind_of_code(code: &str) -> MonitorErrorKind {
+    if THROTTLING_CODES.contains(&code) || SERVER_CODES.contains(&code) {
+        MonitorErrorKind::Transient
+    } else if AUTH_CODES.contains(&code) {
+        MonitorErrorKind::Auth
+    } else {
+        MonitorErrorKind::Permanent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miette::{Report, miette};
+    use pretty_assertions::assert_eq;
+
+    use super::{MonitorError, MonitorErrorKind, kind_of_code};
+
+    #[test]
+    fn classify_aws_codes() {
+        let test_cases = [
+            ("Throttling", MonitorErrorKind::Transient),
+            ("InternalServiceFault", MonitorErrorKind::Transient),
+            ("AccessDenied", MonitorErrorKind::Auth),
+            ("ExpiredToken", MonitorErrorKind::Auth),
+            ("InvalidParameterValue", MonitorErrorKind::Permanent),
+        ];
+        for (code, expected) in test_cases {
+            assert_eq!(kind_of_code(code), expected, "{code}");
+        }
+    }
+
+    #[test]
+    fn classify_statuses() {
+        let test_cases = [
+            (401, MonitorErrorKind::Auth),
+            (403, MonitorErrorKind::Auth),
+            (429, MonitorErrorKind::Transient),
+            (503, MonitorErrorKind::Transient),
+            (400, MonitorErrorKind::Permanent),
+        ];
+        for (status, expected) in test_cases {
+            assert_eq!(MonitorError::from_status(status, "").kind(), expected);
+        }
+    }
+
+    #[test]
+    fn kind_of_report() {
+        let report = Report::from(MonitorError::Auth("denied".to_owned()));
+        assert_eq!(MonitorError::kind_of(&report), MonitorErrorKind::Auth);
+        assert_eq!(
+            MonitorError::kind_of(&miette!("unclassified")),
+            MonitorErrorKind::Transient
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use aws_sdk_cloudwatch::{client::Client as CloudWatchClient, config::Region};
+use aws_sdk_lambda::client::Client as LambdaClient;
+use aws_smithy_types::DateTime as AwsDateTime;
+use chrono::{DateTime, Duration, Utc};
+use miette::Result;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::{
+    Shutdownable,
+    metrics::ResponseStatusCode,
+    stats::{CategoricalObservation, Group},
+    subsystems::ShutdownResult,
+    utils::{alias_version, load_default_aws_config, version_from_arn},
+};
+
+use super::{
+    CanaryWatch, Measurement, Monitor, MonitorError, StatusCode, cloudwatch_query::MetricConfig,
+    query_window,
+};
+
+/// The alias pointing at the baseline's version, unless configured otherwise.
+const DEFAULT_STABLE_ALIAS: &str = "live";
+
+/// The period of each datapoint, in seconds.
+const PERIOD_SECS: i32 = 60;
+
+/// The Lambda metrics we count. Successful invocations are counted
+/// as 2XX, errors as 5XX, and throttles, which never reach the
+/// function, as 4XX, like the 429 a client would see.
+#[derive(Debug, Clone, Copy)]
+enum LambdaMetric {
+    Invocations,
+    Errors,
+    Throttles,
+}
+
+impl LambdaMetric {
+    const ALL: [Self; 3] = [Self::Invocations, Self::Errors, Self::Throttles];
+
+    // Returns the value as a valid AWS query id.
+    fn to_id(self) -> &'static str {
+        match self {
+            Self::Invocations => "invocations",
+            Self::Errors => "errors",
+            Self::Throttles => "throttles",
+        }
+    }
+
+    fn to_metric_name(self) -> &'static str {
+        match self {
+            Self::Invocations => "Invocations",
+            Self::Errors => "Errors",
+            Self::Throttles => "Throttles",
+        }
+    }
+}
+
+/// How each version's metrics are told apart.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum VersionDimension {
+    /// Callers invoke the stable alias, which splits traffic between
+    /// the versions. Lambda reports the version that handled each
+    /// invocation as `ExecutedVersion`. Throttled invocations never
+    /// execute, so they aren't reported this way.
+    #[default]
+    ExecutedVersion,
+    /// Callers invoke each version directly by its qualified ARN, so
+    /// Lambda reports the version as the `Resource`.
+    Resource,
+}
+
+impl VersionDimension {
+    /// The dimensions selecting the metrics of the function's version.
+    fn dimensions(self, name: &str, alias: &str, version: &str) -> BTreeMap<String, String> {
+        let resource = match self {
+            Self::ExecutedVersion => format!("{name}:{alias}"),
+            Self::Resource => format!("{name}:{version}"),
+        };
+        let mut dimensions = BTreeMap::from([
+            ("FunctionName".to_owned(), name.to_owned()),
+            ("Resource".to_owned(), resource),
+        ]);
+        if self == Self::ExecutedVersion {
+            dimensions.insert("ExecutedVersion".to_owned(), version.to_owned());
+        }
+        dimensions
+    }
+}
+
+/// The user-facing configuration for the [LambdaMonitor].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct LambdaMonitorConfig {
+    /// The region to query. Defaults to the region in the environment.
+    region: Option<String>,
+    /// The name of the function.
+    name: String,
+    /// The alias pointing at the baseline's version. Defaults to `live`.
+    /// It should match the stable alias the Lambda platform promotes.
+    stable_alias: Option<String>,
+    /// How each version's metrics are told apart.
+    #[serde(default)]
+    keyed_by: VersionDimension,
+}
+
+/// [LambdaMonitor] counts the invocations, errors and throttles of a
+/// directly invoked Lambda, for both the baseline and canary versions.
+/// The baseline is the version the stable alias points to, and the
+/// canary is the version the Lambda platform deployed, once it has.
+pub struct LambdaMonitor {
+    lambda: LambdaClient,
+    cloudwatch: CloudWatchClient,
+    name: String,
+    stable_alias: String,
+    keyed_by: VersionDimension,
+    /// The ARN of the version the platform deployed.
+    canary: CanaryWatch,
+    // The time we last queried CloudWatch
+    last_query_time: DateTime<Utc>,
+}
+
+impl LambdaMonitor {
+    pub async fn new(config: LambdaMonitorConfig, canary: CanaryWatch) -> Self {
+        let aws_config = load_default_aws_config().await;
+        let mut lambda_config = aws_sdk_lambda::config::Builder::from(aws_config);
+        let mut cloudwatch_config = aws_sdk_cloudwatch::config::Builder::from(aws_config);
+        if let Some(region) = config.region {
+            lambda_config =
+                lambda_config.region(aws_sdk_lambda::config::Region::new(region.clone()));
+            cloudwatch_config = cloudwatch_config.region(Region::new(region));
+        }
+        Self {
+            lambda: LambdaClient::from_conf(lambda_config.build()),
+            cloudwatch: CloudWatchClient::from_conf(cloudwatch_config.build()),
+            name: config.name,
+            stable_alias: config
+                .stable_alias
+                .unwrap_or_else(|| DEFAULT_STABLE_ALIAS.to_owned()),
+            keyed_by: config.keyed_by,
+            canary,
+            last_query_time: Utc::now() - Duration::minutes(5),
+        }
+    }
+
+    /// Count the version's invocations, errors and throttles within
+    /// the window, in the order of [LambdaMetric::ALL].
+    async fn query_version(
+        &self,
+        version: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<[u32; 3]> {
+        let dimensions = self
+            .keyed_by
+            .dimensions(&self.name, &self.stable_alias, version);
+        let queries = LambdaMetric::ALL
+            .iter()
+            .map(|metric| {
+                MetricConfig::new("AWS/Lambda", metric.to_metric_name(), dimensions.clone())
+                    .to_query(metric.to_id(), PERIOD_SECS, true)
+            })
+            .collect();
+        let response = self
+            .cloudwatch
+            .get_metric_data()
+            // AWS has custom DateTime formats, so we need to do a conversion first
+            .start_time(AwsDateTime::from_secs(start.timestamp()))
+            .end_time(AwsDateTime::from_secs(end.timestamp()))
+            .set_metric_data_queries(Some(queries))
+            .send()
+            .await
+            .map_err(MonitorError::from_sdk)?;
+        let mut counts = [0; 3];
+        for (count, metric) in counts.iter_mut().zip(LambdaMetric::ALL) {
+            let sum: f64 = response
+                .metric_data_results()
+                .iter()
+                .filter(|result| result.id() == Some(metric.to_id()))
+                .flat_map(|result| result.values())
+                .sum();
+            *count = sum.round() as u32;
+        }
+        Ok(counts)
+    }
+
+    /// Bin the group's counts into status code categories. A group
+    /// without a version, like the baseline on the first deploy,
+    /// has no observations.
+    async fn query_group(
+        &self,
+        group: Group,
+        version: Option<&str>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<StatusCode> {
+        let mut observation = CategoricalObservation::new(group);
+        let Some(version) = version else {
+            return Ok(observation);
+        };
+        let [invocations, errors, throttles] = self.query_version(version, start, end).await?;
+        debug!(
+            "{group:?} version {version}: {invocations} invocations, {errors} errors, {throttles} throttles"
+        );
+        observation.increment_by(
+            &ResponseStatusCode::_2XX,
+            invocations.saturating_sub(errors),
+        );
+        observation.increment_by(&ResponseStatusCode::_4XX, throttles);
+        observation.increment_by(&ResponseStatusCode::_5XX, errors);
+        Ok(observation)
+    }
+
+    /// Count the requests each version served within the window.
+    async fn collect(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Measurement>> {
+        let baseline = alias_version(&self.lambda, &self.name, &self.stable_alias)
+            .await
+            .map_err(MonitorError::from_sdk)?;
+        let canary = canary_version(self.canary.borrow().as_deref(), baseline.as_deref())?;
+        let (baseline, canary) = tokio::join!(
+            self.query_group(Group::Control, baseline.as_deref(), start, end),
+            self.query_group(Group::Experimental, canary.as_deref(), start, end),
+        );
+        Ok(vec![baseline?.into(), canary?.into()])
+    }
+}
+
+/// The version of the deployed canary, unless it's since been
+/// promoted and is the baseline.
+fn canary_version(canary_id: Option<&str>, baseline: Option<&str>) -> Result<Option<String>> {
+    let Some(canary_id) = canary_id else {
+        return Ok(None);
+    };
+    let version = version_from_arn(canary_id)?;
+    Ok((Some(version.as_str()) != baseline).then_some(version))
+}
+
+#[async_trait]
+impl Shutdownable for LambdaMonitor {
+    async fn shutdown(&mut self) -> ShutdownResult {
+        // When we get the shutdown signal, all we need to do is not query CloudWatch
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Monitor for LambdaMonitor {
+    async fn query(&mut self) -> Result<Vec<Measurement>> {
+        info!("Querying CloudWatch for new Lambda metrics.");
+        let mut last = self.last_query_time;
+        let result =
+            query_window(&mut last, Utc::now(), |start, end| self.collect(start, end)).await;
+        self.last_query_time = last;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{LambdaMonitorConfig, VersionDimension, canary_version};
+
+    #[test]
+    fn parse_keyed_by() {
+        let config: LambdaMonitorConfig = serde_json::from_value(json!({
+            "name": "api",
+        }))
+        .unwrap();
+        assert_eq!(config.keyed_by, VersionDimension::ExecutedVersion);
+        let config: LambdaMonitorConfig = serde_json::from_value(json!({
+            "name": "api",
+            "keyed_by": "resource",
+        }))
+        .unwrap();
+        assert_eq!(config.keyed_by, VersionDimension::Resource);
+    }
+
+    #[test]
+    fn version_dimensions() {
+        let dimensions = VersionDimension::ExecutedVersion.dimensions("api", "live", "7");
+        assert_eq!(dimensions["Resource"], "api:live");
+        assert_eq!(dimensions["ExecutedVersion"], "7");
+        let dimensions = VersionDimension::Resource.dimensions("api", "live", "7");
+        assert_eq!(dimensions["Resource"], "api:7");
+        assert!(!dimensions.contains_key("ExecutedVersion"));
+    }
+
+    /// The canary is whichever version was deployed, not
+    /// whichever was published last.
+    #[test]
+    fn canary_is_the_deployed_version() {
+        let arn = "arn:aws:lambda:us-east-1:1234:function:api:7";
+        assert_eq!(canary_version(None, Some("6")).unwrap(), None);
+        assert_eq!(
+            canary_version(Some(arn), Some("6")).unwrap(),
+            Some("7".to_owned())
+        );
+        // Once it's promoted, it's the baseline.
+        assert_eq!(canary_version(Some(arn), Some("7")).unwrap(), None);
+        assert!(canary_version(Some("arn:aws:lambda:us-east-1:1234:function:api"), None).is_err());
+    }
+}
@@ -0,0 +1,294 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::{
+    adapters::MonitorErrorKind,
+    metrics::{LATENCY_BUCKETS, LatencyBucket, ResponseStatusCode},
+    numbers::CpuUsage,
+    stats::{CategoricalObservation, Group, Observation, ObservationWindow},
+};
+
+/// StatusCode is a type alias for the unwieldly named type on the right.
+pub type StatusCode = CategoricalObservation<5, ResponseStatusCode>;
+/// Latency counts how many requests fell into each latency bucket.
+pub type Latency = CategoricalObservation<LATENCY_BUCKETS, LatencyBucket>;
+
+/// A [ResponseTally] counts the responses of one group across
+/// the status codes in a batch of measurements.
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResponseTally {
+    /// The number of 5XX responses.
+    pub errors: u32,
+    pub total: u32,
+}
+
+impl ResponseTally {
+    pub fn new(batch: &[Measurement], group: Group) -> Self {
+        batch
+            .iter()
+            .filter_map(|measurement| match measurement {
+                Measurement::StatusCode(observation) if observation.group() == group => {
+                    Some(observation)
+                }
+                _ => None,
+            })
+            .fold(Self::default(), |tally, observation| Self {
+                errors: tally.errors + observation.get_count(&ResponseStatusCode::_5XX),
+                total: tally.total + observation.total(),
+            })
+    }
+}
+
+/// A [Measurement] is any of the observations a monitor can make.
+/// A single query may return several kinds.
+#[derive(Debug, Clone)]
+pub enum Measurement {
+    StatusCode(StatusCode),
+    /// The time between receiving a request and responding to it.
+    Latency(Latency),
+    /// The time spent waiting on the backing service, as opposed
+    /// to the ingress in front of it.
+    IntegrationLatency(Latency),
+    Cpu(CpuObservation),
+    Counter(CounterObservation),
+    /// The monitor couldn't collect any data for a query.
+    Gap(DataGap),
+}
+
+impl Measurement {
+    /// The group the measurement was taken from. Gaps in the data
+    /// don't belong to either group.
+    pub fn group(&self) -> Option<Group> {
+        match self {
+            Self::StatusCode(observation) => Some(observation.group()),
+            Self::Latency(observation) | Self::IntegrationLatency(observation) => {
+                Some(observation.group())
+            }
+            Self::Cpu(observation) => Some(observation.group()),
+            Self::Counter(observation) => Some(observation.group()),
+            Self::Gap(_) => None,
+        }
+    }
+
+    /// The span of time the measurement covers, if known.
+    pub fn window(&self) -> Option<ObservationWindow> {
+        match self {
+            Self::StatusCode(observation) => observation.window(),
+            Self::Latency(observation) | Self::IntegrationLatency(observation) => {
+                observation.window()
+            }
+            Self::Cpu(_) | Self::Counter(_) | Self::Gap(_) => None,
+        }
+    }
+
+    /// Attribute the measurement to the span of time it covers.
+    /// Only categorical observations keep track of their window.
+    pub fn with_window(mut self, window: ObservationWindow) -> Self {
+        match &mut self {
+            Self::StatusCode(observation) => observation.set_window(window),
+            Self::Latency(observation) | Self::IntegrationLatency(observation) => {
+                observation.set_window(window)
+            }
+            Self::Cpu(_) | Self::Counter(_) | Self::Gap(_) => (),
+        }
+        self
+    }
+}
+
+impl From<StatusCode> for Measurement {
+    fn from(observation: StatusCode) -> Self {
+        Self::StatusCode(observation)
+    }
+}
+
+impl From<CpuObservation> for Measurement {
+    fn from(observation: CpuObservation) -> Self {
+        Self::Cpu(observation)
+    }
+}
+
+impl From<CounterObservation> for Measurement {
+    fn from(observation: CounterObservation) -> Self {
+        Self::Counter(observation)
+    }
+}
+
+impl From<DataGap> for Measurement {
+    fn from(gap: DataGap) -> Self {
+        Self::Gap(gap)
+    }
+}
+
+/// The average CPU utilization of a group over some window, as a
+/// percentage of a single core.
+#[derive(Debug, Clone)]
+pub struct CpuObservation {
+    group: Group,
+    usage: CpuUsage,
+}
+
+impl CpuObservation {
+    pub fn new(group: Group, usage: CpuUsage) -> Self {
+        Self { group, usage }
+    }
+}
+
+impl Observation for CpuObservation {
+    fn group(&self) -> Group {
+        self.group
+    }
+}
+
+impl fmt::Display for CpuObservation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} CPU usage: {}%", self.group, self.usage)
+    }
+}
+
+/// A user-defined count, like the number of failed jobs, that
+/// increased by `count` over some window.
+#[derive(Debug, Clone)]
+pub struct CounterObservation {
+    group: Group,
+    name: String,
+    count: u64,
+}
+
+impl CounterObservation {
+    pub fn new(group: Group, name: String, count: u64) -> Self {
+        Self { group, name, count }
+    }
+}
+
+impl Observation for CounterObservation {
+    fn group(&self) -> Group {
+        self.group
+    }
+}
+
+impl fmt::Display for CounterObservation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} {}: {}", self.group, self.name, self.count)
+    }
+}
+
+/// A [DataGap] records that a query returned nothing, even after
+/// retrying, so the absence of errors in that window says nothing
+/// about the canary's health.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataGap {
+    kind: MonitorErrorKind,
+}
+
+impl DataGap {
+    pub fn new(kind: MonitorErrorKind) -> Self {
+        Self { kind }
+    }
+
+    /// The kind of error that caused the gap.
+    pub fn kind(&self) -> MonitorErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for DataGap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Gap in monitoring data ({} error)", self.kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::{assert_eq, assert_str_eq};
+
+    use chrono::{TimeZone, Utc};
+
+    use super::{
+        CounterObservation, CpuObservation, DataGap, Measurement, ResponseTally, StatusCode,
+    };
+    use crate::{
+        adapters::MonitorErrorKind,
+        metrics::ResponseStatusCode,
+        stats::{Group, ObservationWindow},
+    };
+
+    #[test]
+    fn measurements_know_their_group() {
+        let measurements = [
+            Measurement::from(StatusCode::new(Group::Control)),
+            Measurement::from(CpuObservation::new(Group::Experimental, 12.5_f64.into())),
+            Measurement::from(CounterObservation::new(
+                Group::Control,
+                "jobs_failed_total".to_owned(),
+                3,
+            )),
+            Measurement::from(DataGap::new(MonitorErrorKind::Transient)),
+        ];
+        let groups: Vec<_> = measurements.iter().map(Measurement::group).collect();
+        assert_eq!(
+            groups,
+            vec![
+                Some(Group::Control),
+                Some(Group::Experimental),
+                Some(Group::Control),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn categorical_measurements_keep_their_window() {
+        let window = ObservationWindow::new(
+            Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2025, 1, 1, 12, 5, 0).unwrap(),
+        );
+        let status = Measurement::from(StatusCode::new(Group::Control)).with_window(window);
+        assert_eq!(status.window(), Some(window));
+        let cpu = Measurement::from(CpuObservation::new(Group::Control, 12.5_f64.into()))
+            .with_window(window);
+        assert_eq!(cpu.window(), None);
+    }
+
+    #[test]
+    fn tally_responses() {
+        let mut control = StatusCode::new(Group::Control);
+        control.increment_by(&ResponseStatusCode::_2XX, 8);
+        control.increment_by(&ResponseStatusCode::_5XX, 2);
+        let mut canary = StatusCode::new(Group::Experimental);
+        canary.increment_by(&ResponseStatusCode::_5XX, 3);
+        let batch = [
+            Measurement::from(control.clone()),
+            Measurement::from(control),
+            Measurement::from(canary),
+            Measurement::from(DataGap::new(MonitorErrorKind::Transient)),
+        ];
+        assert_eq!(
+            ResponseTally::new(&batch, Group::Control),
+            ResponseTally {
+                errors: 4,
+                total: 20
+            }
+        );
+        assert_eq!(
+            ResponseTally::new(&batch, Group::Experimental),
+            ResponseTally {
+                errors: 3,
+                total: 3
+            }
+        );
+    }
+
+    #[test]
+    fn fmt_observations() {
+        let cpu = CpuObservation::new(Group::Experimental, 12.5_f64.into());
+        assert_str_eq!(cpu.to_string(), "Experimental CPU usage: 12.50%");
+        let counter = CounterObservation::new(Group::Control, "jobs_failed_total".to_owned(), 3);
+        assert_str_eq!(counter.to_string(), "Control jobs_failed_total: 3");
+        let gap = DataGap::new(MonitorErrorKind::Auth);
+        assert_str_eq!(
+            gap.to_string(),
+            "Gap in monitoring data (authorization error)"
+        );
+    }
+}
@@ -1,29 +1,116 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use miette::Result;
+use tokio::sync::watch;
 
-use crate::{
-    Shutdownable,
-    metrics::ResponseStatusCode,
-    stats::{CategoricalObservation, Observation},
-};
+use crate::{Shutdownable, stats::ObservationWindow};
 
-/// StatusCode is a type alias for the unwieldly named type on the right.
-pub type StatusCode = CategoricalObservation<5, ResponseStatusCode>;
+/// Convenience alias since this type is often dynamically
+/// dispatched.
+pub type BoxedMonitor = Box<dyn Monitor + Send + Sync>;
 
-// TODO: For now, we require all monitors to monitor just
-// the status code. We may have trouble with the Builder in the
-// future because we can't really genericize it. But when we add
-// more metrics, we'll upgrade Monitors to handle them all, simultaniously,
-// and there may not be a generic parameter on the Monitor type anymore.
-pub type BoxedMonitor = Box<dyn Monitor<Item = StatusCode> + Send + Sync>;
+pub(crate) use builder::{LocalMonitorConfig, MonitorBuilder};
+pub use error::{MonitorError, MonitorErrorKind};
+pub use measurement::{
+    CounterObservation, CpuObservation, DataGap, Latency, Measurement, ResponseTally, StatusCode,
+};
 
-pub(crate) use builder::MonitorBuilder;
+/// The platform's id for the deployed canary, for monitors that look
+/// up its metrics by version. It's `None` until the canary is deployed.
+pub(crate) type CanaryWatch = watch::Receiver<Option<String>>;
 
+/// Monitors observe the baseline and the canary. Each query returns
+/// the measurements taken since the previous query, which may be of
+/// several kinds at once, e.g. status codes alongside latencies.
 #[async_trait]
 pub trait Monitor: Shutdownable {
-    type Item: Observation;
-    async fn query(&mut self) -> Result<Vec<Self::Item>>;
+    async fn query(&mut self) -> Result<Vec<Measurement>>;
+}
+
+/// Collect the measurements taken between the `last` time a monitor
+/// was queried and `end`, attributing each to that window. `collect`
+/// is given the same bounds. Since `collect` usually borrows the monitor,
+/// callers pass a copy of `last` and store it once it's advanced.
+async fn query_window<F, Fut>(
+    last: &mut DateTime<Utc>,
+    end: DateTime<Utc>,
+    collect: F,
+) -> Result<Vec<Measurement>>
+where
+    F: FnOnce(DateTime<Utc>, DateTime<Utc>) -> Fut,
+    Fut: Future<Output = Result<Vec<Measurement>>>,
+{
+    let window = ObservationWindow::new(*last, end);
+    let result = collect(*last, end).await.map(|measurements| {
+        measurements
+            .into_iter()
+            .map(|measurement| measurement.with_window(window))
+            .collect()
+    });
+    // A transient failure is retried over the same window, so none
+    // of its data is lost. Any other failure would happen again, so
+    // we move past the window, or else we'd never advance it.
+    if !result.as_ref().is_err_and(MonitorError::is_retryable) {
+        *last = end;
+    }
+    result
 }
 
 mod builder;
 mod cloudwatch;
+/// A CloudWatch monitor driven by user-defined metric queries.
+mod cloudwatch_query;
+/// The ways a monitor's query can fail.
+mod error;
+/// A monitor for directly invoked Lambdas, using per-version metrics.
+mod lambda;
+/// The kinds of observations monitors report.
+mod measurement;
+/// A monitor backed by the Prometheus HTTP API.
+mod prometheus;
+/// A monitor fed by the responses `multi proxy` observes.
+mod proxy;
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+    use static_assertions::assert_obj_safe;
+
+    use super::{Monitor, MonitorError, StatusCode, query_window};
+    use crate::stats::{Group, ObservationWindow};
+
+    assert_obj_safe!(Monitor);
+
+    /// The window only advances past data that won't come back.
+    #[tokio::test]
+    async fn windows_advance_unless_retried() {
+        let at = |minute| Utc.with_ymd_and_hms(2025, 1, 1, 12, minute, 0).unwrap();
+        let mut last = at(0);
+        let err = query_window(&mut last, at(1), |_, _| async {
+            Err(MonitorError::Transient("throttled".to_owned()).into())
+        })
+        .await;
+        assert!(err.is_err());
+        assert_eq!(last, at(0));
+
+        let measurements = query_window(&mut last, at(2), |start, end| async move {
+            assert_eq!((start, end), (at(0), at(2)));
+            Ok(vec![StatusCode::new(Group::Control).into()])
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            measurements[0].window(),
+            Some(ObservationWindow::new(at(0), at(2)))
+        );
+        assert_eq!(last, at(2));
+
+        let err = query_window(&mut last, at(3), |_, _| async {
+            Err(MonitorError::Permanent("bad query".to_owned()).into())
+        })
+        .await;
+        assert!(err.is_err());
+        assert_eq!(last, at(3));
+    }
+}
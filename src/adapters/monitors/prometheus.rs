@@ -0,0 +1,582 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use bon::bon;
+use chrono::{DateTime, Duration, Utc};
+use futures_util::{FutureExt as _, future::BoxFuture};
+use miette::Result;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::{
+    Shutdownable,
+    metrics::ResponseStatusCode,
+    stats::{CategoricalObservation, Group},
+    subsystems::ShutdownResult,
+};
+
+use super::{
+    CounterObservation, CpuObservation, Measurement, Monitor, MonitorError, StatusCode,
+    query_window,
+};
+
+/// By default, we count requests using the metric name recommended
+/// by the Prometheus instrumentation guidelines.
+const DEFAULT_METRIC: &str = "http_requests_total";
+/// The label holding the response's status code.
+const DEFAULT_STATUS_LABEL: &str = "code";
+/// The resolution of each range query, in seconds.
+const DEFAULT_STEP_SECS: u64 = 60;
+
+/// The user-facing configuration for the [Prometheus] monitor.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct PrometheusConfig {
+    /// The base URL of the Prometheus server, e.g. `http://localhost:9090`.
+    url: String,
+    /// A label selector matching only the baseline's series,
+    /// e.g. `job="api", track="stable"`.
+    baseline_selector: String,
+    /// A label selector matching only the canary's series.
+    canary_selector: String,
+    /// The counter tracking requests by status code.
+    metric: Option<String>,
+    /// The label on `metric` containing the status code.
+    status_label: Option<String>,
+    /// The step of each range query, in seconds.
+    step_secs: Option<u64>,
+    /// Counters reported alongside the status codes, like
+    /// `jobs_failed_total`. Their increase is reported for each group.
+    counters: Option<Vec<String>>,
+    /// A counter of CPU seconds, like `process_cpu_seconds_total`,
+    /// used to report each group's CPU usage.
+    cpu_metric: Option<String>,
+}
+
+/// [Prometheus] issues PromQL range queries against the Prometheus HTTP
+/// API, counting how many requests the baseline and canary served in
+/// each status code category.
+pub struct Prometheus {
+    client: reqwest::Client,
+    url: String,
+    metric: String,
+    status_label: String,
+    baseline_selector: String,
+    canary_selector: String,
+    counters: Vec<String>,
+    cpu_metric: Option<String>,
+    step: Duration,
+    /// The timestamp of the last point we've counted. Points are
+    /// spaced one step apart, and each covers the step before it,
+    /// so the next query starts one step after this one.
+    last_point: DateTime<Utc>,
+}
+
+#[bon]
+impl Prometheus {
+    #[builder]
+    pub fn new(
+        url: String,
+        baseline_selector: String,
+        canary_selector: String,
+        metric: Option<String>,
+        status_label: Option<String>,
+        step_secs: Option<u64>,
+        counters: Option<Vec<String>>,
+        cpu_metric: Option<String>,
+    ) -> Self {
+        let step_secs = step_secs.unwrap_or(DEFAULT_STEP_SECS).max(1);
+        let step = Duration::seconds(step_secs as i64);
+        Self {
+            client: reqwest::Client::new(),
+            url: url.trim_end_matches('/').to_owned(),
+            metric: metric.unwrap_or_else(|| DEFAULT_METRIC.to_owned()),
+            status_label: status_label.unwrap_or_else(|| DEFAULT_STATUS_LABEL.to_owned()),
+            baseline_selector,
+            canary_selector,
+            counters: counters.unwrap_or_default(),
+            cpu_metric,
+            step,
+            last_point: Utc::now() - Duration::minutes(5),
+        }
+    }
+}
+
+impl From<PrometheusConfig> for Prometheus {
+    fn from(config: PrometheusConfig) -> Self {
+        Self::builder()
+            .url(config.url)
+            .baseline_selector(config.baseline_selector)
+            .canary_selector(config.canary_selector)
+            .maybe_metric(config.metric)
+            .maybe_status_label(config.status_label)
+            .maybe_step_secs(config.step_secs)
+            .maybe_counters(config.counters)
+            .maybe_cpu_metric(config.cpu_metric)
+            .build()
+    }
+}
+
+impl Prometheus {
+    fn selector(&self, group: Group) -> &str {
+        match group {
+            Group::Control => &self.baseline_selector,
+            Group::Experimental => &self.canary_selector,
+        }
+    }
+
+    /// Build the PromQL query counting the group's requests by status code.
+    fn promql(&self, group: Group) -> String {
+        format!(
+            "sum by ({label}) (increase({metric}{{{selector}}}[{step}s]))",
+            label = self.status_label,
+            metric = self.metric,
+            selector = self.selector(group),
+            step = self.step.num_seconds(),
+        )
+    }
+
+    /// Run a range query, returning every series in the result.
+    async fn query_range(
+        &self,
+        promql: String,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Series>> {
+        let response = self
+            .client
+            .get(format!("{}/api/v1/query_range", self.url))
+            .query(&[
+                ("query", promql.clone()),
+                ("start", start.timestamp().to_string()),
+                ("end", end.timestamp().to_string()),
+                ("step", format!("{}s", self.step.num_seconds())),
+            ])
+            .send()
+            .await
+            .map_err(MonitorError::from)?;
+        let status = response.status();
+        let response: QueryResponse = match response.json().await {
+            Ok(response) => response,
+            // Proxies in front of Prometheus, like an auth proxy,
+            // don't respond with JSON.
+            Err(_) if !status.is_success() => {
+                return Err(MonitorError::from_status(
+                    status.as_u16(),
+                    format!("Prometheus responded with {status}"),
+                )
+                .into());
+            }
+            Err(err) => return Err(MonitorError::from(err).into()),
+        };
+
+        if response.status != "success" {
+            // Prometheus explains why the query failed in the body, and
+            // uses the status to tell bad queries from overloaded servers.
+            return Err(MonitorError::from_status(
+                status.as_u16(),
+                format!(
+                    "Prometheus query `{promql}` failed: {}",
+                    response.error.unwrap_or_default()
+                ),
+            )
+            .into());
+        }
+        Ok(response.data.map(|data| data.result).unwrap_or_default())
+    }
+
+    /// Take every measurement between the given points.
+    async fn collect(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Measurement>> {
+        let (baseline, canary, extras) = tokio::join!(
+            self.query_status_codes(Group::Control, start, end),
+            self.query_status_codes(Group::Experimental, start, end),
+            futures_util::future::join_all(self.extra_queries(start, end)),
+        );
+        let baseline = baseline?;
+        let canary = canary?;
+        debug!("Baseline: {baseline:?}");
+        debug!("Canary: {canary:?}");
+        let mut measurements = vec![baseline.into(), canary.into()];
+        // • Nothing is decided on the extra metrics, so a failed query
+        //   only loses its own metric, not the status codes.
+        for extra in extras {
+            match extra {
+                Ok(measurement) => measurements.extend(measurement),
+                Err(err) => warn!("Skipping a metric Prometheus couldn't report: {err}"),
+            }
+        }
+        Ok(measurements)
+    }
+
+    async fn query_status_codes(
+        &self,
+        group: Group,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<StatusCode> {
+        let series = self.query_range(self.promql(group), start, end).await?;
+        let mut observation = CategoricalObservation::new(group);
+        for series in series {
+            let Some(status) = series
+                .metric
+                .get(&self.status_label)
+                .and_then(|code| code.parse().ok())
+                .and_then(ResponseStatusCode::from_status)
+            else {
+                debug!(
+                    "Skipping series without a valid status code: {:?}",
+                    series.metric
+                );
+                continue;
+            };
+            // `increase` extrapolates, so the values aren't always whole numbers.
+            observation.increment_by(&status, series.sum().round() as u32);
+        }
+        Ok(observation)
+    }
+
+    /// Report how much the counter increased for the group.
+    async fn query_counter(
+        &self,
+        name: &str,
+        group: Group,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Option<Measurement>> {
+        let promql = format!(
+            "sum(increase({name}{{{selector}}}[{step}s]))",
+            selector = self.selector(group),
+            step = self.step.num_seconds(),
+        );
+        let series = self.query_range(promql, start, end).await?;
+        let count: f64 = series.iter().map(Series::sum).sum();
+        let observation = CounterObservation::new(group, name.to_owned(), count.round() as u64);
+        Ok(Some(observation.into()))
+    }
+
+    /// Report the group's average CPU usage, as a percentage of one core.
+    /// Returns `None` if Prometheus has no samples for the group.
+    async fn query_cpu(
+        &self,
+        metric: &str,
+        group: Group,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Option<Measurement>> {
+        let promql = format!(
+            "sum(rate({metric}{{{selector}}}[{step}s])) * 100",
+            selector = self.selector(group),
+            step = self.step.num_seconds(),
+        );
+        let series = self.query_range(promql, start, end).await?;
+        let samples: Vec<f64> = series.iter().flat_map(Series::samples).collect();
+        if samples.is_empty() {
+            return Ok(None);
+        }
+        let usage = samples.iter().sum::<f64>() / samples.len() as f64;
+        Ok(Some(CpuObservation::new(group, usage.into()).into()))
+    }
+
+    /// The queries for the counters and CPU usage, if any are configured.
+    fn extra_queries(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Vec<BoxFuture<'_, Result<Option<Measurement>>>> {
+        let mut queries = Vec::new();
+        for group in [Group::Control, Group::Experimental] {
+            for name in &self.counters {
+                queries.push(self.query_counter(name, group, start, end).boxed());
+            }
+            if let Some(metric) = &self.cpu_metric {
+                queries.push(self.query_cpu(metric, group, start, end).boxed());
+            }
+        }
+        queries
+    }
+}
+
+#[async_trait]
+impl Shutdownable for Prometheus {
+    async fn shutdown(&mut self) -> ShutdownResult {
+        // There are no connections to close.
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Monitor for Prometheus {
+    async fn query(&mut self) -> Result<Vec<Measurement>> {
+        info!("Querying Prometheus for new metrics.");
+        let start = self.last_point + self.step;
+        let now = Utc::now();
+        if start > now {
+            // A full step hasn't elapsed since the last point we counted.
+            return Ok(Vec::new());
+        }
+        // Only ask for whole steps, so the next query can pick up
+        // exactly where this one left off.
+        let steps = (now - start).num_seconds() / self.step.num_seconds();
+        let end = start + self.step * steps as i32;
+
+        // The first point counts the increase since the last point
+        // we counted, so that's where the window starts.
+        let mut last = self.last_point;
+        let result = query_window(&mut last, end, |_, end| self.collect(start, end)).await;
+        self.last_point = last;
+        result
+    }
+}
+
+/// The envelope around every Prometheus HTTP API response.
+#[derive(Deserialize)]
+struct QueryResponse {
+    status: String,
+    data: Option<QueryData>,
+    error: Option<String>,
+}
+
+/// The result of a range query is a matrix: a list of series.
+#[derive(Deserialize)]
+struct QueryData {
+    result: Vec<Series>,
+}
+
+#[derive(Deserialize)]
+struct Series {
+    metric: HashMap<String, String>,
+    /// Pairs of Unix timestamps and sample values. Prometheus encodes
+    /// the values as strings to preserve special values, like `NaN`.
+    values: Vec<(f64, String)>,
+}
+
+impl Series {
+    /// The finite sample values. Special values, like `NaN`, are skipped.
+    fn samples(&self) -> impl Iterator<Item = f64> + '_ {
+        self.values
+            .iter()
+            .filter_map(|(_, value)| value.parse::<f64>().ok())
+            .filter(|value| value.is_finite())
+    }
+
+    fn sum(&self) -> f64 {
+        self.samples().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::Prometheus;
+    use crate::{
+        adapters::{Measurement, Monitor, MonitorError, MonitorErrorKind},
+        metrics::ResponseStatusCode,
+        stats::Group,
+        utils::StubServer,
+    };
+
+    fn monitor(url: String) -> Prometheus {
+        let mut monitor = Prometheus::builder()
+            .url(url)
+            .baseline_selector(r#"track="stable""#.to_owned())
+            .canary_selector(r#"track="canary""#.to_owned())
+            .build();
+        // Make sure there are a few steps to query.
+        monitor.last_point = Utc::now() - Duration::minutes(3);
+        monitor
+    }
+
+    #[tokio::test]
+    async fn counts_requests_by_status_code() {
+        let body = json!({
+            "status": "success",
+            "data": {
+                "resultType": "matrix",
+                "result": [
+                    {
+                        "metric": { "code": "200" },
+                        "values": [[1700000000, "10"], [1700000060, "20.4"]],
+                    },
+                    {
+                        "metric": { "code": "503" },
+                        "values": [[1700000000, "2"], [1700000060, "NaN"]],
+                    },
+                    {
+                        "metric": {},
+                        "values": [[1700000000, "7"]],
+                    },
+                ],
+            },
+        });
+        let server = StubServer::always(200, body.to_string()).await;
+        let mut monitor = monitor(server.origin.clone());
+        let observations = monitor.query().await.unwrap();
+
+        assert_eq!(observations.len(), 2);
+        assert_eq!(observations[0].group(), Some(Group::Control));
+        assert_eq!(observations[1].group(), Some(Group::Experimental));
+        for observation in &observations {
+            let Measurement::StatusCode(observation) = observation else {
+                panic!("Expected status codes, found {observation:?}");
+            };
+            assert_eq!(observation.get_count(&ResponseStatusCode::_2XX), 30);
+            assert_eq!(observation.get_count(&ResponseStatusCode::_5XX), 2);
+            assert_eq!(observation.total(), 32);
+        }
+        // Each group is queried with its own selector.
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(
+            requests
+                .iter()
+                .any(|request| request.line.contains("stable"))
+        );
+        assert!(
+            requests
+                .iter()
+                .any(|request| request.line.contains("canary"))
+        );
+        assert!(
+            requests
+                .iter()
+                .all(|request| request.line.contains("/api/v1/query_range"))
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_counters_and_cpu() {
+        let body = json!({
+            "status": "success",
+            "data": {
+                "resultType": "matrix",
+                "result": [
+                    {
+                        "metric": {},
+                        "values": [[1700000000, "2"], [1700000060, "3"]],
+                    },
+                ],
+            },
+        });
+        let server = StubServer::always(200, body.to_string()).await;
+        let mut monitor = monitor(server.origin.clone());
+        monitor.counters = vec!["jobs_failed_total".to_owned()];
+        monitor.cpu_metric = Some("process_cpu_seconds_total".to_owned());
+        let measurements = monitor.query().await.unwrap();
+
+        // Two status codes, then a counter and CPU usage for each group.
+        assert_eq!(measurements.len(), 6);
+        let counters: Vec<_> = measurements
+            .iter()
+            .filter_map(|measurement| match measurement {
+                Measurement::Counter(counter) => Some(counter.to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            counters,
+            vec![
+                "Control jobs_failed_total: 5",
+                "Experimental jobs_failed_total: 5"
+            ]
+        );
+        let cpu: Vec<_> = measurements
+            .iter()
+            .filter_map(|measurement| match measurement {
+                Measurement::Cpu(cpu) => Some(cpu.to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            cpu,
+            vec!["Control CPU usage: 2.50%", "Experimental CPU usage: 2.50%"]
+        );
+        assert_eq!(server.requests().len(), 6);
+    }
+
+    /// A metric nothing is decided on can't hold up the status codes.
+    #[tokio::test]
+    async fn failed_extra_queries_are_skipped() {
+        let matrix = json!({
+            "status": "success",
+            "data": {
+                "resultType": "matrix",
+                "result": [{ "metric": {}, "values": [[1700000000, "2"]] }],
+            },
+        });
+        let error = json!({
+            "status": "error",
+            "errorType": "bad_data",
+            "error": "unknown metric",
+        });
+        let server = StubServer::start(move |request| {
+            if request.line.contains("process_cpu_seconds_total") {
+                (400, error.to_string())
+            } else {
+                (200, matrix.to_string())
+            }
+        })
+        .await;
+        let mut monitor = monitor(server.origin.clone());
+        monitor.counters = vec!["jobs_failed_total".to_owned()];
+        monitor.cpu_metric = Some("process_cpu_seconds_total".to_owned());
+        let measurements = monitor.query().await.unwrap();
+
+        assert_eq!(measurements.len(), 4);
+        assert!(
+            measurements
+                .iter()
+                .all(|measurement| !matches!(measurement, Measurement::Cpu(_)))
+        );
+        assert_eq!(server.requests().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn failed_queries_are_errors() {
+        let body = json!({
+            "status": "error",
+            "errorType": "bad_data",
+            "error": "parse error",
+        });
+        let server = StubServer::always(200, body.to_string()).await;
+        let mut monitor = monitor(server.origin.clone());
+        let before = monitor.last_point;
+        assert!(monitor.query().await.is_err());
+        // The timer still advances.
+        assert!(monitor.last_point > before);
+    }
+
+    #[test]
+    fn promql_uses_group_selector() {
+        let monitor = monitor("http://localhost:9090/".to_owned());
+        assert_eq!(monitor.url, "http://localhost:9090");
+        assert_eq!(
+            monitor.promql(Group::Experimental),
+            r#"sum by (code) (increase(http_requests_total{track="canary"}[60s]))"#
+        );
+    }
+
+    #[tokio::test]
+    async fn classifies_failed_queries() {
+        let test_cases = [
+            (400, MonitorErrorKind::Permanent),
+            (401, MonitorErrorKind::Auth),
+            (503, MonitorErrorKind::Transient),
+        ];
+        for (status, expected) in test_cases {
+            let body = json!({
+                "status": "error",
+                "errorType": "timeout",
+                "error": "query timed out",
+            });
+            let server = StubServer::always(status, body.to_string()).await;
+            let err = monitor(server.origin.clone()).query().await.unwrap_err();
+            assert_eq!(MonitorError::kind_of(&err), expected, "{status}");
+        }
+        // Responses that aren't JSON are classified by their status alone.
+        let server = StubServer::always(403, "Forbidden").await;
+        let err = monitor(server.origin.clone()).query().await.unwrap_err();
+        assert_eq!(MonitorError::kind_of(&err), MonitorErrorKind::Auth);
+    }
+}
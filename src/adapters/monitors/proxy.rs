@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use miette::Result;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::{
+    Shutdownable, adapters::ControlClient, stats::ObservationWindow, subsystems::ShutdownResult,
+};
+
+use super::{Measurement, Monitor};
+
+/// The user-facing configuration for the [ProxyMonitor].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ProxyMonitorConfig {
+    /// The path to the control socket of a running `multi proxy`.
+    control_socket: PathBuf,
+}
+
+/// [ProxyMonitor] collects the response codes observed by a running
+/// `multi proxy`. Since the proxy sees every response, the counts are
+/// exact and available as soon as the response is sent.
+pub struct ProxyMonitor {
+    client: ControlClient,
+    // The time we last collected the proxy's counts.
+    last_query_time: DateTime<Utc>,
+}
+
+impl ProxyMonitor {
+    pub fn new(control_socket: PathBuf) -> Self {
+        Self {
+            client: ControlClient::new(control_socket),
+            last_query_time: Utc::now(),
+        }
+    }
+}
+
+impl From<ProxyMonitorConfig> for ProxyMonitor {
+    fn from(config: ProxyMonitorConfig) -> Self {
+        Self::new(config.control_socket)
+    }
+}
+
+#[async_trait]
+impl Shutdownable for ProxyMonitor {
+    async fn shutdown(&mut self) -> ShutdownResult {
+        // Each query uses its own connection, so there's nothing to close.
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Monitor for ProxyMonitor {
+    async fn query(&mut self) -> Result<Vec<Measurement>> {
+        info!("Querying the proxy for new metrics.");
+        let observations = self.client.observe().await?;
+        // The proxy resets its counts each time we collect them, so
+        // they cover the time since our last query.
+        let end = Utc::now();
+        let window = ObservationWindow::new(self.last_query_time, end);
+        self.last_query_time = end;
+        debug!("Observed: {observations:?}");
+        Ok(observations
+            .into_iter()
+            .map(|observation| Measurement::from(observation).with_window(window))
+            .collect())
+    }
+}
@@ -12,12 +12,34 @@ trait Builder {
 
 pub(crate) struct PlatformBuilder {
     config: PlatformConfig,
-    artifact: LambdaZip,
+    artifact: Option<LambdaZip>,
+    stable_alias: Option<String>,
 }
 
 impl PlatformBuilder {
     pub(crate) fn new(config: PlatformConfig, artifact: LambdaZip) -> Self {
-        Self { config, artifact }
+        Self {
+            config,
+            artifact: Some(artifact),
+            stable_alias: None,
+        }
+    }
+
+    /// Build a platform that manages a canary that's already deployed.
+    /// It has nothing to deploy, so calling `deploy` returns an error.
+    pub(crate) fn without_artifact(config: PlatformConfig) -> Self {
+        Self {
+            config,
+            artifact: None,
+            stable_alias: None,
+        }
+    }
+
+    /// Have the platform move the given alias to the canary's version
+    /// when it's promoted. Without one, the canary can't be promoted.
+    pub(crate) fn with_stable_alias(mut self, stable_alias: Option<String>) -> Self {
+        self.stable_alias = stable_alias;
+        self
     }
 
     pub async fn build(self) -> BoxedPlatform {
@@ -30,9 +52,13 @@ impl Builder for PlatformBuilder {
     async fn build(self) -> BoxedPlatform {
         match self.config {
             PlatformConfig::PlatformConfigOneOf(platform_conf) => {
-                AwsLambdaPlatformBuilder::new(*platform_conf.aws_lambda, self.artifact)
-                    .build()
-                    .await
+                AwsLambdaPlatformBuilder::new(
+                    *platform_conf.aws_lambda,
+                    self.artifact,
+                    self.stable_alias,
+                )
+                .build()
+                .await
             }
         }
     }
@@ -40,12 +66,21 @@ impl Builder for PlatformBuilder {
 
 struct AwsLambdaPlatformBuilder {
     config: PlatformConfigOneOfAwsLambda,
-    artifact: LambdaZip,
+    artifact: Option<LambdaZip>,
+    stable_alias: Option<String>,
 }
 
 impl AwsLambdaPlatformBuilder {
-    fn new(config: PlatformConfigOneOfAwsLambda, artifact: LambdaZip) -> Self {
-        Self { config, artifact }
+    fn new(
+        config: PlatformConfigOneOfAwsLambda,
+        artifact: Option<LambdaZip>,
+        stable_alias: Option<String>,
+    ) -> Self {
+        Self {
+            config,
+            artifact,
+            stable_alias,
+        }
     }
 }
 
@@ -55,7 +90,8 @@ impl Builder for AwsLambdaPlatformBuilder {
         let lambda = LambdaPlatform::builder()
             .name(self.config.name)
             .region(self.config.region)
-            .artifact(self.artifact)
+            .maybe_artifact(self.artifact)
+            .maybe_stable_alias(self.stable_alias)
             .build()
             .await;
         Box::new(lambda)
@@ -1,10 +1,13 @@
 use async_trait::async_trait;
 use bon::bon;
-use miette::{IntoDiagnostic as _, Result, miette};
+use miette::{IntoDiagnostic as _, Result, bail, miette};
 use tracing::info;
 
 use crate::{
-    Shutdownable, artifacts::LambdaZip, subsystems::ShutdownResult, utils::load_default_aws_config,
+    Shutdownable,
+    artifacts::LambdaZip,
+    subsystems::ShutdownResult,
+    utils::{alias_version, load_default_aws_config, version_from_arn},
 };
 use aws_sdk_lambda::{client::Client, primitives::Blob, types::FunctionCode};
 
@@ -14,14 +17,30 @@ pub struct LambdaPlatform {
     client: Client,
     region: String,
     name: String,
-    artifact: LambdaZip,
+    /// The code to deploy. It's missing when we're only managing
+    /// a canary that's already deployed.
+    artifact: Option<LambdaZip>,
     arn: Option<String>,
+    /// The alias pointing at the version that serves production traffic.
+    /// Promotion moves this alias to the canary's version, so it's only
+    /// `None` when we'll never promote, like when rolling back by hand.
+    stable_alias: Option<String>,
+    /// The version the stable alias pointed to before we deployed.
+    /// After promotion, it's kept around as a hot standby.
+    previous_version: Option<String>,
+    /// The version we published for the canary.
+    new_version: Option<String>,
 }
 
 #[bon]
 impl LambdaPlatform {
     #[builder]
-    pub async fn new(region: String, name: String, artifact: LambdaZip) -> Self {
+    pub async fn new(
+        region: String,
+        name: String,
+        artifact: Option<LambdaZip>,
+        stable_alias: Option<String>,
+    ) -> Self {
         let config = load_default_aws_config().await;
         let client = aws_sdk_lambda::Client::new(config);
         Self {
@@ -30,8 +49,33 @@ impl LambdaPlatform {
             name,
             artifact,
             arn: None,
+            stable_alias,
+            previous_version: None,
+            new_version: None,
         }
     }
+
+    /// Returns the version the stable alias points to, or `None`
+    /// if there's no stable alias, or it doesn't exist yet.
+    async fn stable_version(&self) -> Result<Option<String>> {
+        let Some(stable_alias) = &self.stable_alias else {
+            return Ok(None);
+        };
+        alias_version(&self.client, &self.name, stable_alias)
+            .await
+            .into_diagnostic()
+    }
+}
+
+/// Qualify the ARN with the given version, so the ingress routes
+/// to exactly this version.
+fn qualify_arn(arn: &str, version: &str) -> String {
+    let suffix = format!(":{version}");
+    if arn.ends_with(&suffix) {
+        arn.to_owned()
+    } else {
+        format!("{arn}{suffix}")
+    }
 }
 
 #[async_trait]
@@ -39,9 +83,16 @@ impl Platform for LambdaPlatform {
     /// Update the Lambda code with the zip we're holding.
     async fn deploy(&mut self) -> Result<String> {
         info!("Deploying Lambda!");
+        // Remember which version is currently stable, so we
+        // know what to fall back to.
+        self.previous_version = self.stable_version().await?;
         // First, we need to deploy the new version of the lambda
         // Parse the bytes into the format AWS wants
-        let code = Blob::from(self.artifact.as_ref());
+        let artifact = self
+            .artifact
+            .as_ref()
+            .ok_or(miette!("No artifact was provided to deploy"))?;
+        let code = Blob::from(artifact.as_ref());
 
         // Turn it into an uploadable zip file
         let function_code = FunctionCode::builder().zip_file(code).build();
@@ -62,10 +113,13 @@ impl Platform for LambdaPlatform {
 
         let function_arn = res
             .function_arn()
-            .map(ToString::to_string)
             .ok_or(miette!("Couldn't get ARN of deployed lambda"))?;
+        let version = res
+            .version()
+            .ok_or(miette!("Couldn't get version of deployed lambda"))?;
+        self.new_version = Some(version.to_owned());
 
-        self.arn = Some(function_arn);
+        self.arn = Some(qualify_arn(function_arn, version));
         self.arn
             .clone()
             .ok_or_else(|| miette!("No ARN returned from AWS"))
@@ -76,10 +130,17 @@ impl Platform for LambdaPlatform {
         Ok(())
     }
 
+    /// Delete the version we published for the canary. Only that
+    /// version is deleted, never the function itself.
     async fn delete_canary(&mut self) -> Result<()> {
+        let new_version = self
+            .new_version
+            .as_ref()
+            .ok_or(miette!("No canary has been deployed"))?;
         self.client
             .delete_function()
-            .function_name(self.arn.as_ref().ok_or(miette!("Lambda ARN not set"))?)
+            .function_name(&self.name)
+            .qualifier(new_version)
             .send()
             .await
             .into_diagnostic()?;
@@ -87,8 +148,68 @@ impl Platform for LambdaPlatform {
         Ok(())
     }
 
+    /// Move the stable alias to the canary's version. The previous
+    /// version isn't deleted, so it remains available as a hot standby.
     async fn promote_rollout(&mut self) -> Result<()> {
-        todo!("I don't think Lambdas promote until we support Lambda Aliases.")
+        let new_version = self
+            .new_version
+            .clone()
+            .ok_or(miette!("No canary has been deployed"))?;
+        let Some(stable_alias) = &self.stable_alias else {
+            bail!(
+                "Lambda {} version {new_version} can't be promoted, because no stable alias is configured",
+                self.name
+            );
+        };
+        info!(
+            "Promoting Lambda {} version {new_version} in {}.",
+            self.name, self.region
+        );
+        if self.stable_version().await?.is_some() {
+            self.client
+                .update_alias()
+                .function_name(&self.name)
+                .name(stable_alias)
+                .function_version(&new_version)
+                .send()
+                .await
+                .into_diagnostic()?;
+        } else {
+            self.client
+                .create_alias()
+                .function_name(&self.name)
+                .name(stable_alias)
+                .function_version(&new_version)
+                .send()
+                .await
+                .into_diagnostic()?;
+        }
+        if let Some(previous) = &self.previous_version {
+            info!("Version {previous} remains available as a hot standby.");
+        }
+        Ok(())
+    }
+
+    /// The canary is the version the earlier run published, which is
+    /// the last segment of its qualified ARN.
+    async fn reattach(&mut self, canary_id: String) -> Result<()> {
+        let version = version_from_arn(&canary_id)?;
+        // Make sure the version wasn't deleted since it was deployed.
+        self.client
+            .get_function_configuration()
+            .function_name(&self.name)
+            .qualifier(&version)
+            .send()
+            .await
+            .into_diagnostic()?;
+        self.previous_version = self.stable_version().await?;
+        info!(
+            "Reattaching to canary version {version} of Lambda {}.",
+            self.name
+        );
+        self.new_version = Some(version);
+        self.arn = Some(canary_id);
+        Ok(())
     }
 }
 
@@ -100,3 +221,17 @@ impl Shutdownable for LambdaPlatform {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::qualify_arn;
+
+    #[test]
+    fn qualify_arns() {
+        let arn = "arn:aws:lambda:us-east-2:123456789012:function:my-function";
+        assert_eq!(qualify_arn(arn, "7"), format!("{arn}:7"));
+        assert_eq!(qualify_arn(&format!("{arn}:7"), "7"), format!("{arn}:7"));
+    }
+}
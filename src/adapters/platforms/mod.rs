@@ -20,6 +20,10 @@ pub trait Platform: Shutdownable {
     async fn delete_canary(&mut self) -> Result<()>;
     /// Make the canary app the new baseline.
     async fn promote_rollout(&mut self) -> Result<()>;
+    /// Pick up the canary deployed by an earlier run of the rollout, so
+    /// the rollout can be resumed without deploying it again. The canary
+    /// is identified by the id `deploy` returned when it was deployed.
+    async fn reattach(&mut self, canary_id: String) -> Result<()>;
 }
 
 #[async_trait]
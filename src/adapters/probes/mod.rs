@@ -0,0 +1,316 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use chrono::Utc;
+use miette::{Diagnostic, IntoDiagnostic, Result};
+use reqwest::{Client, Method};
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+use super::{Latency, Measurement, StatusCode};
+use crate::{
+    metrics::{LatencyBucket, ResponseStatusCode},
+    stats::{Group, ObservationWindow},
+};
+
+/// How long a probe may wait for the canary to respond, unless configured otherwise.
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// A [ProbeConfig] lists synthetic requests to send once the canary is
+/// deployed, before it receives any user traffic. Their responses are
+/// observations of the canary, and if any of them isn't what's expected,
+/// the canary is rolled back.
+///
+/// The URL and headers of each request may use `{{canary_id}}`, the
+/// platform's identifier for the canary, and `{{canary_version}}`, its
+/// last `:`-separated segment, e.g. a Lambda version. These route the
+/// request straight to the canary, say through an API Gateway stage
+/// whose integration points at the version in a stage variable. Every
+/// request must use one of them, since before the canary receives any
+/// traffic, a request the ingress routes goes to the baseline instead.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct ProbeConfig {
+    #[serde(deserialize_with = "routed_to_canary")]
+    requests: Vec<ProbeRequest>,
+    /// How long each request may take, in seconds.
+    #[serde(default = "default_timeout_secs")]
+    timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+/// A single synthetic request, and what its response must look like.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct ProbeRequest {
+    url: String,
+    #[serde(default = "default_method")]
+    method: ProbeMethod,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    body: Option<String>,
+    /// The status the canary must respond with. If omitted,
+    /// any 2XX status passes.
+    #[serde(default)]
+    expect_status: Option<u16>,
+    /// Text the response body must contain.
+    #[serde(default)]
+    expect_body: Option<String>,
+}
+
+fn default_method() -> ProbeMethod {
+    ProbeMethod(Method::GET)
+}
+
+/// Reject any request that doesn't route straight to the canary.
+fn routed_to_canary<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<ProbeRequest>, D::Error> {
+    let requests = Vec::<ProbeRequest>::deserialize(deserializer)?;
+    if let Some(request) = requests.iter().find(|request| !request.routes_to_canary()) {
+        return Err(D::Error::custom(format!(
+            "The probe of {} would be served by the baseline. Route it to the canary with {{{{canary_id}}}} or {{{{canary_version}}}} in its URL or headers.",
+            request.url
+        )));
+    }
+    Ok(requests)
+}
+
+/// An HTTP method, checked when the config is loaded
+/// rather than when the canary is probed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+struct ProbeMethod(Method);
+
+impl TryFrom<String> for ProbeMethod {
+    type Error = String;
+
+    fn try_from(method: String) -> Result<Self, Self::Error> {
+        Method::from_bytes(method.as_bytes())
+            .map(Self)
+            .map_err(|_| format!("{method:?} isn't an HTTP method"))
+    }
+}
+
+impl From<ProbeMethod> for String {
+    fn from(method: ProbeMethod) -> Self {
+        method.0.to_string()
+    }
+}
+
+/// Returned when some of the canary's responses weren't what
+/// the probes expected.
+#[derive(thiserror::Error, Debug, Diagnostic)]
+#[error("{failed} of {total} synthetic probes of the canary failed")]
+#[diagnostic(help("Each failure is logged above. The canary was rolled back."))]
+pub(crate) struct ProbeError {
+    failed: usize,
+    total: usize,
+}
+
+/// The outcome of probing the canary.
+pub(crate) struct ProbeReport {
+    /// What the probes observed, attributed to the canary.
+    pub(crate) measurements: Vec<Measurement>,
+    failed: usize,
+    total: usize,
+}
+
+impl ProbeReport {
+    /// Fails if any of the probes did.
+    pub(crate) fn check(&self) -> Result<()> {
+        if self.failed > 0 {
+            return Err(ProbeError {
+                failed: self.failed,
+                total: self.total,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+impl ProbeConfig {
+    /// Send each request to the given canary, in order.
+    pub(crate) async fn run(&self, canary_id: &str) -> Result<ProbeReport> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(self.timeout_secs))
+            .build()
+            .into_diagnostic()?;
+        let canary_version = canary_id.rsplit(':').next().unwrap_or(canary_id);
+        let fill = |template: &str| {
+            template
+                .replace("{{canary_id}}", canary_id)
+                .replace("{{canary_version}}", canary_version)
+        };
+
+        let start = Utc::now();
+        let mut status_codes = StatusCode::new(Group::Experimental);
+        let mut latencies = Latency::new(Group::Experimental);
+        let mut failed = 0;
+        for probe in &self.requests {
+            let method = probe.method.0.clone();
+            let url = fill(&probe.url);
+            let mut request = client.request(method.clone(), &url);
+            for (name, value) in &probe.headers {
+                request = request.header(name, fill(value));
+            }
+            if let Some(body) = &probe.body {
+                request = request.body(body.clone());
+            }
+            info!("Probing the canary with {method} {url}...");
+            let sent_at = Instant::now();
+            let outcome = match request.send().await {
+                Ok(response) => {
+                    let status = response.status().as_u16();
+                    let body = response.text().await.unwrap_or_default();
+                    latencies.increment_by(
+                        &LatencyBucket::from_millis(sent_at.elapsed().as_secs_f64() * 1000.0),
+                        1,
+                    );
+                    if let Some(category) = ResponseStatusCode::from_status(status) {
+                        status_codes.increment_by(&category, 1);
+                    }
+                    probe.verify(status, &body)
+                }
+                Err(err) => {
+                    // From the caller's point of view, a canary that
+                    // can't be reached failed to serve the request.
+                    status_codes.increment_by(&ResponseStatusCode::_5XX, 1);
+                    Err(format!("the request failed: {err}"))
+                }
+            };
+            match outcome {
+                Ok(()) => debug!("The probe {method} {url} passed"),
+                Err(reason) => {
+                    warn!("The probe {method} {url} failed: {reason}");
+                    failed += 1;
+                }
+            }
+        }
+        let window = ObservationWindow::new(start, Utc::now());
+        Ok(ProbeReport {
+            measurements: vec![
+                Measurement::StatusCode(status_codes).with_window(window),
+                Measurement::Latency(latencies).with_window(window),
+            ],
+            failed,
+            total: self.requests.len(),
+        })
+    }
+}
+
+impl ProbeRequest {
+    fn routes_to_canary(&self) -> bool {
+        let routes =
+            |text: &str| text.contains("{{canary_id}}") || text.contains("{{canary_version}}");
+        routes(&self.url) || self.headers.values().any(|value| routes(value))
+    }
+
+    /// Describe how the response differs from what was expected, if it does.
+    fn verify(&self, status: u16, body: &str) -> Result<(), String> {
+        match self.expect_status {
+            Some(expected) if status != expected => {
+                return Err(format!("expected status {expected}, got {status}"));
+            }
+            None if !(200..300).contains(&status) => {
+                return Err(format!("expected a 2XX status, got {status}"));
+            }
+            _ => (),
+        }
+        match &self.expect_body {
+            Some(expected) if !body.contains(expected.as_str()) => {
+                Err(format!("the response body doesn't contain {expected:?}"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::ProbeConfig;
+    use crate::{
+        adapters::{Measurement, ResponseTally},
+        stats::Group,
+        utils::StubServer,
+    };
+
+    #[tokio::test]
+    async fn probe_the_canary() {
+        let server = StubServer::replying(vec![(200, "ok"), (200, "not ready"), (503, "")]).await;
+        let origin = &server.origin;
+        let config: ProbeConfig = toml::from_str(&format!(
+            r#"
+            [[requests]]
+            url = "{origin}/{{{{canary_version}}}}/health"
+            expect_body = "ok"
+
+            [[requests]]
+            url = "{origin}/{{{{canary_version}}}}/ready"
+            expect_body = "ok"
+
+            [[requests]]
+            url = "{origin}/orders"
+            method = "POST"
+            headers = {{ x-canary = "{{{{canary_version}}}}" }}
+            expect_status = 201
+            "#
+        ))
+        .unwrap();
+        let report = config
+            .run("arn:aws:lambda:us-east-1:1234:function:orders:7")
+            .await
+            .unwrap();
+        assert!(report.check().is_err());
+        assert_eq!((report.failed, report.total), (2, 3));
+        assert_eq!(
+            ResponseTally::new(&report.measurements, Group::Experimental),
+            ResponseTally {
+                errors: 1,
+                total: 3
+            }
+        );
+        assert_eq!(
+            ResponseTally::new(&report.measurements, Group::Control),
+            ResponseTally::default()
+        );
+        assert!(matches!(report.measurements[1], Measurement::Latency(_)));
+
+        let requests = server.requests();
+        assert!(requests[0].head.starts_with("get /7/health "));
+        assert!(requests[2].head.starts_with("post /orders "));
+        assert!(requests[2].head.lines().any(|line| line == "x-canary: 7"));
+    }
+
+    /// Mistakes in the config are caught before anything is deployed.
+    #[test]
+    fn invalid_probes_are_rejected() {
+        let unrouted = toml::from_str::<ProbeConfig>(
+            r#"
+            [[requests]]
+            url = "https://api.example.com/health"
+            "#,
+        )
+        .unwrap_err();
+        assert!(
+            unrouted
+                .to_string()
+                .contains("would be served by the baseline")
+        );
+
+        let method = toml::from_str::<ProbeConfig>(
+            r#"
+            [[requests]]
+            url = "https://api.example.com/{{canary_version}}/health"
+            method = "GET /"
+            "#,
+        )
+        .unwrap_err();
+        assert!(method.to_string().contains("isn't an HTTP method"));
+    }
+}
@@ -0,0 +1,309 @@
+use miette::{Result, bail};
+use multitool_sdk::models::{RolloutState, RolloutStateType};
+use tokio::runtime::Runtime;
+use tracing::{debug, info, warn};
+
+use crate::adapters::backend::{RolloutId, canary_deployed, is_in_flight};
+use crate::adapters::{
+    BackendClient, BoxedIngress, IngressBuilder, PlatformBuilder, RolloutMetadata, find_canary,
+};
+use crate::fs::{FileSystem, InFlightRollout, InFlightRolloutFile, SessionFile};
+use crate::{Terminal, WholePercent, config::ManualSubcommand};
+
+/// The outcome an operator forces on a rollout.
+#[derive(Clone, Copy, Debug)]
+pub enum Outcome {
+    /// Make the canary the new baseline.
+    Promote,
+    /// Cut all traffic to the canary.
+    Rollback,
+}
+
+impl Outcome {
+    fn past_tense(&self) -> &'static str {
+        match self {
+            Self::Promote => "promoted",
+            Self::Rollback => "rolled back",
+        }
+    }
+
+    /// The state that settles a rollout with this outcome.
+    fn state_type(&self) -> RolloutStateType {
+        match self {
+            Self::Promote => RolloutStateType::PromoteCanary,
+            Self::Rollback => RolloutStateType::RollbackCanary,
+        }
+    }
+}
+
+/// Settle a rollout by hand, driving the platform and ingress
+/// directly instead of waiting for the backend to decide.
+pub struct Override {
+    terminal: Terminal,
+    backend: BackendClient,
+    workspace_name: String,
+    application_name: String,
+    rollout_id: u64,
+    outcome: Outcome,
+    stable_alias: Option<String>,
+}
+
+impl Override {
+    pub fn new(terminal: Terminal, args: ManualSubcommand, outcome: Outcome) -> Result<Self> {
+        // • Promotion moves the stable alias, so make sure
+        //   we have one before touching anything.
+        if matches!(outcome, Outcome::Promote) && args.stable_alias().is_none() {
+            bail!(
+                "Pass `--stable-alias` with the Lambda alias that serves production traffic, so the canary can be promoted"
+            );
+        }
+        let fs = FileSystem::new()?;
+        let session = fs.load_file(SessionFile)?;
+        let backend = BackendClient::new(args.origin().as_deref(), Some(session))?;
+        Ok(Self {
+            terminal,
+            backend,
+            workspace_name: args.workspace().clone(),
+            application_name: args.application().clone(),
+            rollout_id: *args.rollout_id(),
+            outcome,
+            stable_alias: args.stable_alias().clone(),
+        })
+    }
+
+    pub fn dispatch(self) -> Result<()> {
+        let rt = Runtime::new().unwrap();
+        let _guard = rt.enter();
+        rt.block_on(async {
+            debug!("Loading workspace and application...");
+            let workspace = self
+                .backend
+                .get_workspace_by_name(&self.workspace_name)
+                .await?;
+            let application = self
+                .backend
+                .get_application_by_name(workspace.id, &self.application_name)
+                .await?;
+            let meta = RolloutMetadata::builder()
+                .workspace_id(workspace.id)
+                .application_id(application.id)
+                .rollout_id(self.rollout_id)
+                .build();
+
+            // Only settle rollouts that are still in flight, and only
+            // the canary that was deployed for them.
+            let states = self.backend.rollout_states(&meta).await?;
+            let fs = FileSystem::new()?;
+            let in_flight = InFlightRolloutFile::new(workspace.id, application.id);
+            let recorded = fs.load_file(in_flight).ok();
+            let mut ingress = IngressBuilder::new(*application.ingress).build().await;
+            let canary =
+                target_canary(self.outcome, self.rollout_id, &states, &ingress, recorded).await?;
+
+            if let Some(canary) = canary {
+                // There's nothing to deploy, so we only need the platform
+                // to pick up the canary that's already running.
+                let mut platform = PlatformBuilder::without_artifact(*application.platform)
+                    .with_stable_alias(self.stable_alias.clone())
+                    .build()
+                    .await;
+                platform.reattach(canary.clone()).await?;
+                ingress.reattach(canary).await?;
+
+                match self.outcome {
+                    Outcome::Promote => {
+                        info!("Promoting rollout {}...", self.rollout_id);
+                        ingress.promote_canary().await?;
+                        platform.promote_rollout().await?;
+                    }
+                    Outcome::Rollback => {
+                        info!("Rolling back rollout {}...", self.rollout_id);
+                        ingress
+                            .set_canary_traffic(WholePercent::try_from(0).unwrap())
+                            .await?;
+                        ingress.rollback_canary().await?;
+                    }
+                }
+            } else {
+                info!(
+                    "The canary for rollout {} was never deployed, so there's nothing to roll back",
+                    self.rollout_id
+                );
+            }
+
+            // Now that the cloud resources are settled, tell the
+            // backend, so no runner keeps effecting its states.
+            if !self
+                .backend
+                .record_outcome(&meta, self.outcome.state_type())
+                .await?
+            {
+                warn!(
+                    "The backend hasn't scheduled the canary to be {} yet, so rollout {} may still look unfinished there",
+                    self.outcome.past_tense(),
+                    self.rollout_id
+                );
+            }
+
+            // The rollout is finished, so `multi run` shouldn't resume it.
+            in_flight.forget(&fs, self.rollout_id)?;
+
+            self.terminal
+                .override_successful(self.outcome.past_tense(), self.rollout_id)
+        })
+    }
+}
+
+/// Find the canary to settle. We refuse to settle a rollout that's
+/// already finished, or whose canary was deployed but can't be found
+/// in the record or the ingress, since we can't tell which it is.
+async fn target_canary(
+    outcome: Outcome,
+    rollout_id: RolloutId,
+    states: &[RolloutState],
+    ingress: &BoxedIngress,
+    recorded: Option<InFlightRollout>,
+) -> Result<Option<String>> {
+    if !is_in_flight(states) {
+        bail!("Rollout {rollout_id} is already finished, so there's nothing to settle");
+    }
+    let recorded = recorded
+        .filter(|recorded| recorded.rollout_id() == rollout_id)
+        .and_then(|recorded| recorded.canary_id().clone());
+    let canary = find_canary(ingress, rollout_id, recorded, canary_deployed(states)).await?;
+    match (canary, outcome) {
+        (Some(canary), _) => Ok(Some(canary)),
+        (None, Outcome::Promote) => {
+            bail!(
+                "The canary for rollout {rollout_id} hasn't been deployed, so it can't be promoted"
+            )
+        }
+        (None, Outcome::Rollback) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use multitool_sdk::models::{RolloutState, RolloutStateStatus, RolloutStateType};
+    use pretty_assertions::assert_eq;
+
+    use super::{Outcome, target_canary};
+    use crate::{
+        adapters::{BoxedIngress, MockIngress, RolloutMetadata},
+        fs::InFlightRollout,
+    };
+
+    fn state(id: u64, state_type: RolloutStateType, status: RolloutStateStatus) -> RolloutState {
+        RolloutState {
+            id,
+            state_type,
+            status,
+            ..Default::default()
+        }
+    }
+
+    fn recorded(rollout_id: u64) -> Option<InFlightRollout> {
+        let meta = RolloutMetadata::builder()
+            .workspace_id(1)
+            .application_id(2)
+            .rollout_id(rollout_id)
+            .build();
+        Some(InFlightRollout::new(meta, Some("arn:fn:7".to_owned())))
+    }
+
+    /// An ingress routing to the given canary, if any.
+    fn routing_to(canary: Option<&'static str>) -> BoxedIngress {
+        let mut ingress = MockIngress::new();
+        ingress
+            .expect_discover_canary()
+            .returning(move || Ok(canary.map(ToOwned::to_owned)));
+        Box::new(ingress)
+    }
+
+    fn deployed() -> [RolloutState; 2] {
+        [
+            state(1, RolloutStateType::DeployCanary, RolloutStateStatus::Done),
+            state(
+                2,
+                RolloutStateType::SetCanaryTraffic,
+                RolloutStateStatus::Pending,
+            ),
+        ]
+    }
+
+    /// The canary recorded for the rollout is the one that's settled.
+    #[tokio::test]
+    async fn settle_the_recorded_canary() {
+        let ingress = routing_to(Some("arn:fn:8"));
+        for outcome in [Outcome::Promote, Outcome::Rollback] {
+            assert_eq!(
+                target_canary(outcome, 3, &deployed(), &ingress, recorded(3))
+                    .await
+                    .unwrap(),
+                Some("arn:fn:7".to_owned())
+            );
+        }
+    }
+
+    /// A canary deployed from another machine is found in the ingress.
+    #[tokio::test]
+    async fn settle_the_discovered_canary() {
+        let ingress = routing_to(Some("arn:fn:8"));
+        for recorded in [None, recorded(3)] {
+            assert_eq!(
+                target_canary(Outcome::Promote, 4, &deployed(), &ingress, recorded)
+                    .await
+                    .unwrap(),
+                Some("arn:fn:8".to_owned())
+            );
+        }
+    }
+
+    /// Settled rollouts, and rollouts whose canary we can't find, are refused.
+    #[tokio::test]
+    async fn refuse_to_guess() {
+        let settled = [
+            state(1, RolloutStateType::DeployCanary, RolloutStateStatus::Done),
+            state(2, RolloutStateType::PromoteCanary, RolloutStateStatus::Done),
+        ];
+        let ingress = routing_to(None);
+        let err = target_canary(Outcome::Rollback, 3, &settled, &ingress, recorded(3))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("already finished"));
+
+        // The record is for another rollout, and the ingress has no canary.
+        let err = target_canary(Outcome::Promote, 4, &deployed(), &ingress, recorded(3))
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Rollout 4 deployed a canary, but it isn't recorded")
+        );
+        assert!(
+            target_canary(Outcome::Rollback, 4, &deployed(), &ingress, None)
+                .await
+                .is_err()
+        );
+
+        // Without a canary, there's nothing to promote, but
+        // rolling back only needs the backend to be told.
+        let undeployed = [state(
+            1,
+            RolloutStateType::DeployCanary,
+            RolloutStateStatus::Pending,
+        )];
+        let ingress = routing_to(Some("arn:fn:8"));
+        assert!(
+            target_canary(Outcome::Promote, 4, &undeployed, &ingress, None)
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            target_canary(Outcome::Rollback, 4, &undeployed, &ingress, None)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+}
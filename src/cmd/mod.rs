@@ -1,6 +1,8 @@
 pub use login::Login;
 pub use logout::Logout;
+pub use manual::{Outcome, Override};
 pub use run::Run;
+pub use status::Status;
 pub use version::Version;
 
 #[cfg(feature = "proxy")]
@@ -8,7 +10,10 @@ pub use proxy::Proxy;
 
 mod login;
 mod logout;
+/// Commands that let an operator settle a rollout by hand.
+mod manual;
 mod run;
+mod status;
 mod version;
 
 #[cfg(feature = "proxy")]
@@ -1,17 +1,17 @@
 #![cfg(feature = "proxy")]
 
-use crate::{Terminal, config::ProxySubcommand};
+use std::sync::Mutex;
+
+use crate::adapters::{ControlServer, ProxyObservations, ProxyRoutes};
+use crate::stats::Group;
+use crate::{Terminal, WholePercent, config::ProxySubcommand};
 use async_trait::async_trait;
-use miette::Result;
-use pingora::lb::selection::weighted::Weighted;
-use pingora::{lb::selection::SelectionAlgorithm, prelude::*};
-use rand::SeedableRng;
-use rand::distr::{Bernoulli, Distribution};
+use miette::{IntoDiagnostic, Result, miette};
+use pingora::prelude::*;
 use rand::rngs::SmallRng;
-use std::cell::RefCell;
-use std::sync::{Arc, Mutex};
-
-const DEFAULT_CANARY_CHANCE: f64 = 0.4;
+use rand::{Rng, SeedableRng};
+use tokio::runtime::Runtime;
+use tracing::{debug, error, info};
 
 pub struct Proxy {
     _terminal: Terminal,
@@ -27,96 +27,106 @@ impl Proxy {
     }
 
     pub fn dispatch(self) -> Result<()> {
-        let mut server = Server::new(None).unwrap();
+        let percent = WholePercent::try_from(*self.args.canary_percent()).into_diagnostic()?;
+        let routes = ProxyRoutes::new(
+            self.args.baseline().clone(),
+            self.args.canary().clone(),
+            percent,
+        );
+        let observations = ProxyObservations::new();
+
+        // Pingora owns the main thread once the server starts,
+        // so the control socket gets a runtime of its own.
+        if let Some(path) = self.args.control_socket().clone() {
+            let server = ControlServer::new(routes.clone(), observations.clone(), path);
+            let runtime = Runtime::new().into_diagnostic()?;
+            std::thread::spawn(move || {
+                if let Err(err) = runtime.block_on(server.serve()) {
+                    error!("The control socket stopped: {err}");
+                }
+            });
+        }
+
+        let mut server = Server::new(None).map_err(|err| miette!("{err}"))?;
         server.bootstrap();
 
-        let proxy = MultiProxy::new(&self.args);
+        info!(
+            "Proxying requests on {}, sending {} to the canary",
+            self.args.listen(),
+            routes.canary_percent()
+        );
+        let proxy = MultiProxy::new(routes, observations);
         let mut proxy_service = http_proxy_service(&server.configuration, proxy);
-        proxy_service.add_tcp("0.0.0.0:8000");
+        proxy_service.add_tcp(self.args.listen());
         server.add_service(proxy_service);
         server.run_forever();
     }
 }
 
 struct MultiProxy {
-    balancer: Arc<LoadBalancer<Weighted<CanarySelector>>>,
-    is_canary: Box<dyn Fn(String) -> bool + Send + Sync>,
+    routes: ProxyRoutes,
+    observations: ProxyObservations,
+    rng: Mutex<SmallRng>,
 }
 
-struct CanarySelector {
-    dist: Bernoulli,
-    rng: Mutex<RefCell<SmallRng>>,
-}
-
-impl SelectionAlgorithm for CanarySelector {
-    fn new() -> Self {
+impl MultiProxy {
+    pub fn new(routes: ProxyRoutes, observations: ProxyObservations) -> Self {
         Self {
-            dist: Bernoulli::new(DEFAULT_CANARY_CHANCE).unwrap(),
-            rng: Mutex::new(RefCell::new(SmallRng::from_rng(&mut rand::rng()))),
-        }
-    }
-
-    fn next(&self, _key: &[u8]) -> u64 {
-        let rng_guard = self.rng.lock().unwrap();
-        // NOTE: Without the explicit RefCell::borrow_mut function references,
-        // (i.e. if you use self.borrow_mut instead), you'll call the MutexGuard's
-        // version of the function instead of the RefCell's version, as the MutexGuard
-        // shadowed the same-named function in this scope.
-        let mut borrowed = RefCell::borrow_mut(&rng_guard);
-        if self.dist.sample(&mut borrowed) {
-            1
-        } else {
-            0
+            routes,
+            observations,
+            rng: Mutex::new(SmallRng::from_rng(&mut rand::rng())),
         }
     }
-}
 
-impl MultiProxy {
-    pub fn new(flags: &ProxySubcommand) -> Self {
-        let canary = flags.canary().clone();
-        let baseline = flags.baseline().clone();
-        let canary_ip = canary.clone();
-        let is_canary = Box::new(move |ip| ip == canary_ip);
-        let upstreams = LoadBalancer::try_from_iter([baseline, canary]).unwrap();
-
-        Self {
-            balancer: Arc::new(upstreams),
-            is_canary,
-        }
+    /// Roll the dice to decide which upstream serves the next request.
+    fn draw(&self) -> u32 {
+        let mut rng = self.rng.lock().unwrap();
+        rng.random_range(0..100)
     }
 }
 
 #[async_trait]
 impl ProxyHttp for MultiProxy {
-    type CTX = ();
+    /// The group whose upstream is serving the request, once chosen.
+    type CTX = Option<Group>;
 
-    fn new_ctx(&self) {}
+    fn new_ctx(&self) -> Self::CTX {
+        None
+    }
 
-    async fn logging(&self, session: &mut Session, _e: Option<&Error>, _ctx: &mut Self::CTX) {
-        if let Some(resp) = session.response_written() {
-            let status = resp.status;
-            println!("{status}");
+    async fn logging(&self, session: &mut Session, _e: Option<&Error>, ctx: &mut Self::CTX) {
+        // Requests that never reached an upstream say nothing
+        // about the baseline or the canary.
+        if let (Some(resp), Some(group)) = (session.response_written(), *ctx) {
+            debug!("{group:?} responded with {}", resp.status);
+            self.observations.record(group, resp.status.as_u16());
         }
     }
 
     async fn upstream_peer(
         &self,
         _session: &mut Session,
-        _ctx: &mut (),
+        ctx: &mut Self::CTX,
     ) -> pingora::Result<Box<HttpPeer>> {
-        let upstream = self
-            .balancer
-            .select(b"", 256) // hash doesn't matter for round robin
-            .unwrap();
-
-        let addr = upstream.addr.clone();
-        if (self.is_canary)(format!("{addr}")) {
-            println!("Proxying to canary at {addr}");
-        } else {
-            println!("Proxying to baseline at {addr}");
+        let (addr, group) = self.routes.choose(self.draw());
+        *ctx = Some(group);
+        match group {
+            Group::Control => debug!("Proxying to baseline at {addr}"),
+            Group::Experimental => debug!("Proxying to canary at {addr}"),
         }
-
-        let peer = Box::new(HttpPeer::new(upstream, false, "".to_owned()));
+        // Resolve the address without blocking the runtime, since
+        // upstreams can be given by hostname.
+        let socket_addr = tokio::net::lookup_host(&addr)
+            .await
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .ok_or_else(|| {
+                Error::explain(
+                    ErrorType::ConnectNoRoute,
+                    format!("Could not resolve upstream {addr}"),
+                )
+            })?;
+        let peer = Box::new(HttpPeer::new(socket_addr, false, "".to_owned()));
         Ok(peer)
     }
 }
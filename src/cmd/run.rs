@@ -1,19 +1,32 @@
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use crate::adapters::backend::{ApplicationId, WorkspaceId};
+use crate::adapters::backend::{
+    ApplicationId, RolloutId, WorkspaceId, canary_deployed, is_in_flight,
+};
 use crate::adapters::{
-    ApplicationConfig, IngressBuilder, MonitorBuilder, PlatformBuilder, RolloutMetadata,
+    ApplicationConfig, EventSinkConfig, Events, HooksConfig, IngressBuilder, LocalBackend,
+    MonitorBuilder, PlatformBuilder, ProbeConfig, RolloutEvent, RolloutMetadata, SharedBackend,
+    WebhookConfig, find_canary,
+};
+use crate::fs::{
+    FileSystem, HooksConfigFile, InFlightRollout, InFlightRolloutFile, LocalRunConfig,
+    LocalRunConfigFile, NotifierConfigFile, ProbeConfigFile, SessionFile,
+};
+use crate::subsystems::{
+    CONTROLLER_SUBSYSTEM_NAME, EVENTS_SUBSYSTEM_NAME, EventSubsystem, NOTIFIER_SUBSYSTEM_NAME,
 };
-use crate::fs::{FileSystem, SessionFile};
-use crate::subsystems::CONTROLLER_SUBSYSTEM_NAME;
+use crate::terminal::DASHBOARD_SUBSYSTEM_NAME;
 use crate::{
     ControllerSubsystem, adapters::BackendClient, artifacts::LambdaZip, config::RunSubcommand,
 };
-use miette::Result;
+use chrono::Utc;
+use miette::{Result, bail, miette};
 use tokio::runtime::Runtime;
+use tokio::sync::watch;
 use tokio::time::Duration;
 use tokio_graceful_shutdown::{IntoSubsystem as _, SubsystemBuilder, Toplevel};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 use crate::Terminal;
 
@@ -23,27 +36,104 @@ const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 5000;
 
 /// Deploy the Lambda function as a canary and monitor it.
 pub struct Run {
-    _terminal: Terminal,
+    terminal: Terminal,
     artifact_path: PathBuf,
-    workspace_name: String,
-    application_name: String,
-    backend: BackendClient,
+    mode: RunMode,
+    /// Where to write rollout events.
+    events: Vec<EventSinkConfig>,
+    /// The webhooks to notify when the rollout changes state.
+    webhooks: Vec<WebhookConfig>,
+    /// The commands to run around each state.
+    hooks: Option<HooksConfig>,
+    /// The requests to send to the canary once it's deployed.
+    probes: Option<ProbeConfig>,
+}
+
+/// Describes who decides how the rollout progresses.
+enum RunMode {
+    /// The MultiTool backend makes the decisions.
+    Backend {
+        backend: BackendClient,
+        workspace_name: String,
+        application_name: String,
+        /// The rollout to resume, if the user asked for one.
+        resume: Option<RolloutId>,
+        /// Whether to start a new rollout, even if one is in flight.
+        fresh: bool,
+        /// The alias to move to the canary's version when it's promoted.
+        stable_alias: String,
+    },
+    /// Decisions are made locally, without contacting the backend.
+    Local { config: LocalRunConfig },
 }
 
 impl Run {
     pub fn new(terminal: Terminal, args: RunSubcommand) -> Result<Self> {
+        // Events written to stdout must not be interleaved with logs.
+        if args.events().contains(&EventSinkConfig::Stdout) {
+            terminal.reserve_stdout();
+        }
         let fs = FileSystem::new().unwrap();
-        let session = fs.load_file(SessionFile)?;
-        let origin = args.origin().as_deref();
+        let mode = if let Some(config_path) = args.local() {
+            // In local mode, we don't need a session since we
+            // never talk to the backend.
+            let config = fs.load_file(LocalRunConfigFile(config_path.to_owned()))?;
+            if config.stable_alias().is_none() {
+                bail!(
+                    "Set `stable_alias` in {} to the Lambda alias that serves production traffic, so the canary can be promoted",
+                    config_path.display()
+                );
+            }
+            RunMode::Local { config }
+        } else {
+            let session = fs.load_file(SessionFile)?;
+            let origin = args.origin().as_deref();
+            let backend = BackendClient::new(origin, Some(session))?;
+            RunMode::Backend {
+                backend,
+                workspace_name: args
+                    .workspace()
+                    .clone()
+                    .ok_or_else(|| miette!("A workspace is required"))?,
+                application_name: args
+                    .application()
+                    .clone()
+                    .ok_or_else(|| miette!("An application is required"))?,
+                resume: *args.resume(),
+                fresh: *args.new(),
+                stable_alias: args
+                    .stable_alias()
+                    .clone()
+                    .ok_or_else(|| miette!("A stable alias is required"))?,
+            }
+        };
 
-        let backend = BackendClient::new(origin, Some(session))?;
+        let webhooks = match args.notify() {
+            Some(config_path) => {
+                fs.load_file(NotifierConfigFile(config_path.to_owned()))?
+                    .webhooks
+            }
+            None => Vec::new(),
+        };
+        let hooks = args
+            .hooks()
+            .as_ref()
+            .map(|config_path| fs.load_file(HooksConfigFile(config_path.to_owned())))
+            .transpose()?;
+        let probes = args
+            .probe()
+            .as_ref()
+            .map(|config_path| fs.load_file(ProbeConfigFile(config_path.to_owned())))
+            .transpose()?;
 
         Ok(Self {
-            _terminal: terminal,
-            backend,
+            terminal,
             artifact_path: args.artifact_path().to_owned(),
-            workspace_name: args.workspace().to_owned(),
-            application_name: args.application().to_owned(),
+            mode,
+            events: args.events().clone(),
+            webhooks,
+            hooks,
+            probes,
         })
     }
 
@@ -57,39 +147,155 @@ impl Run {
             // doesn't exist or we don't have permission to read the file.
             debug!("Loading the lambda artifact...");
             let artifact = LambdaZip::load(&self.artifact_path).await?;
-            // We need to convert our workspace and application names into the full workspace and application object
-            debug!("Loading workspace and application...");
-            let workspace = self
-                .backend
-                .get_workspace_by_name(&self.workspace_name)
-                .await?;
-            let application = self
-                .backend
-                .get_application_by_name(workspace.id, &self.application_name)
-                .await?;
-            // Now, we have to load the application's configuration
-            // from the backend. We have the name of the workspace and
-            // application, but we need to look up the details.
-            debug!("Loading application conf...");
-            let conf = ApplicationConfig {
-                platform: PlatformBuilder::new(*application.platform, artifact)
-                    .build()
-                    .await,
-                ingress: IngressBuilder::new(*application.ingress).build().await,
-                monitor: MonitorBuilder::new(*application.monitor).build().await,
+            // Open the event sinks up front too, so a bad path
+            // fails before we touch the rollout.
+            let mut sinks = Vec::with_capacity(self.events.len());
+            for sink in &self.events {
+                sinks.push(sink.build().await?);
+            }
+            let webhooks = self
+                .webhooks
+                .iter()
+                .map(WebhookConfig::build)
+                .collect::<Result<Vec<_>>>()?;
+            // The monitor may need to know which canary was deployed,
+            // which isn't known until we've attached to a rollout.
+            let (canary, canary_watch) = watch::channel(None);
+            let (backend, conf, metadata, in_flight, resumed, canary_id) = match self.mode {
+                RunMode::Backend {
+                    backend,
+                    workspace_name,
+                    application_name,
+                    resume,
+                    fresh,
+                    stable_alias,
+                } => {
+                    // We need to convert our workspace and application names into the full workspace and application object
+                    debug!("Loading workspace and application...");
+                    let workspace = backend.get_workspace_by_name(&workspace_name).await?;
+                    let application = backend
+                        .get_application_by_name(workspace.id, &application_name)
+                        .await?;
+                    // Now, we have to load the application's configuration
+                    // from the backend. We have the name of the workspace and
+                    // application, but we need to look up the details.
+                    debug!("Loading application conf...");
+                    let mut conf = ApplicationConfig {
+                        platform: PlatformBuilder::new(*application.platform, artifact)
+                            .with_stable_alias(Some(stable_alias))
+                            .build()
+                            .await,
+                        ingress: IngressBuilder::new(*application.ingress).build().await,
+                        monitor: MonitorBuilder::new(*application.monitor)
+                            .with_canary(canary_watch)
+                            .build()
+                            .await,
+                    };
+
+                    // If an earlier run didn't finish its rollout, we pick
+                    // it up instead of starting a new one.
+                    let fs = FileSystem::new()?;
+                    let in_flight = InFlightRolloutFile::new(workspace.id, application.id);
+                    let mut attach = choose_rollout(resume, fresh, fs.load_file(in_flight).ok());
+                    let mut states = Vec::new();
+                    if let Attach::Resume { rollout_id, .. } = attach {
+                        // The record outlives runs that exit early, so make
+                        // sure the backend still has work for the rollout.
+                        let meta = RolloutMetadata::builder()
+                            .workspace_id(workspace.id)
+                            .application_id(application.id)
+                            .rollout_id(rollout_id)
+                            .build();
+                        states = backend.rollout_states(&meta).await?;
+                        attach = confirm_resume(attach, is_in_flight(&states))?;
+                    }
+                    let resumed = attach != Attach::New;
+                    let (metadata, canary_id) = match attach {
+                        Attach::Resume {
+                            rollout_id,
+                            canary_id,
+                            ..
+                        } => {
+                            // A rollout started on another machine
+                            // didn't record its canary here.
+                            let deployed = canary_deployed(&states);
+                            let canary_id =
+                                find_canary(&conf.ingress, rollout_id, canary_id, deployed).await?;
+                            let metadata = resume_rollout(
+                                &mut conf,
+                                workspace.id,
+                                application.id,
+                                rollout_id,
+                                canary_id.clone(),
+                            )
+                            .await?;
+                            (metadata, canary_id)
+                        }
+                        Attach::New => (
+                            create_rollout(&backend, workspace.id, application.id).await?,
+                            None,
+                        ),
+                    };
+                    // Record the rollout, so we can resume it if we exit
+                    // before it's finished.
+                    fs.save_file(
+                        &in_flight,
+                        &InFlightRollout::new(metadata.clone(), canary_id.clone()),
+                    )?;
+                    let backend: SharedBackend = Arc::new(backend);
+                    (backend, conf, metadata, Some(in_flight), resumed, canary_id)
+                }
+                RunMode::Local { config } => {
+                    debug!("Loading local application conf...");
+                    let stable_alias = config.stable_alias();
+                    let conf = ApplicationConfig {
+                        platform: PlatformBuilder::new(config.platform, artifact)
+                            .with_stable_alias(stable_alias)
+                            .build()
+                            .await,
+                        ingress: IngressBuilder::new(config.ingress).build().await,
+                        monitor: MonitorBuilder::new(config.monitor)
+                            .with_canary(canary_watch)
+                            .build()
+                            .await,
+                    };
+                    let backend: SharedBackend = Arc::new(LocalBackend::new(config.policy)?);
+                    // There's no workspace or application to speak of,
+                    // so we only need an id to tell rollouts apart.
+                    let metadata = RolloutMetadata::builder()
+                        .workspace_id(0)
+                        .application_id(0)
+                        .rollout_id(Utc::now().timestamp() as u64)
+                        .build();
+                    info!("Starting a local rollout. Decisions will be made without the backend.");
+                    (backend, conf, metadata, None, false, None)
+                }
             };
+            canary.send_replace(canary_id);
 
-            // Create a new rollout.
-            let metadata = self.create_rollout(workspace.id, application.id).await?;
+            // Subscribe the sinks before the first event is emitted.
+            let events = Events::new(*metadata.rollout_id());
+            let has_sinks = !sinks.is_empty();
+            let event_subsystem = EventSubsystem::new(&events, sinks);
+            let has_webhooks = !webhooks.is_empty();
+            let notifier = EventSubsystem::new(&events, webhooks);
+            events.emit(RolloutEvent::RolloutCreated { resumed });
 
             // Build the ControllerSubsystem using the boxed objects.
             debug!("Building controller...");
+            let dashboard = self.terminal.dashboard(*metadata.rollout_id());
             let controller = ControllerSubsystem::builder()
-                .backend(self.backend)
+                .backend(backend)
                 .monitor(conf.monitor)
                 .ingress(conf.ingress)
                 .platform(conf.platform)
                 .meta(metadata)
+                .maybe_in_flight(in_flight)
+                .dashboard(dashboard.clone())
+                .events(events)
+                .maybe_hooks(self.hooks)
+                .maybe_probes(self.probes)
+                .canary(canary)
                 .build();
 
             info!("Starting the rollout...");
@@ -101,6 +307,27 @@ impl Run {
                     CONTROLLER_SUBSYSTEM_NAME,
                     controller.into_subsystem(),
                 ));
+                // • Draw the dashboard, if the terminal is interactive.
+                if dashboard.interactive() {
+                    s.start(SubsystemBuilder::new(
+                        DASHBOARD_SUBSYSTEM_NAME,
+                        dashboard.into_subsystem(),
+                    ));
+                }
+                // • Write rollout events, if anyone asked for them.
+                if has_sinks {
+                    s.start(SubsystemBuilder::new(
+                        EVENTS_SUBSYSTEM_NAME,
+                        event_subsystem.into_subsystem(),
+                    ));
+                }
+                // • Notify webhooks, if any were configured.
+                if has_webhooks {
+                    s.start(SubsystemBuilder::new(
+                        NOTIFIER_SUBSYSTEM_NAME,
+                        notifier.into_subsystem(),
+                    ));
+                }
             })
             .catch_signals()
             .handle_shutdown_requests(Duration::from_millis(DEFAULT_SHUTDOWN_TIMEOUT))
@@ -108,29 +335,222 @@ impl Run {
             .map_err(Into::into)
         })
     }
+}
 
-    async fn create_rollout(
-        &self,
-        workspace_id: WorkspaceId,
-        application_id: ApplicationId,
-    ) -> Result<RolloutMetadata> {
-        debug!("Creating new rollout...");
-        let rollout_id = self
-            .backend
-            .new_rollout(workspace_id, application_id)
-            .await?;
-
-        info!(
-            "New rollout created! Follow along in the dashboard here: https://app.multitool.run/workspaces/{}/applications/{}/activity/{}/events",
-            workspace_id, application_id, rollout_id
-        );
+/// Which rollout `multi run` attaches to.
+#[derive(Debug, PartialEq)]
+enum Attach {
+    /// Create a new rollout.
+    New,
+    /// Resume an existing rollout, reattaching to its canary
+    /// if we know which one was deployed.
+    Resume {
+        rollout_id: RolloutId,
+        canary_id: Option<String>,
+        /// Whether the user asked for this rollout, rather
+        /// than us finding it in flight.
+        requested: bool,
+    },
+}
+
+/// Decide which rollout to attach to, given the rollout the user asked
+/// to resume, whether they asked for a new one instead, and the rollout
+/// an earlier run left in flight.
+fn choose_rollout(
+    requested: Option<RolloutId>,
+    fresh: bool,
+    recorded: Option<InFlightRollout>,
+) -> Attach {
+    match (requested, recorded) {
+        (_, recorded) if fresh => {
+            if let Some(recorded) = recorded {
+                info!(
+                    "Ignoring rollout {}, which an earlier run left in flight",
+                    recorded.rollout_id()
+                );
+            }
+            Attach::New
+        }
+        (Some(rollout_id), Some(recorded)) if recorded.rollout_id() == rollout_id => {
+            Attach::Resume {
+                rollout_id,
+                canary_id: recorded.canary_id().clone(),
+                requested: true,
+            }
+        }
+        (Some(rollout_id), recorded) => {
+            if let Some(recorded) = recorded {
+                warn!(
+                    "Resuming rollout {rollout_id}, but rollout {} was the last one in flight",
+                    recorded.rollout_id()
+                );
+            }
+            info!(
+                "No canary was recorded for rollout {rollout_id} on this machine, so we'll look for it in the ingress."
+            );
+            Attach::Resume {
+                rollout_id,
+                canary_id: None,
+                requested: true,
+            }
+        }
+        (None, Some(recorded)) => {
+            info!(
+                "Found rollout {} still in flight. Resuming it...",
+                recorded.rollout_id()
+            );
+            Attach::Resume {
+                rollout_id: recorded.rollout_id(),
+                canary_id: recorded.canary_id().clone(),
+                requested: false,
+            }
+        }
+        (None, None) => Attach::New,
+    }
+}
+
+/// Only resume rollouts the backend says are unfinished. A finished
+/// rollout found in flight was left behind by a run that exited early,
+/// so we start a new one instead. If the user asked for it by id,
+/// that's a mistake worth reporting.
+fn confirm_resume(attach: Attach, in_flight: bool) -> Result<Attach> {
+    match attach {
+        Attach::Resume {
+            rollout_id,
+            requested,
+            ..
+        } if !in_flight => {
+            if requested {
+                bail!("Rollout {rollout_id} is already finished, so it can't be resumed");
+            }
+            info!("Rollout {rollout_id} was already finished. Starting a new one...");
+            Ok(Attach::New)
+        }
+        attach => Ok(attach),
+    }
+}
+
+async fn create_rollout(
+    backend: &BackendClient,
+    workspace_id: WorkspaceId,
+    application_id: ApplicationId,
+) -> Result<RolloutMetadata> {
+    debug!("Creating new rollout...");
+    let rollout_id = backend.new_rollout(workspace_id, application_id).await?;
+
+    info!(
+        rollout_id,
+        "New rollout created! Follow along in the dashboard here: https://app.multitool.run/workspaces/{}/applications/{}/activity/{}/events",
+        workspace_id,
+        application_id,
+        rollout_id
+    );
+
+    debug!("Creating new rollout metadata...");
+    let meta = RolloutMetadata::builder()
+        .workspace_id(workspace_id)
+        .application_id(application_id)
+        .rollout_id(rollout_id)
+        .build();
+    Ok(meta)
+}
 
-        debug!("Creating new rollout metadata...");
+/// Pick up a rollout started by an earlier run. The canary may already
+/// be deployed and receiving traffic, so we ask the platform and ingress
+/// to recover its state from the cloud instead of deploying it again.
+async fn resume_rollout(
+    conf: &mut ApplicationConfig,
+    workspace_id: WorkspaceId,
+    application_id: ApplicationId,
+    rollout_id: RolloutId,
+    canary_id: Option<String>,
+) -> Result<RolloutMetadata> {
+    debug!("Reattaching to rollout {rollout_id}...");
+    if let Some(canary) = canary_id {
+        conf.platform.reattach(canary.clone()).await?;
+        conf.ingress.reattach(canary).await?;
+    }
+
+    info!(
+        rollout_id,
+        "Resuming rollout! Follow along in the dashboard here: https://app.multitool.run/workspaces/{}/applications/{}/activity/{}/events",
+        workspace_id,
+        application_id,
+        rollout_id
+    );
+
+    let meta = RolloutMetadata::builder()
+        .workspace_id(workspace_id)
+        .application_id(application_id)
+        .rollout_id(rollout_id)
+        .build();
+    Ok(meta)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Attach, choose_rollout, confirm_resume};
+    use crate::{adapters::RolloutMetadata, fs::InFlightRollout};
+
+    fn recorded(rollout_id: u64, canary_id: Option<&str>) -> Option<InFlightRollout> {
         let meta = RolloutMetadata::builder()
-            .workspace_id(workspace_id)
-            .application_id(application_id)
+            .workspace_id(1)
+            .application_id(2)
             .rollout_id(rollout_id)
             .build();
-        Ok(meta)
+        Some(InFlightRollout::new(meta, canary_id.map(ToOwned::to_owned)))
+    }
+
+    fn resume(rollout_id: u64, canary_id: Option<&str>, requested: bool) -> Attach {
+        Attach::Resume {
+            rollout_id,
+            canary_id: canary_id.map(ToOwned::to_owned),
+            requested,
+        }
+    }
+
+    #[test]
+    fn choose_which_rollout_to_attach_to() {
+        let canary = Some("arn:fn:7");
+        // Nothing in flight, and nothing asked for.
+        assert_eq!(choose_rollout(None, false, None), Attach::New);
+        // A rollout left in flight is picked up with its canary.
+        assert_eq!(
+            choose_rollout(None, false, recorded(3, canary)),
+            resume(3, canary, false)
+        );
+        // Unless the user asked for a new one.
+        assert_eq!(choose_rollout(None, true, recorded(3, canary)), Attach::New);
+        // The rollout the user asked for wins, but we only know
+        // its canary if it's the one in flight.
+        assert_eq!(
+            choose_rollout(Some(3), false, recorded(3, canary)),
+            resume(3, canary, true)
+        );
+        assert_eq!(
+            choose_rollout(Some(4), false, recorded(3, canary)),
+            resume(4, None, true)
+        );
+        assert_eq!(choose_rollout(Some(4), false, None), resume(4, None, true));
+    }
+
+    #[test]
+    fn only_resume_unfinished_rollouts() {
+        let canary = Some("arn:fn:7");
+        // Rollouts the backend still has work for are resumed.
+        assert_eq!(
+            confirm_resume(resume(3, canary, false), true).unwrap(),
+            resume(3, canary, false)
+        );
+        // A finished rollout left in flight is discarded.
+        assert_eq!(
+            confirm_resume(resume(3, canary, false), false).unwrap(),
+            Attach::New
+        );
+        // But asking for a finished rollout is an error.
+        assert!(confirm_resume(resume(3, canary, true), false).is_err());
+        assert_eq!(confirm_resume(Attach::New, false).unwrap(), Attach::New);
     }
 }
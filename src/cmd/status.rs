@@ -0,0 +1,85 @@
+use miette::{IntoDiagnostic as _, Result};
+use serde_json::json;
+use tokio::runtime::Runtime;
+use tracing::debug;
+
+use crate::Terminal;
+use crate::adapters::{BackendClient, RolloutMetadata};
+use crate::config::{OutputFormat, StatusSubcommand};
+use crate::fs::{FileSystem, SessionFile};
+
+/// Show the application's rollouts, or the progress of one of them.
+pub struct Status {
+    terminal: Terminal,
+    backend: BackendClient,
+    workspace_name: String,
+    application_name: String,
+    rollout_id: Option<u64>,
+    output: OutputFormat,
+}
+
+impl Status {
+    pub fn new(terminal: Terminal, args: StatusSubcommand) -> Result<Self> {
+        let fs = FileSystem::new()?;
+        let session = fs.load_file(SessionFile)?;
+        let backend = BackendClient::new(args.origin().as_deref(), Some(session))?;
+        Ok(Self {
+            terminal,
+            backend,
+            workspace_name: args.workspace().clone(),
+            application_name: args.application().clone(),
+            rollout_id: *args.rollout_id(),
+            output: *args.output(),
+        })
+    }
+
+    pub fn dispatch(self) -> Result<()> {
+        let rt = Runtime::new().unwrap();
+        let _guard = rt.enter();
+        rt.block_on(async {
+            debug!("Loading workspace and application...");
+            let workspace = self
+                .backend
+                .get_workspace_by_name(&self.workspace_name)
+                .await?;
+            let application = self
+                .backend
+                .get_application_by_name(workspace.id, &self.application_name)
+                .await?;
+
+            let output = match self.rollout_id {
+                Some(rollout_id) => {
+                    let meta = RolloutMetadata::builder()
+                        .workspace_id(workspace.id)
+                        .application_id(application.id)
+                        .rollout_id(rollout_id)
+                        .build();
+                    let status = self.backend.rollout_status(&meta).await?;
+                    match self.output {
+                        OutputFormat::Human => status.to_string(),
+                        OutputFormat::Json => {
+                            serde_json::to_string_pretty(&status).into_diagnostic()? + "\n"
+                        }
+                    }
+                }
+                None => {
+                    let rollouts = self
+                        .backend
+                        .list_rollouts(workspace.id, application.id)
+                        .await?;
+                    match self.output {
+                        OutputFormat::Human => rollouts
+                            .iter()
+                            .map(|id| format!("Rollout {id}\n"))
+                            .collect(),
+                        OutputFormat::Json => {
+                            let body = json!({ "rollouts": rollouts });
+                            serde_json::to_string_pretty(&body).into_diagnostic()? + "\n"
+                        }
+                    }
+                }
+            };
+            self.terminal.print_output(&output)
+        })
+    }
+}
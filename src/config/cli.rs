@@ -4,6 +4,7 @@ use tracing::level_filters::LevelFilter;
 
 use super::colors::EnableColors;
 use super::command::MultiCommand;
+use super::logs::LogFormat;
 
 /// multi is a cloud rollout multitool.
 #[derive(Getters, Parser)]
@@ -21,4 +22,9 @@ pub struct Cli {
     /// Options are case-insensitive.
     #[arg(long, env, global = true, default_value_t = LevelFilter::INFO)]
     log_level: LevelFilter,
+
+    /// How log lines are formatted. Use 'json' when the logs are
+    /// shipped somewhere that indexes them, like a CI pipeline.
+    #[arg(long, env, global = true, value_enum, default_value_t = LogFormat::default())]
+    log_format: LogFormat,
 }
@@ -3,10 +3,10 @@ use miette::Result;
 
 #[cfg(feature = "proxy")]
 use crate::cmd::Proxy;
-use crate::cmd::{Login, Logout, Run, Version};
+use crate::cmd::{Login, Logout, Outcome, Override, Run, Status, Version};
 use crate::terminal::Terminal;
 
-use super::{LoginSubcommand, RunSubcommand};
+use super::{LoginSubcommand, ManualSubcommand, RunSubcommand, StatusSubcommand};
 
 #[cfg(feature = "proxy")]
 use super::ProxySubcommand;
@@ -18,11 +18,19 @@ pub enum MultiCommand {
     /// Log in to the hosted SaaS.
     Login(LoginSubcommand),
     Logout,
+    /// Promote the canary of a rollout right away, overriding
+    /// the backend's decision.
+    Promote(ManualSubcommand),
     #[cfg(feature = "proxy")]
     Proxy(ProxySubcommand),
+    /// Roll back the canary of a rollout right away, overriding
+    /// the backend's decision.
+    Rollback(ManualSubcommand),
     /// Run will execute `multi` in "runner mode", where it will
     /// immediately deploy the provided artifact and start canarying.
     Run(RunSubcommand),
+    /// Show the application's rollouts, or the progress of one of them.
+    Status(StatusSubcommand),
     /// Print the CLI version and exit
     Version,
 }
@@ -33,9 +41,12 @@ impl MultiCommand {
         match self {
             Self::Login(flags) => Login::new(console, flags)?.dispatch(),
             Self::Logout => Logout::new(console).dispatch(),
+            Self::Promote(flags) => Override::new(console, flags, Outcome::Promote)?.dispatch(),
             #[cfg(feature = "proxy")]
             Self::Proxy(flags) => Proxy::new(console, flags).dispatch(),
+            Self::Rollback(flags) => Override::new(console, flags, Outcome::Rollback)?.dispatch(),
             Self::Run(flags) => Run::new(console, flags)?.dispatch(),
+            Self::Status(flags) => Status::new(console, flags)?.dispatch(),
             Self::Version => Version::new(console).dispatch(),
         }
     }
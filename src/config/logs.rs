@@ -0,0 +1,22 @@
+use clap::ValueEnum;
+
+/// This enum tracks how log lines are formatted. People usually
+/// want the compact format, while CI pipelines that ship and index
+/// logs want JSON.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, ValueEnum, Default)]
+pub enum LogFormat {
+    /// One line per event, with the local time.
+    #[default]
+    Compact,
+    /// Several lines per event, easier on the eyes.
+    Pretty,
+    /// One JSON object per event, with its fields at the top level.
+    Json,
+}
+
+impl LogFormat {
+    /// Whether the logs are meant to be read by machines.
+    pub fn is_structured(self) -> bool {
+        self == LogFormat::Json
+    }
+}
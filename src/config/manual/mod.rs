@@ -0,0 +1,22 @@
+use clap::Args;
+use derive_getters::Getters;
+
+/// The arguments for commands that settle a rollout by hand,
+/// like `multi rollback` and `multi promote`.
+#[derive(Args, Getters, Clone)]
+pub struct ManualSubcommand {
+    #[arg(short, long, env = "MULTI_WORKSPACE")]
+    workspace: String,
+    #[arg(short, long, env = "MULTI_APPLICATION")]
+    application: String,
+    /// The rollout to settle.
+    #[arg(value_name = "ROLLOUT_ID")]
+    rollout_id: u64,
+    /// The Lambda alias that serves production traffic. Promoting
+    /// the canary moves the alias to its version.
+    #[arg(long, value_name = "ALIAS", env = "MULTI_STABLE_ALIAS")]
+    stable_alias: Option<String>,
+
+    #[arg(long, short = 'o', default_value = Some("https://staging.api.multitool.run"))]
+    origin: Option<String>,
+}
@@ -1,11 +1,19 @@
 pub use cli::Cli;
 pub use login::LoginSubcommand;
+pub use logs::LogFormat;
+pub use manual::ManualSubcommand;
+pub use output::OutputFormat;
 pub use proxy::ProxySubcommand;
 pub use run::RunSubcommand;
+pub use status::StatusSubcommand;
 
 mod cli;
 mod colors;
 mod command;
 mod login;
+mod logs;
+mod manual;
+mod output;
 mod proxy;
 mod run;
+mod status;
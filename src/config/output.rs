@@ -0,0 +1,12 @@
+use clap::ValueEnum;
+
+/// How a command prints its results. People read the human format,
+/// while scripts parse the JSON.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, ValueEnum, Default)]
+pub enum OutputFormat {
+    /// Print a summary meant to be read by a person.
+    #[default]
+    Human,
+    /// Print JSON, for scripts.
+    Json,
+}
@@ -1,10 +1,25 @@
+use std::path::PathBuf;
+
 use clap::Args;
 use derive_getters::Getters;
 
 #[derive(Args, Clone, Getters)]
 pub struct ProxySubcommand {
+    /// The address of the baseline upstream, e.g. `127.0.0.1:8080`.
     #[clap(long, short = 'b')]
     baseline: String,
+    /// The address of the canary upstream. The canary can also be
+    /// released later through the control socket.
     #[clap(long, short = 'c')]
-    canary: String,
+    canary: Option<String>,
+    /// The percentage of requests sent to the canary, from 0 to 100.
+    #[clap(long, default_value_t = 0, requires = "canary")]
+    canary_percent: u32,
+    /// The address the proxy listens on.
+    #[clap(long, short = 'l', default_value = "0.0.0.0:8000")]
+    listen: String,
+    /// Accept commands over a Unix socket at this path, so the
+    /// canary's traffic can be changed while the proxy is running.
+    #[clap(long)]
+    control_socket: Option<PathBuf>,
 }
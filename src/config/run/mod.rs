@@ -3,16 +3,83 @@ use std::path::PathBuf;
 use clap::Args;
 use derive_getters::Getters;
 
+use crate::adapters::EventSinkConfig;
+
 #[derive(Args, Getters, Clone)]
 pub struct RunSubcommand {
-    #[arg(short, long, env = "MULTI_WORKSPACE")]
-    workspace: String,
-    #[arg(short, long, env = "MULTI_APPLICATION")]
-    application: String,
+    #[arg(
+        short,
+        long,
+        env = "MULTI_WORKSPACE",
+        required_unless_present = "local"
+    )]
+    workspace: Option<String>,
+    #[arg(
+        short,
+        long,
+        env = "MULTI_APPLICATION",
+        required_unless_present = "local"
+    )]
+    application: Option<String>,
     /// The path to the zipped serverless function.
     #[arg(value_name = "FILE")]
     artifact_path: PathBuf,
 
+    /// The Lambda alias that serves production traffic. When the canary
+    /// is promoted, the alias is moved to its version. Local rollouts
+    /// read it from their config instead.
+    #[arg(
+        long,
+        value_name = "ALIAS",
+        env = "MULTI_STABLE_ALIAS",
+        required_unless_present = "local",
+        conflicts_with = "local"
+    )]
+    stable_alias: Option<String>,
+
     #[arg(long, short = 'o', default_value = Some("https://staging.api.multitool.run"))]
     origin: Option<String>,
+
+    /// Run the rollout without the MultiTool backend. Decisions are
+    /// made locally, using the adapters and policy in the given
+    /// TOML file.
+    #[arg(long, value_name = "CONFIG")]
+    local: Option<PathBuf>,
+
+    /// Resume the rollout with the given id instead of starting a new
+    /// one. Without this flag, a rollout left in flight by an earlier
+    /// run of the application is resumed automatically, as long as the
+    /// backend says it's unfinished.
+    #[arg(long, value_name = "ROLLOUT_ID", conflicts_with = "local")]
+    resume: Option<u64>,
+
+    /// Always start a new rollout, even if an earlier run of the
+    /// application left one in flight.
+    #[arg(long, alias = "no-resume", conflicts_with_all = ["resume", "local"])]
+    new: bool,
+
+    /// Write an event for each transition in the rollout, as
+    /// newline-delimited JSON. Pass `-` for stdout, `unix:<PATH>` for
+    /// a Unix socket, or a file path. May be given more than once.
+    /// When events are sent to stdout, logs are written to stderr.
+    #[arg(long, value_name = "SINK")]
+    events: Vec<EventSinkConfig>,
+
+    /// Notify the webhooks listed in the given TOML file when the
+    /// rollout changes state or fails.
+    #[arg(long, value_name = "CONFIG")]
+    notify: Option<PathBuf>,
+
+    /// Run the shell commands listed in the given TOML file before
+    /// and after each state of the rollout is effected. A failing hook
+    /// before a state stops the run, and a failing hook after the
+    /// canary is deployed or its traffic is changed rolls it back.
+    #[arg(long, value_name = "CONFIG")]
+    hooks: Option<PathBuf>,
+
+    /// Once the canary is deployed, send it the synthetic requests
+    /// listed in the given TOML file before it receives any traffic.
+    /// If a response isn't what's expected, the canary is rolled back.
+    #[arg(long, value_name = "CONFIG")]
+    probe: Option<PathBuf>,
 }
@@ -0,0 +1,23 @@
+use clap::Args;
+use derive_getters::Getters;
+
+use super::OutputFormat;
+
+#[derive(Args, Getters, Clone)]
+pub struct StatusSubcommand {
+    #[arg(short, long, env = "MULTI_WORKSPACE")]
+    workspace: String,
+    #[arg(short, long, env = "MULTI_APPLICATION")]
+    application: String,
+    /// The rollout to show. If omitted, the application's rollouts
+    /// are listed instead.
+    #[arg(value_name = "ROLLOUT_ID")]
+    rollout_id: Option<u64>,
+
+    /// How to print the status.
+    #[arg(long, value_enum, default_value_t = OutputFormat::default())]
+    output: OutputFormat,
+
+    #[arg(long, short = 'o', default_value = Some("https://staging.api.multitool.run"))]
+    origin: Option<String>,
+}
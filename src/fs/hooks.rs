@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use miette::Result;
+
+use super::{File, FileSystem};
+use crate::adapters::HooksConfig;
+
+/// The user-provided TOML file containing a [HooksConfig], e.g.
+///
+/// ```toml
+/// [deploy_canary]
+/// after = ["./scripts/smoke-test.sh"]
+///
+/// [set_canary_traffic]
+/// before = ["./scripts/warm-cache.sh"]
+/// ```
+///
+/// Unlike most files, its path is chosen by the user.
+pub(crate) struct HooksConfigFile(pub(crate) PathBuf);
+
+impl File for HooksConfigFile {
+    type Data = HooksConfig;
+    const EXTENSION: &'static str = "toml";
+
+    fn path(&self, _fs: &FileSystem) -> Result<PathBuf> {
+        Ok(self.0.clone())
+    }
+}
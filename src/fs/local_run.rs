@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+
+use miette::Result;
+use multitool_sdk::models::PlatformConfig;
+use serde::{Deserialize, Serialize};
+
+use super::{File, FileSystem};
+use crate::adapters::{LocalIngressConfig, LocalMonitorConfig, PolicyConfig};
+
+/// A [LocalRunConfig] holds everything `multi run --local` needs
+/// that would otherwise be fetched from the backend: the application's
+/// adapters, and the policy used to decide how the rollout progresses.
+/// The adapter sections use the same schema as the backend's
+/// application configuration, plus monitors that are only
+/// available locally.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct LocalRunConfig {
+    pub(crate) platform: PlatformConfig,
+    pub(crate) ingress: LocalIngressConfig,
+    pub(crate) monitor: LocalMonitorConfig,
+    #[serde(default)]
+    pub(crate) policy: PolicyConfig,
+    /// The Lambda alias the platform moves to the canary's version when
+    /// it's promoted. Defaults to the Lambda alias ingress's alias, if
+    /// that's the ingress in use. Otherwise, it's required.
+    #[serde(default)]
+    stable_alias: Option<String>,
+}
+
+impl LocalRunConfig {
+    /// The alias the Lambda platform should promote, if any.
+    pub(crate) fn stable_alias(&self) -> Option<String> {
+        self.stable_alias
+            .clone()
+            .or_else(|| self.ingress.lambda_alias().map(ToOwned::to_owned))
+    }
+}
+
+/// The user-provided TOML file containing a [LocalRunConfig].
+/// Unlike most files, its path is chosen by the user.
+pub(crate) struct LocalRunConfigFile(pub(crate) PathBuf);
+
+impl File for LocalRunConfigFile {
+    type Data = LocalRunConfig;
+    const EXTENSION: &'static str = "toml";
+
+    fn path(&self, _fs: &FileSystem) -> Result<PathBuf> {
+        Ok(self.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::LocalRunConfig;
+
+    const CONFIG: &str = r#"
+[platform.aws_lambda]
+name = "my-function"
+region = "us-east-2"
+
+[ingress.lambda_alias]
+function_name = "my-function"
+alias_name = "prod"
+region = "us-east-2"
+
+[monitor.lambda]
+name = "my-function"
+"#;
+
+    /// The platform promotes the ingress' alias, unless told otherwise.
+    #[test]
+    fn stable_alias_follows_the_ingress() {
+        let config: LocalRunConfig = toml::from_str(CONFIG).unwrap();
+        assert_eq!(config.stable_alias().as_deref(), Some("prod"));
+        let config: LocalRunConfig =
+            toml::from_str(&format!("stable_alias = \"live\"\n{CONFIG}")).unwrap();
+        assert_eq!(config.stable_alias().as_deref(), Some("live"));
+    }
+}
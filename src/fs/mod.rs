@@ -10,13 +10,31 @@ use std::{
 };
 
 pub(crate) use file::File;
+pub(crate) use hooks::HooksConfigFile;
+pub(crate) use local_run::{LocalRunConfig, LocalRunConfigFile};
+pub(crate) use notifier::NotifierConfigFile;
+pub(crate) use observations::{ObservationQueueFile, RejectedObservationsFile};
+pub(crate) use probe::ProbeConfigFile;
+pub(crate) use rollout::{InFlightRollout, InFlightRolloutFile};
 pub(crate) use session::{Session, SessionFile, UserCreds};
 
 use manifest::{JsonManifest, Manifest, TomlManifest};
 
 mod file;
+/// The commands to run around each state of a rollout.
+mod hooks;
+/// The configuration file for running a rollout without the backend.
+mod local_run;
 /// The schema and parsing code for the Wack.toml manifest file.
 pub mod manifest;
+/// The webhooks to notify as a rollout progresses.
+mod notifier;
+/// Observations waiting to be uploaded to the backend.
+mod observations;
+/// The synthetic probes to send to a freshly deployed canary.
+mod probe;
+/// A record of the rollout in progress, so it can be resumed.
+mod rollout;
 mod session;
 
 /// The name of the application as used on the filesystem for XDG conventions.
@@ -38,12 +56,33 @@ impl FileSystem {
         Ok(Self { xdg_dirs })
     }
 
+    /// A filesystem whose files live in a fresh temporary directory,
+    /// so tests don't touch the user's own.
+    #[cfg(test)]
+    pub(crate) fn scratch() -> Result<Self> {
+        // Joined onto an absolute path, the XDG base directories are ignored.
+        let root = std::env::temp_dir().join(format!("multi-test-{}", uuid::Uuid::new_v4()));
+        let xdg_dirs =
+            ProjectDirs::from_path(root).ok_or_else(|| miette!("$HOME directory unavailable"))?;
+        Ok(Self { xdg_dirs })
+    }
+
     /// Returns `Ok(true)` if the file existed and was deleted.
     /// Returns `Ok(false)`` if the file did not exist.
     /// Returns `Err(_)`` if the file could not be deleted or there was another io error.
     pub(crate) fn delete_file<T: StaticFile>(&self) -> Result<bool> {
         // • Grab the path to the file.
         let path = T::static_path(self)?;
+        Self::remove_path(path)
+    }
+
+    /// Like [FileSystem::delete_file], but for files whose name
+    /// isn't statically known.
+    pub(crate) fn delete<F: File>(&self, file: &F) -> Result<bool> {
+        Self::remove_path(file.path(self)?)
+    }
+
+    fn remove_path(path: PathBuf) -> Result<bool> {
         // Remove the file but check the error.
         match std::fs::remove_file(path) {
             Ok(_) => Ok(true),
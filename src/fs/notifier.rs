@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use miette::Result;
+use serde::{Deserialize, Serialize};
+
+use super::{File, FileSystem};
+use crate::adapters::WebhookConfig;
+
+/// A [NotifierConfig] lists the webhooks to notify as the rollout
+/// progresses, e.g.
+///
+/// ```toml
+/// [[webhooks]]
+/// url = "https://hooks.slack.com/services/..."
+/// secret_env = "SLACK_WEBHOOK_SECRET"
+/// payload = { text = "{{summary}}" }
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct NotifierConfig {
+    #[serde(default)]
+    pub(crate) webhooks: Vec<WebhookConfig>,
+}
+
+/// The user-provided TOML file containing a [NotifierConfig].
+/// Unlike most files, its path is chosen by the user.
+pub(crate) struct NotifierConfigFile(pub(crate) PathBuf);
+
+impl File for NotifierConfigFile {
+    type Data = NotifierConfig;
+    const EXTENSION: &'static str = "toml";
+
+    fn path(&self, _fs: &FileSystem) -> Result<PathBuf> {
+        Ok(self.0.clone())
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use miette::Result;
+
+use super::{DirectoryType, File, FileSystem};
+use crate::adapters::backend::{PendingBatch, RolloutId};
+
+/// This file holds the observations of a rollout that haven't been
+/// uploaded to the backend yet, stored as JSON. It outlives the CLI,
+/// so observations buffered while the backend was unreachable can be
+/// uploaded once the rollout resumes.
+pub(crate) struct ObservationQueueFile(pub(crate) RolloutId);
+
+impl File for ObservationQueueFile {
+    type Data = Vec<PendingBatch>;
+    const EXTENSION: &'static str = "json";
+
+    fn path(&self, fs: &FileSystem) -> Result<PathBuf> {
+        let filename = format!("observations-{}.{}", self.0, Self::EXTENSION);
+        fs.init_dir(DirectoryType::Data)
+            .map(|path| path.join(filename))
+    }
+}
+
+/// This file holds the observations of a rollout that couldn't be
+/// uploaded, because the backend rejected them or too many were
+/// waiting, stored as newline-delimited JSON. We only ever append to
+/// it. It's left for the operator to inspect or replay by hand.
+pub(crate) struct RejectedObservationsFile(pub(crate) RolloutId);
+
+impl File for RejectedObservationsFile {
+    type Data = PendingBatch;
+    const EXTENSION: &'static str = "ndjson";
+
+    fn path(&self, fs: &FileSystem) -> Result<PathBuf> {
+        let filename = format!("observations-{}-rejected.{}", self.0, Self::EXTENSION);
+        fs.init_dir(DirectoryType::Data)
+            .map(|path| path.join(filename))
+    }
+}
@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use miette::Result;
+
+use super::{File, FileSystem};
+use crate::adapters::ProbeConfig;
+
+/// The user-provided TOML file containing a [ProbeConfig], e.g.
+///
+/// ```toml
+/// [[requests]]
+/// url = "https://api.example.com/canary/health"
+/// headers = { x-lambda-version = "{{canary_version}}" }
+/// expect_status = 200
+/// expect_body = "ok"
+/// ```
+///
+/// Unlike most files, its path is chosen by the user.
+pub(crate) struct ProbeConfigFile(pub(crate) PathBuf);
+
+impl File for ProbeConfigFile {
+    type Data = ProbeConfig;
+    const EXTENSION: &'static str = "toml";
+
+    fn path(&self, _fs: &FileSystem) -> Result<PathBuf> {
+        Ok(self.0.clone())
+    }
+}
@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+
+use derive_getters::Getters;
+use miette::Result;
+use serde::{Deserialize, Serialize};
+
+use super::{DirectoryType, File, FileSystem};
+use crate::adapters::{
+    RolloutMetadata,
+    backend::{ApplicationId, RolloutId, WorkspaceId},
+};
+
+/// This file records the rollout that's in progress for an application,
+/// stored as JSON. It's written when the rollout starts and deleted
+/// when the canary is promoted or rolled back. If it's still around
+/// when `multi run` starts, the previous run never finished, so we
+/// resume its rollout instead of starting another.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct InFlightRolloutFile {
+    workspace_id: WorkspaceId,
+    application_id: ApplicationId,
+}
+
+/// What we remember about a rollout in flight.
+#[derive(Getters, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct InFlightRollout {
+    #[serde(flatten)]
+    meta: RolloutMetadata,
+    /// The platform's id for the canary, as returned when it was
+    /// deployed. `None` until then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    canary_id: Option<String>,
+}
+
+impl InFlightRollout {
+    pub(crate) fn new(meta: RolloutMetadata, canary_id: Option<String>) -> Self {
+        Self { meta, canary_id }
+    }
+
+    pub(crate) fn rollout_id(&self) -> RolloutId {
+        *self.meta.rollout_id()
+    }
+}
+
+impl InFlightRolloutFile {
+    pub(crate) fn new(workspace_id: WorkspaceId, application_id: ApplicationId) -> Self {
+        Self {
+            workspace_id,
+            application_id,
+        }
+    }
+
+    /// Delete the record, but only if it's for the given rollout.
+    /// Returns whether it was deleted.
+    pub(crate) fn forget(&self, fs: &FileSystem, rollout_id: RolloutId) -> Result<bool> {
+        match fs.load_file(*self) {
+            Ok(recorded) if recorded.rollout_id() == rollout_id => fs.delete(self),
+            _ => Ok(false),
+        }
+    }
+}
+
+impl File for InFlightRolloutFile {
+    type Data = InFlightRollout;
+    const EXTENSION: &'static str = "json";
+
+    fn path(&self, fs: &FileSystem) -> Result<PathBuf> {
+        let filename = format!(
+            "rollout-{}-{}.{}",
+            self.workspace_id,
+            self.application_id,
+            Self::EXTENSION
+        );
+        fs.init_dir(DirectoryType::Data)
+            .map(|path| path.join(filename))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{InFlightRollout, InFlightRolloutFile};
+    use crate::{adapters::RolloutMetadata, fs::FileSystem};
+
+    fn rollout(rollout_id: u64, canary_id: Option<&str>) -> InFlightRollout {
+        let meta = RolloutMetadata::builder()
+            .workspace_id(1)
+            .application_id(2)
+            .rollout_id(rollout_id)
+            .build();
+        InFlightRollout::new(meta, canary_id.map(ToOwned::to_owned))
+    }
+
+    /// Records written before the canary was tracked still load.
+    #[test]
+    fn load_records_without_a_canary() {
+        let recorded: InFlightRollout =
+            serde_json::from_str(r#"{"workspace_id":1,"application_id":2,"rollout_id":3}"#)
+                .unwrap();
+        assert_eq!(recorded, rollout(3, None));
+        let json = serde_json::to_string(&rollout(3, Some("arn:fn:7"))).unwrap();
+        assert_eq!(
+            serde_json::from_str::<InFlightRollout>(&json).unwrap(),
+            rollout(3, Some("arn:fn:7"))
+        );
+    }
+
+    /// Only the rollout the file records is forgotten.
+    #[test]
+    fn forget_only_the_recorded_rollout() {
+        let fs = FileSystem::scratch().unwrap();
+        let file = InFlightRolloutFile::new(1, 2);
+        assert!(!file.forget(&fs, 3).unwrap());
+        fs.save_file(&file, &rollout(3, Some("arn:fn:7"))).unwrap();
+        assert!(!file.forget(&fs, 4).unwrap());
+        assert_eq!(fs.load_file(file).unwrap(), rollout(3, Some("arn:fn:7")));
+        assert!(file.forget(&fs, 3).unwrap());
+        assert!(fs.load_file(file).is_err());
+    }
+}
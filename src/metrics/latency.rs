@@ -0,0 +1,96 @@
+use std::fmt;
+
+use crate::stats::Categorical;
+
+/// The number of buckets latencies are binned into.
+pub const LATENCY_BUCKETS: usize = 10;
+
+/// [LatencyBucket] bins response latencies into log-spaced buckets,
+/// so a canary that is consistently slower than the baseline shifts
+/// its requests into higher buckets. This type is used as the
+/// dependent variable in statistical observations.
+#[derive(Hash, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct LatencyBucket(usize);
+
+impl LatencyBucket {
+    /// The boundaries between buckets, in milliseconds. Each bucket
+    /// includes its lower bound and excludes its upper bound. The
+    /// first bucket has no lower bound and the last has no upper bound.
+    pub const BOUNDS_MS: [u32; LATENCY_BUCKETS - 1] = [10, 25, 50, 100, 250, 500, 1000, 2500, 5000];
+
+    /// Bin the given latency, in milliseconds.
+    pub fn from_millis(millis: f64) -> Self {
+        let index = Self::BOUNDS_MS
+            .iter()
+            .take_while(|bound| millis >= f64::from(**bound))
+            .count();
+        Self(index)
+    }
+
+    /// Every bucket, from fastest to slowest.
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..LATENCY_BUCKETS).map(Self)
+    }
+
+    /// The smallest latency in this bucket, if it has a lower bound.
+    pub fn lower_ms(&self) -> Option<u32> {
+        self.0.checked_sub(1).map(|index| Self::BOUNDS_MS[index])
+    }
+
+    /// The latency this bucket stops at, if it has an upper bound.
+    pub fn upper_ms(&self) -> Option<u32> {
+        Self::BOUNDS_MS.get(self.0).copied()
+    }
+}
+
+impl Categorical<LATENCY_BUCKETS> for LatencyBucket {
+    fn category(&self) -> usize {
+        self.0
+    }
+}
+
+impl fmt::Display for LatencyBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.lower_ms(), self.upper_ms()) {
+            (None, Some(upper)) => write!(f, "<{upper}ms"),
+            (Some(lower), Some(upper)) => write!(f, "{lower}-{upper}ms"),
+            (Some(lower), None) => write!(f, ">={lower}ms"),
+            (None, None) => unreachable!("There is more than one bucket"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LATENCY_BUCKETS, LatencyBucket};
+    use crate::stats::Categorical;
+    use pretty_assertions::{assert_eq, assert_str_eq};
+
+    #[test]
+    fn bin_latencies() {
+        let test_cases = [
+            (0.0, 0, "<10ms"),
+            (9.9, 0, "<10ms"),
+            (10.0, 1, "10-25ms"),
+            (99.0, 3, "50-100ms"),
+            (100.0, 4, "100-250ms"),
+            (4999.0, 8, "2500-5000ms"),
+            (60000.0, 9, ">=5000ms"),
+        ];
+
+        for (millis, index, expected) in test_cases {
+            let bucket = LatencyBucket::from_millis(millis);
+            assert_eq!(bucket.category(), index);
+            assert_str_eq!(expected, bucket.to_string());
+        }
+    }
+
+    #[test]
+    fn buckets_are_contiguous() {
+        let buckets: Vec<_> = LatencyBucket::all().collect();
+        assert_eq!(buckets.len(), LATENCY_BUCKETS);
+        for pair in buckets.windows(2) {
+            assert_eq!(pair[0].upper_ms(), pair[1].lower_ms());
+        }
+    }
+}
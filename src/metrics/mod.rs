@@ -1,3 +1,5 @@
+pub use latency::{LATENCY_BUCKETS, LatencyBucket};
 pub use status_code::ResponseStatusCode;
 
+mod latency;
 mod status_code;
@@ -1,24 +1,49 @@
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
 use crate::stats::Categorical;
 
 /// [ResponseStatusCode] groups HTTP response status codes according
 /// to five general categories. This type is used as the dependent
 /// variable in statical observations.
-#[derive(Hash, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[derive(Hash, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
 pub enum ResponseStatusCode {
     // Information responses
+    #[serde(rename = "1XX")]
     _1XX,
     // Successful responses
+    #[serde(rename = "2XX")]
     _2XX,
     // Redirection messages
+    #[serde(rename = "3XX")]
     _3XX,
     // Client error responses
+    #[serde(rename = "4XX")]
     _4XX,
     // Server error responses
+    #[serde(rename = "5XX")]
     _5XX,
 }
 
+impl ResponseStatusCode {
+    /// Every category, in the order of their indices.
+    pub const ALL: [Self; 5] = [Self::_1XX, Self::_2XX, Self::_3XX, Self::_4XX, Self::_5XX];
+
+    /// Bin the given HTTP status code into its category. Returns
+    /// `None` if the code is outside of the 100-599 range.
+    pub fn from_status(code: u16) -> Option<Self> {
+        match code {
+            100..=199 => Some(Self::_1XX),
+            200..=299 => Some(Self::_2XX),
+            300..=399 => Some(Self::_3XX),
+            400..=499 => Some(Self::_4XX),
+            500..=599 => Some(Self::_5XX),
+            _ => None,
+        }
+    }
+}
+
 impl Categorical<5> for ResponseStatusCode {
     fn category(&self) -> usize {
         match self {
@@ -46,7 +71,8 @@ impl fmt::Display for ResponseStatusCode {
 #[cfg(test)]
 mod tests {
     use super::ResponseStatusCode;
-    use pretty_assertions::assert_str_eq;
+    use crate::stats::Categorical;
+    use pretty_assertions::{assert_eq, assert_str_eq};
 
     #[test]
     fn fmt_response_status_code() {
@@ -63,4 +89,38 @@ mod tests {
             assert_str_eq!(expected, observed);
         }
     }
+
+    #[test]
+    fn bin_status_codes() {
+        let test_cases = [
+            (101, Some(ResponseStatusCode::_1XX)),
+            (200, Some(ResponseStatusCode::_2XX)),
+            (304, Some(ResponseStatusCode::_3XX)),
+            (429, Some(ResponseStatusCode::_4XX)),
+            (503, Some(ResponseStatusCode::_5XX)),
+            (99, None),
+            (600, None),
+        ];
+
+        for (input, expected) in test_cases {
+            assert_eq!(ResponseStatusCode::from_status(input), expected);
+        }
+    }
+
+    #[test]
+    fn all_categories_in_order() {
+        for (index, category) in ResponseStatusCode::ALL.iter().enumerate() {
+            assert_eq!(category.category(), index);
+        }
+    }
+
+    #[test]
+    fn deserialize_categories() {
+        let categories: Vec<ResponseStatusCode> =
+            serde_json::from_str(r#"["2XX", "5XX"]"#).unwrap();
+        assert_eq!(
+            categories,
+            vec![ResponseStatusCode::_2XX, ResponseStatusCode::_5XX]
+        );
+    }
 }
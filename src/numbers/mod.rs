@@ -12,7 +12,7 @@ mod whole_percent;
 
 /// Unlike floating point numbers, which have floating percision,
 /// this number has fixed percision.
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct FixedPrecisionNumber<const SCALE: usize>(BigDecimal);
 
 // TODO: we probably have to override the default implementaiton
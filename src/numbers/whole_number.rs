@@ -3,7 +3,7 @@ use std::fmt;
 
 use super::FixedPrecisionNumber;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct WholeNumber(FixedPrecisionNumber<0>);
 
 impl fmt::Display for WholeNumber {
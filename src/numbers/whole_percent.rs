@@ -3,6 +3,7 @@ use std::fmt;
 
 use super::OutOfRangeError;
 
+#[derive(Clone, Debug)]
 pub struct WholePercent(WholeNumber);
 
 impl fmt::Display for WholePercent {
@@ -11,6 +12,20 @@ impl fmt::Display for WholePercent {
     }
 }
 
+impl WholePercent {
+    /// Returns the percentage as a fraction between zero and one.
+    /// e.g. 25% becomes 0.25.
+    pub fn as_fraction(&self) -> f64 {
+        f64::from(self.0.clone().as_i32()) / 100.0
+    }
+
+    /// Returns the percentage as a whole number between zero
+    /// and one hundred. e.g. 25% becomes 25.
+    pub fn as_whole(&self) -> i32 {
+        self.0.clone().as_i32()
+    }
+}
+
 impl TryFrom<WholeNumber> for WholePercent {
     type Error = OutOfRangeError;
     fn try_from(value: WholeNumber) -> Result<Self, Self::Error> {
@@ -111,4 +126,11 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn whole_percent_as_fraction() {
+        assert_eq!(WholePercent::try_from(0).unwrap().as_fraction(), 0.0);
+        assert_eq!(WholePercent::try_from(25).unwrap().as_fraction(), 0.25);
+        assert_eq!(WholePercent::try_from(100).unwrap().as_fraction(), 1.0);
+    }
 }
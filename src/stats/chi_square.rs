@@ -0,0 +1,441 @@
+use std::fmt;
+
+use super::{Categorical, contingency::ContingencyTable};
+
+/// The significance level used when the caller doesn't provide one.
+/// A 5% chance of a false positive is the conventional default.
+pub const DEFAULT_ALPHA: f64 = 0.05;
+
+/// The [ChiSquareTest] performs Pearson's chi-square test on a
+/// [ContingencyTable]. It computes a test statistic, converts it
+/// into a p-value using the chi-square distribution, and compares
+/// the p-value against the significance level `alpha`.
+///
+/// Two flavors of the test are available:
+/// * [ChiSquareTest::goodness_of_fit] treats the expected row as a known
+///   distribution and checks whether the observed row could have been
+///   drawn from it.
+/// * [ChiSquareTest::independence] treats both rows as samples and checks
+///   whether the category is independent of the group. This is the
+///   right test when comparing a canary against a baseline, since the
+///   baseline is itself a noisy sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChiSquareTest {
+    alpha: f64,
+}
+
+impl ChiSquareTest {
+    /// Create a new test using the given significance level, which must
+    /// fall within the open interval `(0, 1)`.
+    pub fn new(alpha: f64) -> Result<Self, InvalidSignificanceLevel> {
+        if alpha > 0.0 && alpha < 1.0 {
+            Ok(Self { alpha })
+        } else {
+            Err(InvalidSignificanceLevel(alpha))
+        }
+    }
+
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Test whether the observed row of the table fits the distribution
+    /// described by the expected row.
+    pub fn goodness_of_fit<const N: usize, C: Categorical<N>>(
+        &self,
+        table: &ContingencyTable<N, C>,
+    ) -> ChiSquareOutcome {
+        let mut statistic = 0.0;
+        // Categories that were neither expected nor observed carry no
+        // information, so they don't contribute a degree of freedom.
+        let mut categories: usize = 0;
+        for i in 0..N {
+            let expected = table.expected_by_index(i);
+            let observed = table.observed_by_index(i) as f64;
+            if expected == 0.0 && observed == 0.0 {
+                continue;
+            }
+            categories += 1;
+            if expected == 0.0 {
+                // We observed something that should be impossible.
+                statistic = f64::INFINITY;
+            } else {
+                statistic += (observed - expected).powi(2) / expected;
+            }
+        }
+        self.outcome(statistic, categories.saturating_sub(1))
+    }
+
+    /// Test whether the distribution of categories is independent of
+    /// the group (expected vs. observed). Both rows are treated as raw
+    /// counts, and the expected cell counts are derived from the row and
+    /// column totals.
+    pub fn independence<const N: usize, C: Categorical<N>>(
+        &self,
+        table: &ContingencyTable<N, C>,
+    ) -> ChiSquareOutcome {
+        let rows: [[f64; N]; 2] = [
+            std::array::from_fn(|i| table.expected_count_by_index(i) as f64),
+            std::array::from_fn(|i| table.observed_by_index(i) as f64),
+        ];
+        let row_totals = rows.map(|row| row.iter().sum::<f64>());
+        let total: f64 = row_totals.iter().sum();
+        // If either group is empty, there's nothing to compare.
+        if row_totals.contains(&0.0) {
+            return self.outcome(0.0, 0);
+        }
+
+        let mut statistic = 0.0;
+        let mut categories: usize = 0;
+        for i in 0..N {
+            let column_total = rows[0][i] + rows[1][i];
+            // Empty columns don't contribute a degree of freedom.
+            if column_total == 0.0 {
+                continue;
+            }
+            categories += 1;
+            for (row, row_total) in rows.iter().zip(row_totals) {
+                let expected = row_total * column_total / total;
+                statistic += (row[i] - expected).powi(2) / expected;
+            }
+        }
+        // For a 2xK table, there are (2-1)*(K-1) degrees of freedom.
+        self.outcome(statistic, categories.saturating_sub(1))
+    }
+
+    fn outcome(&self, statistic: f64, degrees_of_freedom: usize) -> ChiSquareOutcome {
+        // With zero degrees of freedom, the data can't disagree with
+        // the null hypothesis.
+        let p_value = if degrees_of_freedom == 0 {
+            1.0
+        } else {
+            chi_square_survival(statistic, degrees_of_freedom)
+        };
+        ChiSquareOutcome {
+            statistic,
+            degrees_of_freedom,
+            p_value,
+            alpha: self.alpha,
+        }
+    }
+}
+
+impl Default for ChiSquareTest {
+    fn default() -> Self {
+        Self {
+            alpha: DEFAULT_ALPHA,
+        }
+    }
+}
+
+/// The result of running a [ChiSquareTest].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChiSquareOutcome {
+    statistic: f64,
+    degrees_of_freedom: usize,
+    p_value: f64,
+    alpha: f64,
+}
+
+impl ChiSquareOutcome {
+    /// Pearson's chi-square test statistic.
+    pub fn statistic(&self) -> f64 {
+        self.statistic
+    }
+
+    /// The number of degrees of freedom used to calculate the p-value.
+    /// Categories without any observations are excluded.
+    pub fn degrees_of_freedom(&self) -> usize {
+        self.degrees_of_freedom
+    }
+
+    /// The probability of seeing a test statistic at least this extreme
+    /// if the null hypothesis were true.
+    pub fn p_value(&self) -> f64 {
+        self.p_value
+    }
+
+    /// The significance level the p-value is compared against.
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Returns true if we can reject the null hypothesis, i.e. the two
+    /// rows of the table differ by more than chance would explain.
+    pub fn is_significant(&self) -> bool {
+        self.p_value < self.alpha
+    }
+}
+
+impl fmt::Display for ChiSquareOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.is_significant() {
+            "the difference is statistically significant"
+        } else {
+            "the difference is not statistically significant"
+        };
+        write!(
+            f,
+            "χ²({}) = {:.3}, p = {:.4} (α = {}): {verdict}",
+            self.degrees_of_freedom, self.statistic, self.p_value, self.alpha
+        )
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("The significance level must be in the open interval (0, 1), but {0} was provided")]
+pub struct InvalidSignificanceLevel(f64);
+
+/// The maximum number of iterations used when evaluating the
+/// incomplete gamma function before giving up on convergence.
+const MAX_ITERATIONS: usize = 1000;
+/// The relative error at which we consider the series converged.
+const EPSILON: f64 = 1e-15;
+/// A number near the smallest representable float, used to prevent
+/// division by zero in the continued fraction.
+const TINY: f64 = 1e-300;
+
+/// Returns `P(X > x)` for a chi-square distribution with `k` degrees
+/// of freedom. This is the upper regularized incomplete gamma function
+/// `Q(k/2, x/2)`.
+fn chi_square_survival(x: f64, k: usize) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    if x.is_infinite() {
+        return 0.0;
+    }
+    upper_regularized_gamma(k as f64 / 2.0, x / 2.0)
+}
+
+/// Returns `P(X <= x)` for a chi-square distribution with `k` degrees
+/// of freedom.
+#[cfg(test)]
+fn chi_square_cdf(x: f64, k: usize) -> f64 {
+    1.0 - chi_square_survival(x, k)
+}
+
+/// The upper regularized incomplete gamma function, `Q(a, x)`.
+/// We use the series expansion when it converges quickly (`x < a + 1`),
+/// and the continued fraction otherwise, as described in
+/// Numerical Recipes §6.2.
+fn upper_regularized_gamma(a: f64, x: f64) -> f64 {
+    if x < a + 1.0 {
+        1.0 - lower_gamma_series(a, x)
+    } else {
+        upper_gamma_continued_fraction(a, x)
+    }
+}
+
+/// Evaluates `P(a, x)` using its series representation.
+fn lower_gamma_series(a: f64, x: f64) -> f64 {
+    let mut denominator = a;
+    let mut term = 1.0 / a;
+    let mut sum = term;
+    for _ in 0..MAX_ITERATIONS {
+        denominator += 1.0;
+        term *= x / denominator;
+        sum += term;
+        if term.abs() < sum.abs() * EPSILON {
+            break;
+        }
+    }
+    sum * (-x + a * x.ln() - ln_gamma(a)).exp()
+}
+
+/// Evaluates `Q(a, x)` using the modified Lentz's method on its
+/// continued fraction representation.
+fn upper_gamma_continued_fraction(a: f64, x: f64) -> f64 {
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..MAX_ITERATIONS {
+        let i = i as f64;
+        let an = -i * (i - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = b + an / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+    (-x + a * x.ln() - ln_gamma(a)).exp() * h
+}
+
+/// The natural log of the gamma function, using the Lanczos
+/// approximation (g=7, n=9), which is accurate to about 15 digits.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    const G: f64 = 7.0;
+    if x < 0.5 {
+        // Use the reflection formula for small inputs.
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + G + 0.5;
+    let series = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .skip(1)
+        .fold(COEFFICIENTS[0], |acc, (i, coef)| {
+            acc + coef / (x + i as f64)
+        });
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{ChiSquareTest, InvalidSignificanceLevel, chi_square_cdf, ln_gamma};
+    use crate::{
+        metrics::ResponseStatusCode,
+        stats::contingency::{Coin, ContingencyTable},
+    };
+
+    /// Floating point comparisons need some wiggle room.
+    fn assert_close(expected: f64, observed: f64, tolerance: f64) {
+        assert!(
+            (expected - observed).abs() < tolerance,
+            "expected {expected}, observed {observed}"
+        );
+    }
+
+    #[test]
+    fn ln_gamma_matches_factorials() {
+        // Γ(n) = (n-1)!
+        assert_close(0.0, ln_gamma(1.0), 1e-12);
+        assert_close(0.0, ln_gamma(2.0), 1e-12);
+        assert_close(24f64.ln(), ln_gamma(5.0), 1e-12);
+        // Γ(1/2) = √π
+        assert_close(std::f64::consts::PI.sqrt().ln(), ln_gamma(0.5), 1e-12);
+    }
+
+    /// These values come from a standard chi-square table.
+    #[test]
+    fn chi_square_cdf_critical_values() {
+        let test_cases = [
+            // (statistic, degrees of freedom, cdf)
+            (3.841, 1, 0.95),
+            (6.635, 1, 0.99),
+            (5.991, 2, 0.95),
+            (9.488, 4, 0.95),
+            (0.0, 3, 0.0),
+        ];
+        for (x, k, expected) in test_cases {
+            assert_close(expected, chi_square_cdf(x, k), 1e-4);
+        }
+        // With two degrees of freedom, the CDF has a closed form.
+        assert_close(1.0 - (-1.0f64).exp(), chi_square_cdf(2.0, 2), 1e-12);
+    }
+
+    #[test]
+    fn alpha_must_be_a_probability() {
+        assert_eq!(ChiSquareTest::new(0.0), Err(InvalidSignificanceLevel(0.0)));
+        assert_eq!(ChiSquareTest::new(1.0), Err(InvalidSignificanceLevel(1.0)));
+        assert!(ChiSquareTest::new(0.01).is_ok());
+    }
+
+    /// A coin that lands on heads 20 times out of 100 is not fair.
+    #[test]
+    fn unfair_coin_goodness_of_fit() {
+        let mut table = ContingencyTable::new();
+        table.set_expected(&Coin::Heads, 50);
+        table.set_expected(&Coin::Tails, 50);
+        table.set_observed(&Coin::Heads, 20);
+        table.set_observed(&Coin::Tails, 80);
+
+        let outcome = ChiSquareTest::default().goodness_of_fit(&table);
+        // (20-50)²/50 + (80-50)²/50 = 36
+        assert_close(36.0, outcome.statistic(), 1e-9);
+        assert_eq!(outcome.degrees_of_freedom(), 1);
+        assert!(outcome.p_value() < 1e-8);
+        assert!(outcome.is_significant());
+    }
+
+    /// A coin that lands on heads 48 times out of 100 is probably fair.
+    #[test]
+    fn fair_coin_goodness_of_fit() {
+        let mut table = ContingencyTable::new();
+        table.set_expected(&Coin::Heads, 1);
+        table.set_expected(&Coin::Tails, 1);
+        table.set_observed(&Coin::Heads, 48);
+        table.set_observed(&Coin::Tails, 52);
+
+        let outcome = ChiSquareTest::default().goodness_of_fit(&table);
+        assert_close(0.16, outcome.statistic(), 1e-9);
+        assert_close(0.6892, outcome.p_value(), 1e-4);
+        assert!(!outcome.is_significant());
+    }
+
+    /// Scenario: the baseline served 1000 requests with 10 server errors.
+    /// The canary served 100 requests with 9 server errors. The canary
+    /// is a regression.
+    #[test]
+    fn canary_regression_independence() {
+        let mut table = ContingencyTable::new();
+        table.set_expected(&ResponseStatusCode::_2XX, 990);
+        table.set_expected(&ResponseStatusCode::_5XX, 10);
+        table.set_observed(&ResponseStatusCode::_2XX, 91);
+        table.set_observed(&ResponseStatusCode::_5XX, 9);
+
+        let outcome = ChiSquareTest::default().independence(&table);
+        // The 1XX, 3XX, and 4XX columns are empty, so there's only one
+        // degree of freedom.
+        assert_eq!(outcome.degrees_of_freedom(), 1);
+        assert!(outcome.is_significant(), "{outcome}");
+        // The difference is in the wrong direction: the canary observed more
+        // server errors than we'd expect given the baseline.
+        let expected_errors = table.expected(&ResponseStatusCode::_5XX);
+        let observed_errors = table.observed(&ResponseStatusCode::_5XX) as f64;
+        assert!(observed_errors > expected_errors);
+    }
+
+    /// Scenario: the baseline and the canary have nearly identical error rates.
+    #[test]
+    fn healthy_canary_independence() {
+        let mut table = ContingencyTable::new();
+        table.set_expected(&ResponseStatusCode::_2XX, 990);
+        table.set_expected(&ResponseStatusCode::_5XX, 10);
+        table.set_observed(&ResponseStatusCode::_2XX, 98);
+        table.set_observed(&ResponseStatusCode::_5XX, 2);
+
+        let outcome = ChiSquareTest::default().independence(&table);
+        assert!(!outcome.is_significant(), "{outcome}");
+    }
+
+    /// Without any observations in one of the groups, we can't draw
+    /// any conclusions.
+    #[test]
+    fn empty_group_is_never_significant() {
+        let mut table = ContingencyTable::new();
+        table.set_expected(&ResponseStatusCode::_2XX, 990);
+        table.set_expected(&ResponseStatusCode::_5XX, 10);
+
+        let outcome = ChiSquareTest::default().independence(&table);
+        assert_eq!(outcome.degrees_of_freedom(), 0);
+        assert_eq!(outcome.p_value(), 1.0);
+        assert!(!outcome.is_significant());
+    }
+}
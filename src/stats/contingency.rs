@@ -1,6 +1,6 @@
 use std::num::NonZeroUsize;
 
-use crate::stats::{Categorical, histogram::Histogram};
+use crate::stats::{Categorical, CategoricalObservation, Group, histogram::Histogram};
 
 /// A `ContingencyTable` is conceptually a two-dimensional table,
 /// where each column represents a category, each row is a group (expected and observed),
@@ -49,6 +49,12 @@ impl<const N: usize, C: Categorical<N>> ContingencyTable<N, C> {
         expected_in_category * total_observed / expected_total
     }
 
+    /// returns the raw count in the expected row for the category with
+    /// index `i`, without scaling it against the observed total.
+    pub fn expected_count_by_index(&self, i: usize) -> u32 {
+        self.expected.get_count_by_index(i)
+    }
+
     /// calculate the observed count for the category with index `i`.
     pub fn observed_by_index(&self, i: usize) -> u32 {
         self.observed.get_count_by_index(i)
@@ -65,6 +71,16 @@ impl<const N: usize, C: Categorical<N>> ContingencyTable<N, C> {
         NonZeroUsize::new(N - 1).unwrap()
     }
 
+    /// returns the total number of observations in the expected row.
+    pub fn expected_total(&self) -> u32 {
+        self.expected.total()
+    }
+
+    /// returns the total number of observations in the observed row.
+    pub fn observed_total(&self) -> u32 {
+        self.observed.total()
+    }
+
     pub fn observed(&self, cat: &C) -> u32 {
         self.observed.get_count(cat)
     }
@@ -84,6 +100,17 @@ impl<const N: usize, C: Categorical<N>> ContingencyTable<N, C> {
     pub fn increment_observed(&mut self, cat: &C, count: u32) {
         self.observed.increment_by(cat, count);
     }
+
+    /// Add the observation's counts to the table. Observations from the
+    /// control group form the expectation, and observations from the
+    /// experimental group are the observed counts.
+    pub fn add_observation(&mut self, observation: &CategoricalObservation<N, C>) {
+        let row = match observation.group() {
+            Group::Control => &mut self.expected,
+            Group::Experimental => &mut self.observed,
+        };
+        row.merge(observation.histogram());
+    }
 }
 
 impl<const N: usize, C: Categorical<N>> Default for ContingencyTable<N, C> {
@@ -170,6 +197,27 @@ mod tests {
         );
     }
 
+    /// Control observations fill the expected row, and experimental
+    /// observations fill the observed row.
+    #[test]
+    fn add_observations_by_group() {
+        let mut table = ContingencyTable::new();
+        let mut control = CategoricalObservation::new(Group::Control);
+        control.increment_by(&ResponseStatusCode::_2XX, 50);
+        control.increment_by(&ResponseStatusCode::_5XX, 20);
+        let mut canary = CategoricalObservation::new(Group::Experimental);
+        canary.increment_by(&ResponseStatusCode::_2XX, 10);
+        canary.increment_by(&ResponseStatusCode::_5XX, 30);
+        table.add_observation(&control);
+        table.add_observation(&canary);
+        table.add_observation(&canary);
+
+        assert_eq!(table.expected_count_by_index(1), 50);
+        assert_eq!(table.expected_count_by_index(4), 20);
+        assert_eq!(table.observed(&ResponseStatusCode::_2XX), 20);
+        assert_eq!(table.observed(&ResponseStatusCode::_5XX), 60);
+    }
+
     /// Demonstrate the default implementation to calculate
     /// degrees of freedom is correct.
     #[test]
@@ -180,7 +228,10 @@ mod tests {
         assert_eq!(observed, expected);
     }
 
-    use crate::{metrics::ResponseStatusCode, stats::Categorical};
+    use crate::{
+        metrics::ResponseStatusCode,
+        stats::{Categorical, CategoricalObservation, Group},
+    };
     #[derive(PartialEq, Eq, Debug, Hash)]
     pub(crate) enum Coin {
         Heads,
@@ -41,9 +41,10 @@ where
     }
 
     /// Increment the observed count for the given category by `count`.
+    /// Counts saturate rather than overflow on very long runs.
     pub fn increment_by(&mut self, categorical: &C, count: u32) {
         let index = categorical.category();
-        self.bins[index] += count;
+        self.bins[index] = self.bins[index].saturating_add(count);
     }
 
     /// Return the count for the given category.
@@ -55,7 +56,9 @@ where
     /// return the total number of observations in the histogram.
     /// This is the sum of the counts across all bins.
     pub fn total(&self) -> u32 {
-        self.bins.iter().sum::<u32>()
+        self.bins
+            .iter()
+            .fold(0, |total, count| total.saturating_add(*count))
     }
 
     /// Reset all bins to zero.
@@ -74,6 +77,13 @@ where
         self.increment_by(cat, count);
     }
 
+    /// Add the counts from each of the other histogram's bins to this one.
+    pub(super) fn merge(&mut self, other: &Self) {
+        for (bin, count) in self.bins.iter_mut().zip(other.bins.iter()) {
+            *bin = bin.saturating_add(*count);
+        }
+    }
+
     pub(super) fn get_count_by_index(&self, i: usize) -> u32 {
         if i >= N {
             panic!(
@@ -149,4 +159,19 @@ mod tests {
         assert_eq!(hist.get_count(&true), 2);
         assert_eq!(hist.get_count(&false), 1);
     }
+
+    /// Long runs can't overflow the counts.
+    #[test]
+    fn counts_saturate() {
+        let mut hist = Histogram::new();
+        hist.increment_by(&true, u32::MAX - 1);
+        hist.increment_by(&false, 1);
+        let mut other = Histogram::new();
+        other.increment_by(&true, 2);
+        hist.merge(&other);
+        assert_eq!(hist.get_count(&true), u32::MAX);
+        assert_eq!(hist.total(), u32::MAX);
+        hist.increment(&true);
+        assert_eq!(hist.get_count(&true), u32::MAX);
+    }
 }
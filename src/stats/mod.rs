@@ -1,9 +1,15 @@
 pub use categorical::Categorical;
+pub use chi_square::{ChiSquareOutcome, ChiSquareTest};
+pub use contingency::ContingencyTable;
 pub use group::Group;
-pub use observation::{CategoricalObservation, Observation};
+pub use observation::{CategoricalObservation, Observation, ObservationWindow};
+pub use sequential::{SequentialDecision, SequentialTest};
 
 /// For modeling categorical data.
 mod categorical;
+/// Pearson's chi-square test, for deciding whether the canary's
+/// observations differ significantly from the baseline's.
+mod chi_square;
 mod contingency;
 /// `group` defines the two groups.
 mod group;
@@ -11,3 +17,6 @@ mod group;
 mod histogram;
 /// An observation represents a group and the observed category.
 mod observation;
+/// A sequential probability ratio test, which can decide whether the
+/// canary is a regression as soon as the evidence allows.
+mod sequential;
@@ -1,12 +1,60 @@
 use std::fmt::Debug;
 
+use chrono::{DateTime, SecondsFormat, Utc};
+
 use super::{Categorical, group::Group, histogram::Histogram};
 use std::fmt;
 
-/// Marker trait. This marker trait is used to ensure concrete types satisfy
+/// This trait is used to ensure concrete types satisfy
 /// the expectations of the backend. The backend accepts
-/// certain types of metrics only, like Response Codes, CPU, and Memory.
-pub trait Observation: Debug {}
+/// certain types of metrics only, like Response Codes, CPU, and Memory,
+/// and each must be attributed to either the baseline or the canary.
+pub trait Observation: Debug {
+    /// The group the observation was taken from.
+    fn group(&self) -> Group;
+}
+
+/// An [ObservationWindow] is the span of time an observation covers.
+/// Monitors usually collect data over a window, like the time since
+/// their last query, rather than at a single instant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ObservationWindow {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+impl ObservationWindow {
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self { start, end }
+    }
+
+    pub fn start(&self) -> DateTime<Utc> {
+        self.start
+    }
+
+    pub fn end(&self) -> DateTime<Utc> {
+        self.end
+    }
+
+    /// Returns the smallest window covering both windows.
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+impl fmt::Display for ObservationWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} to {}",
+            self.start.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.end.to_rfc3339_opts(SecondsFormat::Secs, true)
+        )
+    }
+}
 
 // Implement the marker trait. CategoricalObservations are a type of observation.
 /// An [CategoricalObservation] represents a measured outcome binned into
@@ -25,6 +73,8 @@ pub struct CategoricalObservation<const N: usize, Cat: Categorical<N>> {
     /// The outcome of the observation, bucketed into a specific category.
     /// e.g. a response status code's highest order digit, 2XX, 5XX, etc.
     histogram: Histogram<N, Cat>,
+    /// The span of time the observation covers, if known.
+    window: Option<ObservationWindow>,
 }
 
 impl<const N: usize, Cat: Categorical<N>> CategoricalObservation<N, Cat> {
@@ -33,9 +83,19 @@ impl<const N: usize, Cat: Categorical<N>> CategoricalObservation<N, Cat> {
         Self {
             group,
             histogram: Histogram::default(),
+            window: None,
         }
     }
 
+    /// Record the span of time the observation covers.
+    pub fn set_window(&mut self, window: ObservationWindow) {
+        self.window = Some(window);
+    }
+
+    pub fn window(&self) -> Option<ObservationWindow> {
+        self.window
+    }
+
     /// Increase the stored count for the given category by the given number.
     pub fn increment_by(&mut self, category: &Cat, count: u32) {
         self.histogram.increment_by(category, count);
@@ -48,26 +108,57 @@ impl<const N: usize, Cat: Categorical<N>> CategoricalObservation<N, Cat> {
     pub fn get_count(&self, cat: &Cat) -> u32 {
         self.histogram.get_count(cat)
     }
+
+    /// Returns the total number of observations across all categories.
+    pub fn total(&self) -> u32 {
+        self.histogram.total()
+    }
+
+    pub(super) fn histogram(&self) -> &Histogram<N, Cat> {
+        &self.histogram
+    }
 }
 
 impl<const N: usize, Cat: Categorical<N> + fmt::Debug> fmt::Debug
     for CategoricalObservation<N, Cat>
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Group: {:?}, Outcome: {:?}", self.group, self.histogram)
+        write!(f, "Group: {:?}, Outcome: {:?}", self.group, self.histogram)?;
+        if let Some(window) = self.window {
+            write!(f, ", Window: {window}")?;
+        }
+        Ok(())
     }
 }
 
-// Implement the marker trait. CategoricalObservations are a type of observation.
+// CategoricalObservations are a type of observation.
 impl<const N: usize, Cat: Categorical<N> + fmt::Debug> Observation
     for CategoricalObservation<N, Cat>
 {
+    fn group(&self) -> Group {
+        self.group
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Observation;
+    use super::{Observation, ObservationWindow};
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::{assert_eq, assert_str_eq};
     use static_assertions::assert_obj_safe;
 
     assert_obj_safe!(Observation);
+
+    #[test]
+    fn window_union() {
+        let at = |minute| Utc.with_ymd_and_hms(2025, 1, 1, 12, minute, 0).unwrap();
+        let first = ObservationWindow::new(at(0), at(1));
+        let second = ObservationWindow::new(at(1), at(2));
+        assert_eq!(first.union(second), ObservationWindow::new(at(0), at(2)));
+        assert_eq!(second.union(first), ObservationWindow::new(at(0), at(2)));
+        assert_str_eq!(
+            first.to_string(),
+            "2025-01-01T12:00:00Z to 2025-01-01T12:01:00Z"
+        );
+    }
 }
@@ -0,0 +1,338 @@
+use bon::bon;
+
+use super::{Categorical, CategoricalObservation, Group};
+
+/// By default, we accept a 5% chance of rolling back a healthy canary.
+const DEFAULT_ALPHA: f64 = 0.05;
+/// By default, we accept a 20% chance of failing to detect a regression
+/// before the test concludes that there is none.
+const DEFAULT_BETA: f64 = 0.2;
+/// By default, we consider the canary a regression if it fails at least
+/// twice as often as the baseline.
+const DEFAULT_RELATIVE_RISK: f64 = 2.0;
+/// The verdict of a [SequentialTest] given the data observed so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequentialDecision {
+    /// The canary fails significantly more often than the baseline.
+    Regression,
+    /// We're confident the canary does not fail more often than the
+    /// baseline by the configured relative risk.
+    NoRegression,
+    /// There isn't enough evidence in either direction yet.
+    Continue,
+}
+
+/// A [SequentialTest] is a sequential probability ratio test (SPRT) over
+/// the failure rate of the experimental group relative to the control
+/// group. Unlike a fixed-horizon test, it can be evaluated after every
+/// observation while keeping the false positive rate below `alpha`, so it
+/// can stop as soon as the evidence is overwhelming.
+///
+/// The baseline failure rate is unknown, and may drift over the course of
+/// the rollout, so rather than estimating it, the test conditions it away.
+/// Observations are paired into strata: once both groups have been observed
+/// since the last stratum closed, their counts form a new one. Given how many
+/// requests each group served in a stratum, and how many of them failed, the
+/// number of failures that were the canary's is hypergeometric if the groups
+/// fail at the same rate, and Fisher's noncentral hypergeometric if the
+/// canary's odds of failure are `relative_risk` times higher. Neither depends
+/// on the baseline rate, and for the low failure rates we care about, the
+/// odds ratio is close to the relative risk.
+///
+/// The product of each stratum's likelihood ratio has an expected value of
+/// one while the canary is healthy, however the baseline changes between
+/// strata. By Ville's inequality, it exceeds `1 / alpha` with probability at
+/// most `alpha`, no matter how often it's checked, so that's where we declare
+/// a regression. The lower bound is Wald's approximation, and only affects
+/// how long it takes to accept a healthy canary.
+///
+/// Once a boundary is crossed, the decision is final.
+pub struct SequentialTest<const N: usize, C: Categorical<N>> {
+    /// The category that counts as a failure, e.g. 5XX status codes.
+    failure: C,
+    /// The natural log of the relative risk, used as the odds ratio.
+    log_odds_ratio: f64,
+    /// Once the log-likelihood ratio exceeds this bound, we declare a regression.
+    upper_bound: f64,
+    /// Once the log-likelihood ratio falls below this bound, we declare the
+    /// canary healthy.
+    lower_bound: f64,
+    /// Counts for each group since the last stratum closed.
+    control: Counts,
+    experimental: Counts,
+    /// The log-likelihood ratio summed over every closed stratum.
+    log_likelihood_ratio: Option<f64>,
+    decision: SequentialDecision,
+}
+
+#[bon]
+impl<const N: usize, C: Categorical<N>> SequentialTest<N, C> {
+    #[builder]
+    pub fn new(
+        /// The category that counts as a failure.
+        failure: C,
+        /// The maximum probability of declaring a regression when there is none.
+        alpha: Option<f64>,
+        /// The maximum probability of missing a regression of size `relative_risk`.
+        beta: Option<f64>,
+        /// How many times more often the canary must fail to be considered a regression.
+        relative_risk: Option<f64>,
+    ) -> Result<Self, SequentialTestError> {
+        let alpha = alpha.unwrap_or(DEFAULT_ALPHA);
+        let beta = beta.unwrap_or(DEFAULT_BETA);
+        let relative_risk = relative_risk.unwrap_or(DEFAULT_RELATIVE_RISK);
+        if !is_probability(alpha) {
+            return Err(SequentialTestError::Alpha(alpha));
+        }
+        if !is_probability(beta) {
+            return Err(SequentialTestError::Beta(beta));
+        }
+        if relative_risk.is_nan() || relative_risk <= 1.0 {
+            return Err(SequentialTestError::RelativeRisk(relative_risk));
+        }
+        Ok(Self {
+            failure,
+            log_odds_ratio: relative_risk.ln(),
+            // Ville's inequality bounds the false positive rate, unlike
+            // Wald's approximation, which assumes the baseline is known.
+            upper_bound: (1.0 / alpha).ln(),
+            lower_bound: (beta / (1.0 - alpha)).ln(),
+            control: Counts::default(),
+            experimental: Counts::default(),
+            log_likelihood_ratio: None,
+            decision: SequentialDecision::Continue,
+        })
+    }
+}
+
+impl<const N: usize, C: Categorical<N>> SequentialTest<N, C> {
+    /// Add an observation to the test and return the updated decision.
+    pub fn observe(&mut self, observation: &CategoricalObservation<N, C>) -> SequentialDecision {
+        let counts = match observation.group() {
+            Group::Control => &mut self.control,
+            Group::Experimental => &mut self.experimental,
+        };
+        counts.total += u64::from(observation.total());
+        counts.failures += u64::from(observation.get_count(&self.failure));
+        // Each stratum needs both groups, so wait for the other one.
+        if self.control.total == 0 || self.experimental.total == 0 {
+            return self.decision;
+        }
+        let stratum = stratum_log_likelihood_ratio(
+            self.log_odds_ratio,
+            std::mem::take(&mut self.control),
+            std::mem::take(&mut self.experimental),
+        );
+        let llr = self.log_likelihood_ratio.unwrap_or(0.0) + stratum;
+        self.log_likelihood_ratio = Some(llr);
+        // The decision is final once a boundary has been crossed.
+        if self.decision == SequentialDecision::Continue {
+            self.decision = if llr >= self.upper_bound {
+                SequentialDecision::Regression
+            } else if llr <= self.lower_bound {
+                SequentialDecision::NoRegression
+            } else {
+                SequentialDecision::Continue
+            };
+        }
+        self.decision
+    }
+
+    /// The decision given all of the data observed so far.
+    pub fn decision(&self) -> SequentialDecision {
+        self.decision
+    }
+
+    /// The current log-likelihood ratio of the alternative hypothesis
+    /// (the canary is a regression) against the null hypothesis. Returns
+    /// `None` until both groups have been observed, closing the first stratum.
+    pub fn log_likelihood_ratio(&self) -> Option<f64> {
+        self.log_likelihood_ratio
+    }
+}
+
+/// The log-likelihood ratio of the canary's share of a stratum's failures,
+/// under Fisher's noncentral hypergeometric distribution with the given odds
+/// ratio, against the central one.
+fn stratum_log_likelihood_ratio(log_odds_ratio: f64, control: Counts, experimental: Counts) -> f64 {
+    let failures = control.failures + experimental.failures;
+    // The canary's share of the failures ranges over `lowest..=highest`.
+    let lowest = failures.saturating_sub(control.total);
+    let highest = failures.min(experimental.total);
+    // The log of each share's weight, relative to the lowest share's.
+    // Consecutive weights are C(n_e, u) * C(n_c, k - u), whose ratio
+    // is cheap to compute without overflowing.
+    let mut log_weights = Vec::with_capacity((highest - lowest + 1) as usize);
+    let mut log_weight = 0.0;
+    for share in lowest..=highest {
+        log_weights.push(log_weight);
+        let numerator = (experimental.total - share) as f64 * (failures - share) as f64;
+        let denominator = (share + 1) as f64 * (control.total + share + 1 - failures) as f64;
+        log_weight += (numerator / denominator).ln();
+    }
+    let null = log_sum_exp(log_weights.iter().copied());
+    let alternative = log_sum_exp(
+        (lowest..=highest)
+            .zip(&log_weights)
+            .map(|(share, log_weight)| log_weight + share as f64 * log_odds_ratio),
+    );
+    experimental.failures as f64 * log_odds_ratio + null - alternative
+}
+
+/// Computes `ln(sum(exp(x)))` without overflowing.
+fn log_sum_exp(values: impl Iterator<Item = f64> + Clone) -> f64 {
+    let max = values.clone().fold(f64::NEG_INFINITY, f64::max);
+    max + values.map(|value| (value - max).exp()).sum::<f64>().ln()
+}
+
+/// Running totals for one group.
+#[derive(Default, Clone, Copy)]
+struct Counts {
+    total: u64,
+    failures: u64,
+}
+
+fn is_probability(value: f64) -> bool {
+    value > 0.0 && value < 1.0
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum SequentialTestError {
+    #[error("Alpha must be in the open interval (0, 1), but {0} was provided")]
+    Alpha(f64),
+    #[error("Beta must be in the open interval (0, 1), but {0} was provided")]
+    Beta(f64),
+    #[error("The relative risk must be greater than 1, but {0} was provided")]
+    RelativeRisk(f64),
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+    use super::{DEFAULT_ALPHA, SequentialDecision, SequentialTest, SequentialTestError};
+    use crate::{
+        metrics::ResponseStatusCode,
+        stats::{CategoricalObservation, Group},
+    };
+
+    fn observation(
+        group: Group,
+        ok: u32,
+        errors: u32,
+    ) -> CategoricalObservation<5, ResponseStatusCode> {
+        let mut obs = CategoricalObservation::new(group);
+        obs.increment_by(&ResponseStatusCode::_2XX, ok);
+        obs.increment_by(&ResponseStatusCode::_5XX, errors);
+        obs
+    }
+
+    fn new_test() -> SequentialTest<5, ResponseStatusCode> {
+        SequentialTest::builder()
+            .failure(ResponseStatusCode::_5XX)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        let relative_risk = SequentialTest::<5, _>::builder()
+            .failure(ResponseStatusCode::_5XX)
+            .relative_risk(0.5)
+            .build()
+            .err();
+        assert_eq!(relative_risk, Some(SequentialTestError::RelativeRisk(0.5)));
+        let alpha = SequentialTest::<5, _>::builder()
+            .failure(ResponseStatusCode::_5XX)
+            .alpha(1.5)
+            .build()
+            .err();
+        assert_eq!(alpha, Some(SequentialTestError::Alpha(1.5)));
+    }
+
+    /// We can't say anything until we've seen both groups.
+    #[test]
+    fn continue_without_data() {
+        let mut test = new_test();
+        assert_eq!(test.decision(), SequentialDecision::Continue);
+        test.observe(&observation(Group::Control, 1000, 1));
+        assert_eq!(test.log_likelihood_ratio(), None);
+        assert_eq!(test.decision(), SequentialDecision::Continue);
+    }
+
+    /// A canary returning errors for half of its requests should be caught
+    /// within the first small batch.
+    #[test]
+    fn obvious_regression_is_caught_early() {
+        let mut test = new_test();
+        test.observe(&observation(Group::Control, 999, 1));
+        let decision = test.observe(&observation(Group::Experimental, 10, 10));
+        assert_eq!(decision, SequentialDecision::Regression);
+    }
+
+    /// A canary that fails as often as the baseline should eventually be
+    /// declared healthy.
+    #[test]
+    fn healthy_canary_is_accepted() {
+        let mut test = new_test();
+        let mut decision = SequentialDecision::Continue;
+        for _ in 0..10 {
+            test.observe(&observation(Group::Control, 990, 10));
+            decision = test.observe(&observation(Group::Experimental, 100, 1));
+        }
+        assert_eq!(decision, SequentialDecision::NoRegression);
+    }
+
+    /// Small amounts of data that mirror the baseline aren't enough to decide.
+    #[test]
+    fn ambiguous_data_continues() {
+        let mut test = new_test();
+        test.observe(&observation(Group::Control, 990, 10));
+        let decision = test.observe(&observation(Group::Experimental, 99, 1));
+        assert_eq!(decision, SequentialDecision::Continue);
+    }
+
+    /// Once a decision is reached, new data doesn't change it.
+    #[test]
+    fn decisions_are_final() {
+        let mut test = new_test();
+        test.observe(&observation(Group::Control, 999, 1));
+        test.observe(&observation(Group::Experimental, 10, 10));
+        test.observe(&observation(Group::Control, 100_000, 100));
+        let decision = test.observe(&observation(Group::Experimental, 100_000, 0));
+        assert_eq!(decision, SequentialDecision::Regression);
+    }
+
+    /// Simulate rollouts of healthy canaries, where the baseline failure
+    /// rate drifts between batches, and check that we rarely roll them back.
+    #[test]
+    fn false_positive_rate_is_bounded() {
+        const ROLLOUTS: u32 = 1000;
+        const BATCHES: u32 = 50;
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut sample = |group, requests, failure_rate| {
+            let errors = (0..requests)
+                .filter(|_| rng.random_bool(failure_rate))
+                .count() as u32;
+            observation(group, requests - errors, errors)
+        };
+        let mut false_positives = 0;
+        for _ in 0..ROLLOUTS {
+            let mut test = new_test();
+            for batch in 0..BATCHES {
+                let failure_rate = if batch % 10 < 5 { 0.01 } else { 0.05 };
+                test.observe(&sample(Group::Control, 200, failure_rate));
+                test.observe(&sample(Group::Experimental, 50, failure_rate));
+            }
+            if test.decision() == SequentialDecision::Regression {
+                false_positives += 1;
+            }
+        }
+        let false_positive_rate = f64::from(false_positives) / f64::from(ROLLOUTS);
+        assert!(
+            false_positive_rate <= DEFAULT_ALPHA,
+            "{false_positive_rate} exceeds {DEFAULT_ALPHA}"
+        );
+    }
+}
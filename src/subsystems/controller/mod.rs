@@ -1,11 +1,17 @@
 use async_trait::async_trait;
 use bon::bon;
 use miette::{Report, Result};
+use tokio::sync::watch;
 use tokio_graceful_shutdown::{IntoSubsystem, SubsystemBuilder, SubsystemHandle};
 use tracing::{debug, trace};
 
-use crate::adapters::{BackendClient, BoxedIngress, BoxedMonitor, BoxedPlatform, RolloutMetadata};
+use crate::adapters::{
+    BoxedIngress, BoxedMonitor, BoxedPlatform, Events, HooksConfig, ProbeConfig, RolloutMetadata,
+    SharedBackend,
+};
+use crate::fs::InFlightRolloutFile;
 use crate::subsystems::PLATFORM_SUBSYSTEM_NAME;
+use crate::terminal::Dashboard;
 use crate::{IngressSubsystem, PlatformSubsystem};
 
 use monitor::{MONITOR_CONTROLLER_SUBSYSTEM_NAME, MonitorController};
@@ -21,24 +27,40 @@ pub const CONTROLLER_SUBSYSTEM_NAME: &str = "controller";
 /// on cloud resources, and reports the state of those instructions back
 /// to the backend.
 pub struct ControllerSubsystem {
-    backend: BackendClient,
+    backend: SharedBackend,
     monitor: BoxedMonitor,
     ingress: BoxedIngress,
     platform: BoxedPlatform,
     /// This field contains context about the current rollout
     /// and is frequently passed to the backend.
     meta: RolloutMetadata,
+    /// The record of this rollout on disk, cleared once the rollout
+    /// finishes. Local rollouts can't be resumed, so they don't have one.
+    in_flight: Option<InFlightRolloutFile>,
+    dashboard: Dashboard,
+    events: Events,
+    hooks: Option<HooksConfig>,
+    probes: Option<ProbeConfig>,
+    /// The canary deployed for this rollout. When resuming, it
+    /// starts out as the canary an earlier run deployed.
+    canary: watch::Sender<Option<String>>,
 }
 
 #[bon]
 impl ControllerSubsystem {
     #[builder]
     pub fn new(
-        backend: BackendClient,
+        backend: SharedBackend,
         monitor: BoxedMonitor,
         ingress: BoxedIngress,
         platform: BoxedPlatform,
         meta: RolloutMetadata,
+        in_flight: Option<InFlightRolloutFile>,
+        dashboard: Dashboard,
+        events: Events,
+        hooks: Option<HooksConfig>,
+        probes: Option<ProbeConfig>,
+        canary: watch::Sender<Option<String>>,
     ) -> Self {
         trace!("Creating a new controller subsystem...");
 
@@ -48,6 +70,12 @@ impl ControllerSubsystem {
             ingress,
             platform,
             meta,
+            in_flight,
+            dashboard,
+            events,
+            hooks,
+            probes,
+            canary,
         }
     }
 }
@@ -62,7 +90,10 @@ impl IntoSubsystem<Report> for ControllerSubsystem {
         let platform_subsystem = PlatformSubsystem::new(self.platform);
         let platform_handle = platform_subsystem.handle();
 
-        let mut monitor_controller = MonitorController::builder().monitor(self.monitor).build();
+        let mut monitor_controller = MonitorController::builder()
+            .monitor(self.monitor)
+            .events(self.events.clone())
+            .build();
         let observation_stream = monitor_controller.stream()?;
 
         let relay_subsystem = RelaySubsystem::builder()
@@ -71,6 +102,12 @@ impl IntoSubsystem<Report> for ControllerSubsystem {
             .platform(platform_handle)
             .ingress(ingress_handle)
             .meta(self.meta)
+            .maybe_in_flight(self.in_flight)
+            .dashboard(self.dashboard)
+            .events(self.events)
+            .maybe_hooks(self.hooks)
+            .maybe_probes(self.probes)
+            .canary(self.canary)
             .build();
 
         // • Start the ingress subsystem.
@@ -11,12 +11,12 @@ use tokio::{
 };
 use tokio_graceful_shutdown::{IntoSubsystem, SubsystemBuilder, SubsystemHandle};
 use tokio_stream::{Stream, StreamExt as _, wrappers::IntervalStream};
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::{
     MonitorSubsystem,
-    adapters::{BoxedMonitor, StatusCode},
-    stats::Observation,
+    adapters::{BoxedMonitor, DataGap, Events, Measurement, MonitorError, RolloutEvent},
+    stats::ObservationWindow,
     subsystems::{MONITOR_SUBSYSTEM_NAME, TakenOptionalError},
 };
 
@@ -35,6 +35,15 @@ const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(60);
 /// The frequency with which we emit data from the controller,
 /// (usually to go to the backend).
 const DEFAULT_EMIT_INTERVAL: Duration = Duration::from_secs(60);
+/// How many times a query that failed with a transient error, like
+/// being throttled, is retried before we give up on it.
+const DEFAULT_MAX_RETRIES: u32 = 3;
+/// How long we wait before the first retry. Each subsequent retry
+/// waits twice as long as the one before it.
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+/// The longest we'll wait between retries. All of the retries should
+/// fit well within the poll interval.
+const MAX_BACKOFF: Duration = Duration::from_secs(16);
 
 pub const MONITOR_CONTROLLER_SUBSYSTEM_NAME: &str = "controller/monitor";
 
@@ -45,29 +54,32 @@ pub const MONITOR_CONTROLLER_SUBSYSTEM_NAME: &str = "controller/monitor";
 ///
 /// Its a "controller" in the sense of PID controller, not in the sense
 /// of Model-View-Controller.
-pub struct MonitorController<T>
-where
-    T: Observation,
-{
+pub struct MonitorController {
     monitor: BoxedMonitor,
     /// This field stores the stream of outputs.
     /// The stream can only be given to one caller. The first
     /// call to `Self::stream` will return the stream, and all
     /// subsequent calls will return None.
-    recv: Option<Receiver<Vec<T>>>,
-    sender: Sender<Vec<T>>,
+    recv: Option<Receiver<Vec<Measurement>>>,
+    sender: Sender<Vec<Measurement>>,
     poll_interval: Duration,
     emit_interval: Duration,
+    backoff: Backoff,
     on_error: Box<dyn Fn(&miette::Report) + Send + Sync>,
+    /// Tells anyone listening about each batch, and each failed query.
+    events: Events,
 }
 
 #[bon]
-impl MonitorController<StatusCode> {
+impl MonitorController {
     #[builder]
     pub fn new(
         monitor: BoxedMonitor,
         poll_interval: Option<Duration>,
         emit_interval: Option<Duration>,
+        max_retries: Option<u32>,
+        initial_backoff: Option<Duration>,
+        events: Events,
     ) -> Self {
         let (sender, receiver) = mpsc::channel(DEFAULT_MAX_BATCH_SIZE);
         Self {
@@ -76,13 +88,18 @@ impl MonitorController<StatusCode> {
             recv: Some(receiver),
             poll_interval: poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL),
             emit_interval: emit_interval.unwrap_or(DEFAULT_EMIT_INTERVAL),
+            backoff: Backoff {
+                max_retries: max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+                initial: initial_backoff.unwrap_or(DEFAULT_INITIAL_BACKOFF),
+            },
             on_error: Box::new(log_error),
+            events,
         }
     }
 
     /// This function returns a channel receiver of values the first time
     /// its called. Subsequent calls return None.
-    pub fn stream(&mut self) -> Result<Receiver<Vec<StatusCode>>> {
+    pub fn stream(&mut self) -> Result<Receiver<Vec<Measurement>>> {
         self.recv.take().ok_or(TakenOptionalError.into())
         // TODO: This block of code produces an Unpin error at the caller
         //       when using a Stream instead of a receiver, but its
@@ -107,7 +124,7 @@ impl MonitorController<StatusCode> {
 }
 
 #[async_trait]
-impl IntoSubsystem<Report> for MonitorController<StatusCode> {
+impl IntoSubsystem<Report> for MonitorController {
     async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
         // • Build the `MonitorSubsystem`. Don't launch it until
         //   we take a handle to it.
@@ -124,8 +141,13 @@ impl IntoSubsystem<Report> for MonitorController<StatusCode> {
         // Now, we can periodically poll the monitor for
         // new data.
         // • First, schedule the Monitor to be queried every so often.
-        let query_stream = repeat_query(handle, self.poll_interval)
-            .inspect_err(|e| (self.on_error)(e))
+        let query_stream = repeat_query(handle, self.poll_interval, self.backoff)
+            .inspect_err(|e| {
+                (self.on_error)(e);
+                self.events.emit(RolloutEvent::MonitorFailed {
+                    message: e.to_string(),
+                });
+            })
             .filter_map(Result::ok);
         // • Next, aggregate query results and emit them every so often.
         let chunked_stream =
@@ -149,7 +171,13 @@ impl IntoSubsystem<Report> for MonitorController<StatusCode> {
                 next = chunked_stream.next() => {
                     if let Some(batch) = next {
                         // We received a new batch of observations.
-                        // Let's emit them to our output stream.
+                        // Let's emit them to our output stream. Each
+                        // observation keeps the window it was collected
+                        // over, however long it takes to emit.
+                        if let Some(window) = batch_window(&batch) {
+                            debug!("Emitting {} observations from {window}", batch.len());
+                        }
+                        self.events.emit(RolloutEvent::observation_batch(&batch));
                         self.sender.send(batch).await.unwrap();
                     } else {
                         debug!("Shutting down in monitor");
@@ -166,12 +194,58 @@ fn log_error(err: &Report) {
     tracing::error!("Error while collecting monitoring data: {err}");
 }
 
+/// Returns the span of time covered by the batch's observations.
+fn batch_window(batch: &[Measurement]) -> Option<ObservationWindow> {
+    batch
+        .iter()
+        .filter_map(Measurement::window)
+        .reduce(ObservationWindow::union)
+}
+
+/// Describes how failed queries are retried.
+#[derive(Clone, Copy, Debug)]
+struct Backoff {
+    max_retries: u32,
+    initial: Duration,
+}
+
+impl Backoff {
+    /// How long to wait before the given retry, counting from zero.
+    fn delay(&self, retry: u32) -> Duration {
+        self.initial
+            .saturating_mul(1 << retry.min(16))
+            .min(MAX_BACKOFF)
+    }
+}
+
+/// Query the monitor, retrying transient failures with exponential
+/// backoff. Monitors retry transient failures over the same window,
+/// so a retry that succeeds doesn't lose any data.
+async fn query_with_retry(
+    monitor: &mut BoxedMonitor,
+    backoff: Backoff,
+) -> Result<Vec<Measurement>> {
+    let mut retry = 0;
+    loop {
+        match monitor.query().await {
+            Err(err) if retry < backoff.max_retries && MonitorError::is_retryable(&err) => {
+                let delay = backoff.delay(retry);
+                warn!("Monitor query failed, retrying in {delay:?}: {err}");
+                tokio::time::sleep(delay).await;
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
 /// [repeat_query] runs the query on an interval and returns a stream of items.
 /// This function runs indefinitely, as long as its polled.
 fn repeat_query(
     mut monitor: BoxedMonitor,
     duration: tokio::time::Duration,
-) -> impl Stream<Item = Result<StatusCode>> {
+    backoff: Backoff,
+) -> impl Stream<Item = Result<Measurement>> {
     // • Everything happens in this stream closure, which desugars
     //   into a background thread and a channel write at yield points.
     async_stream::stream! {
@@ -184,14 +258,148 @@ fn repeat_query(
         // Each iteration of the loop represents one unit of tiem.
         while timer.next().await.is_some() {
             // • We perform the query then dump the results into the stream.
-            match monitor.query().await {
+            match query_with_retry(&mut monitor, backoff).await {
                 Ok(items) => {
                     for item in items {
                         yield Ok(item);
                     }
                 },
-                Err(err) => yield Err(err),
+                Err(err) => {
+                    // • Besides reporting the error, record the gap in
+                    //   the data so it isn't mistaken for a quiet canary.
+                    let gap = DataGap::new(MonitorError::kind_of(&err));
+                    yield Err(err);
+                    yield Ok(gap.into());
+                },
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use chrono::{TimeZone, Utc};
+    use miette::Result;
+    use pretty_assertions::assert_eq;
+
+    use super::{Backoff, MAX_BACKOFF, batch_window, query_with_retry};
+    use crate::{
+        Shutdownable,
+        adapters::{
+            BoxedMonitor, DataGap, Measurement, Monitor, MonitorError, MonitorErrorKind, StatusCode,
+        },
+        stats::{Group, ObservationWindow},
+        subsystems::ShutdownResult,
+    };
+
+    /// A monitor that fails with each of the given errors in turn,
+    /// then succeeds.
+    struct Flaky {
+        failures: Vec<MonitorErrorKind>,
+        calls: usize,
+    }
+
+    #[async_trait]
+    impl Shutdownable for Flaky {
+        async fn shutdown(&mut self) -> ShutdownResult {
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Monitor for Flaky {
+        async fn query(&mut self) -> Result<Vec<Measurement>> {
+            self.calls += 1;
+            match self.failures.get(self.calls - 1) {
+                Some(MonitorErrorKind::Transient) => {
+                    Err(MonitorError::Transient("throttled".to_owned()).into())
+                }
+                Some(MonitorErrorKind::Permanent) => {
+                    Err(MonitorError::Permanent("bad query".to_owned()).into())
+                }
+                Some(MonitorErrorKind::Auth) => {
+                    Err(MonitorError::Auth("access denied".to_owned()).into())
+                }
+                None => Ok(Vec::new()),
+            }
+        }
+    }
+
+    fn backoff(max_retries: u32) -> Backoff {
+        Backoff {
+            max_retries,
+            initial: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_a_limit() {
+        let backoff = Backoff {
+            max_retries: 3,
+            initial: Duration::from_secs(2),
+        };
+        let delays: Vec<_> = (0..5).map(|retry| backoff.delay(retry)).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Duration::from_secs(2),
+                Duration::from_secs(4),
+                Duration::from_secs(8),
+                MAX_BACKOFF,
+                MAX_BACKOFF
+            ]
+        );
+    }
+
+    #[test]
+    fn batches_span_their_windows() {
+        let at = |minute| Utc.with_ymd_and_hms(2025, 1, 1, 12, minute, 0).unwrap();
+        let batch = vec![
+            Measurement::from(StatusCode::new(Group::Control))
+                .with_window(ObservationWindow::new(at(1), at(2))),
+            Measurement::from(DataGap::new(MonitorErrorKind::Transient)),
+            Measurement::from(StatusCode::new(Group::Experimental))
+                .with_window(ObservationWindow::new(at(0), at(1))),
+        ];
+        assert_eq!(
+            batch_window(&batch),
+            Some(ObservationWindow::new(at(0), at(2)))
+        );
+        assert_eq!(batch_window(&batch[1..2]), None);
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_retried() {
+        let flaky = Flaky {
+            failures: vec![MonitorErrorKind::Transient; 2],
+            calls: 0,
+        };
+        let mut monitor: BoxedMonitor = Box::new(flaky);
+        assert!(query_with_retry(&mut monitor, backoff(3)).await.is_ok());
+        // Too many failures, and we give up.
+        let flaky = Flaky {
+            failures: vec![MonitorErrorKind::Transient; 2],
+            calls: 0,
+        };
+        let mut monitor: BoxedMonitor = Box::new(flaky);
+        assert!(query_with_retry(&mut monitor, backoff(1)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn other_errors_are_not_retried() {
+        for kind in [MonitorErrorKind::Permanent, MonitorErrorKind::Auth] {
+            let flaky = Flaky {
+                failures: vec![kind],
+                calls: 0,
+            };
+            let mut monitor: BoxedMonitor = Box::new(flaky);
+            let err = query_with_retry(&mut monitor, backoff(3))
+                .await
+                .unwrap_err();
+            assert_eq!(MonitorError::kind_of(&err), kind);
+        }
+    }
+}
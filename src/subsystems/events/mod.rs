@@ -0,0 +1,73 @@
+use async_trait::async_trait;
+use miette::{Report, Result};
+use tokio::select;
+use tokio::sync::broadcast::{Receiver, error::RecvError};
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
+use tracing::{debug, warn};
+
+use crate::adapters::{BoxedEventSink, EventRecord, Events};
+
+pub const EVENTS_SUBSYSTEM_NAME: &str = "events";
+/// Webhooks are notified by their own [EventSubsystem], so a slow
+/// webhook doesn't hold up the other sinks.
+pub const NOTIFIER_SUBSYSTEM_NAME: &str = "notifier";
+
+/// The [EventSubsystem] delivers rollout events to each sink. A sink
+/// that fails is reported, but never stops the rollout.
+pub struct EventSubsystem {
+    receiver: Receiver<EventRecord>,
+    sinks: Vec<BoxedEventSink>,
+}
+
+impl EventSubsystem {
+    /// Subscribe to the events right away, so none emitted
+    /// before the subsystem starts are missed.
+    pub(crate) fn new(events: &Events, sinks: Vec<BoxedEventSink>) -> Self {
+        Self {
+            receiver: events.subscribe(),
+            sinks,
+        }
+    }
+
+    async fn deliver(&mut self, record: &EventRecord) {
+        for sink in &mut self.sinks {
+            if let Err(err) = sink.send(record).await {
+                warn!("Failed to deliver a rollout event: {err}");
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl IntoSubsystem<Report> for EventSubsystem {
+    async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
+        debug!("Running the events subsystem...");
+        loop {
+            select! {
+                _ = subsys.on_shutdown_requested() => {
+                    // Deliver whatever was emitted on the way out,
+                    // like the final promotion or rollback.
+                    while let Ok(record) = self.receiver.try_recv() {
+                        self.deliver(&record).await;
+                    }
+                    for sink in &mut self.sinks {
+                        if let Err(err) = sink.flush().await {
+                            warn!("Failed to flush rollout events: {err}");
+                        }
+                    }
+                    return Ok(());
+                }
+                elem = self.receiver.recv() => {
+                    match elem {
+                        Ok(record) => self.deliver(&record).await,
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!("Dropped {skipped} rollout events because the sinks fell behind");
+                        }
+                        // Every handle was dropped, so nothing else will be emitted.
+                        Err(RecvError::Closed) => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+}
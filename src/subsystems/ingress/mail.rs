@@ -11,6 +11,7 @@ pub(super) enum IngressMail {
     SetCanaryTraffic(TrafficParams),
     RollbackCanary(RollbackParams),
     PromoteCanary(PromoteParams),
+    Reattach(ReattachParams),
 }
 
 #[async_trait]
@@ -45,6 +46,14 @@ impl Ingress for IngressHandle {
         self.outbox.send(mail).await.into_diagnostic()?;
         receiver.await.into_diagnostic()?
     }
+
+    async fn reattach(&mut self, platform_id: String) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        let params = ReattachParams::new(sender, platform_id);
+        let mail = IngressMail::Reattach(params);
+        self.outbox.send(mail).await.into_diagnostic()?;
+        receiver.await.into_diagnostic()?
+    }
 }
 
 pub(super) struct ReleaseParams {
@@ -100,7 +109,24 @@ impl PromoteParams {
     }
 }
 
+pub(super) struct ReattachParams {
+    /// The sender where the response is written.
+    pub(super) outbox: oneshot::Sender<ReattachResp>,
+    /// The canary deployed by an earlier run.
+    pub(super) platform_id: String,
+}
+
+impl ReattachParams {
+    pub(super) fn new(outbox: oneshot::Sender<ReattachResp>, platform_id: String) -> Self {
+        Self {
+            outbox,
+            platform_id,
+        }
+    }
+}
+
 pub(super) type ReleaseResp = Result<()>;
 pub(super) type RollbackResp = Result<()>;
 pub(super) type PromoteResp = Result<()>;
 pub(super) type TrafficResp = Result<()>;
+pub(super) type ReattachResp = Result<()>;
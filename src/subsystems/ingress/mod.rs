@@ -1,7 +1,9 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use mail::{IngressMail, PromoteParams, ReleaseParams, RollbackParams, TrafficParams};
+use mail::{
+    IngressMail, PromoteParams, ReattachParams, ReleaseParams, RollbackParams, TrafficParams,
+};
 use miette::{Report, Result};
 use tokio::sync::mpsc::channel;
 use tokio::{select, sync::mpsc::Receiver};
@@ -54,6 +56,7 @@ impl IngressSubsystem {
             IngressMail::RollbackCanary(params) => self.handle_rollback(params).await,
             IngressMail::PromoteCanary(params) => self.handle_promote(params).await,
             IngressMail::SetCanaryTraffic(params) => self.handle_set_traffic(params).await,
+            IngressMail::Reattach(params) => self.handle_reattach(params).await,
         }
     }
 
@@ -77,6 +80,11 @@ impl IngressSubsystem {
         let result = self.ingress.set_canary_traffic(percent).await;
         params.outbox.send(result).unwrap();
     }
+
+    async fn handle_reattach(&mut self, params: ReattachParams) {
+        let result = self.ingress.reattach(params.platform_id).await;
+        params.outbox.send(result).unwrap();
+    }
 }
 
 #[async_trait]
@@ -2,6 +2,7 @@ use async_trait::async_trait;
 use miette::Diagnostic;
 
 pub use controller::{CONTROLLER_SUBSYSTEM_NAME, ControllerSubsystem};
+pub use events::{EVENTS_SUBSYSTEM_NAME, EventSubsystem, NOTIFIER_SUBSYSTEM_NAME};
 pub use ingress::{INGRESS_SUBSYSTEM_NAME, IngressSubsystem};
 
 pub use monitor::{MONITOR_SUBSYSTEM_NAME, MonitorSubsystem};
@@ -9,6 +10,8 @@ pub use platform::{PLATFORM_SUBSYSTEM_NAME, PlatformSubsystem};
 pub use relay::{RELAY_SUBSYSTEM_NAME, RelaySubsystem};
 
 mod controller;
+/// Delivers rollout events to the sinks and webhooks the user asked for.
+mod events;
 mod handle;
 mod ingress;
 mod monitor;
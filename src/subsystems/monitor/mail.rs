@@ -2,14 +2,16 @@ use async_trait::async_trait;
 use miette::{IntoDiagnostic as _, Result};
 use tokio::sync::oneshot;
 
-use crate::{adapters::Monitor, stats::Observation, subsystems::handle::Handle};
+use crate::{
+    adapters::{Measurement, Monitor},
+    subsystems::handle::Handle,
+};
 
-pub(super) type MonitorHandle<T> = Handle<MonitorMail<T>>;
+pub(super) type MonitorHandle = Handle<MonitorMail>;
 
 #[async_trait]
-impl<T: Observation + Send + 'static> Monitor for MonitorHandle<T> {
-    type Item = T;
-    async fn query(&mut self) -> Result<Vec<T>> {
+impl Monitor for MonitorHandle {
+    async fn query(&mut self) -> Result<Vec<Measurement>> {
         let (sender, receiver) = oneshot::channel();
         let params = QueryParams::new(sender);
         let mail = MonitorMail::Query(params);
@@ -18,19 +20,19 @@ impl<T: Observation + Send + 'static> Monitor for MonitorHandle<T> {
     }
 }
 
-pub(super) enum MonitorMail<T: Observation> {
-    Query(QueryParams<T>),
+pub(super) enum MonitorMail {
+    Query(QueryParams),
 }
 
-pub(super) struct QueryParams<T: Observation> {
+pub(super) struct QueryParams {
     /// The sender where the response is written.
-    pub(super) outbox: oneshot::Sender<QueryResp<T>>,
+    pub(super) outbox: oneshot::Sender<QueryResp>,
 }
 
-impl<T: Observation> QueryParams<T> {
-    pub(super) fn new(outbox: oneshot::Sender<QueryResp<T>>) -> Self {
+impl QueryParams {
+    pub(super) fn new(outbox: oneshot::Sender<QueryResp>) -> Self {
         Self { outbox }
     }
 }
 
-pub(super) type QueryResp<T> = Result<Vec<T>>;
+pub(super) type QueryResp = Result<Vec<Measurement>>;
@@ -1,7 +1,6 @@
 use std::sync::Arc;
 
-use crate::adapters::{BoxedMonitor, StatusCode};
-use crate::stats::Observation;
+use crate::adapters::BoxedMonitor;
 use async_trait::async_trait;
 use mail::{MonitorHandle, MonitorMail, QueryParams};
 use miette::{Report, Result};
@@ -19,14 +18,14 @@ pub const MONITOR_SUBSYSTEM_NAME: &str = "monitor";
 /// than picking a power of two.
 const MONITOR_MAILBOX_SIZE: usize = 1 << 4;
 
-pub struct MonitorSubsystem<T: Observation> {
+pub struct MonitorSubsystem {
     monitor: BoxedMonitor,
-    handle: MonitorHandle<T>,
-    mailbox: Receiver<MonitorMail<T>>,
+    handle: MonitorHandle,
+    mailbox: Receiver<MonitorMail>,
     shutdown: Receiver<()>,
 }
 
-impl MonitorSubsystem<StatusCode> {
+impl MonitorSubsystem {
     pub fn new(monitor: BoxedMonitor) -> Self {
         let (shutdown_trigger, shutdown_signal) = channel(1);
         let (mail_outbox, mailbox) = channel(MONITOR_MAILBOX_SIZE);
@@ -45,20 +44,20 @@ impl MonitorSubsystem<StatusCode> {
         Box::new(self.handle.clone())
     }
 
-    async fn respond_to_mail(&mut self, mail: MonitorMail<StatusCode>) {
+    async fn respond_to_mail(&mut self, mail: MonitorMail) {
         match mail {
             MonitorMail::Query(params) => self.handle_query(params).await,
         }
     }
 
-    async fn handle_query(&mut self, params: QueryParams<StatusCode>) {
+    async fn handle_query(&mut self, params: QueryParams) {
         let result = self.monitor.query().await;
         params.outbox.send(result).unwrap();
     }
 }
 
 #[async_trait]
-impl IntoSubsystem<Report> for MonitorSubsystem<StatusCode> {
+impl IntoSubsystem<Report> for MonitorSubsystem {
     async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
         loop {
             select! {
@@ -82,7 +81,7 @@ impl IntoSubsystem<Report> for MonitorSubsystem<StatusCode> {
 }
 
 #[async_trait]
-impl Shutdownable for MonitorSubsystem<StatusCode> {
+impl Shutdownable for MonitorSubsystem {
     async fn shutdown(&mut self) -> ShutdownResult {
         // We just have to shut the monitor down manually,
         // since we have an exclusive lock on it.
@@ -94,12 +93,10 @@ mod mail;
 
 #[cfg(test)]
 mod tests {
-    use crate::adapters::StatusCode;
-
     use super::MonitorSubsystem;
     use miette::Report;
     use static_assertions::assert_impl_all;
     use tokio_graceful_shutdown::IntoSubsystem;
 
-    assert_impl_all!(MonitorSubsystem<StatusCode>: IntoSubsystem<Report>);
+    assert_impl_all!(MonitorSubsystem: IntoSubsystem<Report>);
 }
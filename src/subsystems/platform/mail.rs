@@ -11,6 +11,7 @@ pub(super) enum PlatformMail {
     YankCanary(YankParams),
     DeleteCanary(DeleteParams),
     PromoteRollout(PromoteParams),
+    Reattach(ReattachParams),
 }
 
 #[async_trait]
@@ -46,6 +47,14 @@ impl Platform for PlatformHandle {
         self.outbox.send(mail).await.into_diagnostic()?;
         receiver.await.into_diagnostic()?
     }
+
+    async fn reattach(&mut self, canary_id: String) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        let params = ReattachParams::new(sender, canary_id);
+        let mail = PlatformMail::Reattach(params);
+        self.outbox.send(mail).await.into_diagnostic()?;
+        receiver.await.into_diagnostic()?
+    }
 }
 
 pub(super) struct DeployParams {
@@ -95,7 +104,21 @@ impl DeleteParams {
     }
 }
 
+pub(super) struct ReattachParams {
+    /// The sender where the response is written.
+    pub(super) outbox: oneshot::Sender<ReattachResp>,
+    /// The id of the canary deployed by the earlier run.
+    pub(super) canary_id: String,
+}
+
+impl ReattachParams {
+    pub(super) fn new(outbox: oneshot::Sender<ReattachResp>, canary_id: String) -> Self {
+        Self { outbox, canary_id }
+    }
+}
+
 pub(super) type DeployResp = Result<String>;
 pub(super) type RollbackResp = Result<()>;
 pub(super) type PromoteResp = Result<()>;
 pub(super) type DeleteResp = Result<()>;
+pub(super) type ReattachResp = Result<()>;
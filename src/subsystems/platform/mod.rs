@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use mail::{DeleteParams, DeployParams, PlatformMail, PromoteParams, YankParams};
+use mail::{DeleteParams, DeployParams, PlatformMail, PromoteParams, ReattachParams, YankParams};
 use miette::{Report, Result};
 use tokio::{
     select,
@@ -61,6 +61,7 @@ impl PlatformSubsystem {
             PlatformMail::YankCanary(params) => self.handle_yank(params).await,
             PlatformMail::DeleteCanary(params) => self.handle_delete(params).await,
             PlatformMail::PromoteRollout(params) => self.handle_promote(params).await,
+            PlatformMail::Reattach(params) => self.handle_reattach(params).await,
         }
     }
 
@@ -84,7 +85,13 @@ impl PlatformSubsystem {
 
     async fn handle_promote(&mut self, params: PromoteParams) {
         let outbox = params.outbox;
-        let result = self.platform.yank_canary().await;
+        let result = self.platform.promote_rollout().await;
+        outbox.send(result).unwrap();
+    }
+
+    async fn handle_reattach(&mut self, params: ReattachParams) {
+        let outbox = params.outbox;
+        let result = self.platform.reattach(params.canary_id).await;
         outbox.send(result).unwrap();
     }
 }
@@ -173,4 +180,30 @@ mod tests {
         assert!(res2.is_ok());
         Ok(())
     }
+
+    /// Promoting through the handle promotes the underlying platform.
+    #[tokio::test]
+    async fn promote_through_platform_subsystem() -> Result<()> {
+        let mut mock_platform = MockPlatform::new();
+        mock_platform
+            .expect_promote_rollout()
+            .times(1)
+            .returning(|| Ok(()));
+        mock_platform.expect_yank_canary().never();
+        let platform_subsys = PlatformSubsystem::new(Box::new(mock_platform));
+        let mut handle = platform_subsys.handle();
+        let system_fut = Toplevel::new(|s| async move {
+            s.start(SubsystemBuilder::new(
+                PLATFORM_SUBSYSTEM_NAME,
+                platform_subsys.into_subsystem(),
+            ));
+        })
+        .handle_shutdown_requests(Duration::from_millis(1000));
+        let join_handle = tokio::spawn(system_fut);
+        assert!(handle.promote_rollout().await.is_ok());
+        let (res1, res2) = join!(handle.shutdown(), join_handle);
+        assert!(res1.is_ok());
+        assert!(res2.is_ok());
+        Ok(())
+    }
 }
@@ -2,7 +2,6 @@ use async_trait::async_trait;
 use bon::bon;
 use miette::miette;
 use miette::{Report, Result};
-use multitool_sdk::models::RolloutState;
 use tokio::select;
 use tokio::sync::mpsc::{self, Receiver};
 use tokio::sync::oneshot;
@@ -11,7 +10,7 @@ use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
 
 use crate::{
     Shutdownable,
-    adapters::{BackendClient, RolloutMetadata},
+    adapters::{RolloutMetadata, SharedBackend, TargetState},
     subsystems::ShutdownResult,
 };
 
@@ -19,7 +18,7 @@ use super::LockedState;
 
 pub(super) struct LockManager {
     /// We use this client to refresh locks.
-    backend: BackendClient,
+    backend: SharedBackend,
     /// This field describes the current active rollout.
     /// This is context we pass to the backend on each request.
     meta: RolloutMetadata,
@@ -38,9 +37,9 @@ pub(super) struct LockManager {
 impl LockManager {
     #[builder]
     pub(super) async fn new(
-        backend: BackendClient,
+        backend: SharedBackend,
         metadata: RolloutMetadata,
-        state: RolloutState,
+        state: TargetState,
     ) -> Result<Self> {
         let (done_sender, task_done) = mpsc::channel(1);
         // Take the initial lock.
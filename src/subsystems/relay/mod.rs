@@ -1,21 +1,25 @@
 use async_trait::async_trait;
 use bon::bon;
 use miette::{Report, Result, miette};
-use multitool_sdk::models::RolloutStateData;
 use multitool_sdk::models::RolloutStateType::{
     DeployCanary, PromoteCanary, RollbackCanary, SetCanaryTraffic,
 };
 use tokio::time::Duration;
-use tokio::{select, sync::mpsc::Receiver};
+use tokio::{
+    select,
+    sync::{mpsc::Receiver, watch},
+};
 use tokio_graceful_shutdown::{IntoSubsystem, SubsystemBuilder, SubsystemHandle};
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::WholePercent;
 use crate::adapters::LockedState;
-use crate::{
-    adapters::{BackendClient, BoxedIngress, BoxedPlatform, RolloutMetadata, StatusCode},
-    stats::Observation,
+use crate::adapters::{
+    BoxedIngress, BoxedPlatform, Events, HookContext, HookStage, HooksConfig, Measurement,
+    ProbeConfig, RolloutEvent, RolloutMetadata, SharedBackend,
 };
+use crate::fs::{FileSystem, InFlightRollout, InFlightRolloutFile};
+use crate::terminal::Dashboard;
 
 pub const RELAY_SUBSYSTEM_NAME: &str = "relay";
 
@@ -24,17 +28,16 @@ use poll_state::StatePoller;
 
 /// The RelaySubsystem is responsible for sending messages
 /// to and from the backend.
-pub struct RelaySubsystem<T: Observation + Send + 'static> {
+pub struct RelaySubsystem {
     /// The relay subsystem needs a backend client
     /// so it can send monitoring data to the backend,
     /// update the backend when a new state is effected,
     /// and poll for new states to apply.
-    backend: BackendClient,
+    backend: SharedBackend,
     // These observations come from the MonitorSubsystem.
     // They must be sent to the backend whenever available.
-    // Pin<Box<Stream<Item=T: Observation>>
     // NB: This should probably happen in its own thread.
-    observations: Receiver<Vec<T>>,
+    observations: Receiver<Vec<Measurement>>,
     /// This field provides context about the current rollout,
     /// and is frequently serialized and passed to the backend on
     /// each request.
@@ -42,18 +45,38 @@ pub struct RelaySubsystem<T: Observation + Send + 'static> {
     platform: BoxedPlatform,
     ingress: BoxedIngress,
     backend_poll_frequency: Option<Duration>,
+    /// The record of the rollout on disk, which lets it be resumed
+    /// if the CLI exits before the rollout is finished.
+    in_flight: Option<InFlightRolloutFile>,
+    /// Shows the operator how the rollout is going.
+    dashboard: Dashboard,
+    /// Tells anyone listening about each transition in the rollout.
+    events: Events,
+    /// The user's commands to run around each state.
+    hooks: HooksConfig,
+    /// Synthetic requests that check the canary before it receives traffic.
+    probes: Option<ProbeConfig>,
+    /// The canary deployed for this rollout, if any, which every
+    /// state's hooks and the monitor are told about.
+    canary: watch::Sender<Option<String>>,
 }
 
 #[bon]
-impl<T: Observation + Send + 'static> RelaySubsystem<T> {
+impl RelaySubsystem {
     #[builder]
     pub fn new(
-        backend: BackendClient,
+        backend: SharedBackend,
         meta: RolloutMetadata,
-        observations: Receiver<Vec<T>>,
+        observations: Receiver<Vec<Measurement>>,
         platform: BoxedPlatform,
         ingress: BoxedIngress,
         backend_poll_frequency: Option<Duration>,
+        in_flight: Option<InFlightRolloutFile>,
+        dashboard: Dashboard,
+        events: Events,
+        hooks: Option<HooksConfig>,
+        probes: Option<ProbeConfig>,
+        canary: watch::Sender<Option<String>>,
     ) -> Self {
         debug!("Creating a new relay subsystem...");
         Self {
@@ -63,7 +86,32 @@ impl<T: Observation + Send + 'static> RelaySubsystem<T> {
             platform,
             ingress,
             backend_poll_frequency,
+            in_flight,
+            dashboard,
+            events,
+            hooks: hooks.unwrap_or_default(),
+            probes,
+            canary,
+        }
+    }
+
+    /// The rollout is finished, so there's nothing left to resume.
+    fn clear_in_flight(&self) -> Result<()> {
+        if let Some(file) = &self.in_flight {
+            FileSystem::new()?.delete(file)?;
+        }
+        Ok(())
+    }
+
+    /// Remember which canary was deployed, so a later run
+    /// resuming the rollout reattaches to exactly this one.
+    fn record_canary(&mut self, canary_id: &str) -> Result<()> {
+        self.canary.send_replace(Some(canary_id.to_owned()));
+        if let Some(file) = &self.in_flight {
+            let record = InFlightRollout::new(self.meta.clone(), Some(canary_id.to_owned()));
+            FileSystem::new()?.save_file(file, &record)?;
         }
+        Ok(())
     }
 
     fn new_poller(&mut self) -> StatePoller {
@@ -79,9 +127,23 @@ impl<T: Observation + Send + 'static> RelaySubsystem<T> {
 }
 
 #[async_trait]
-impl IntoSubsystem<Report> for RelaySubsystem<StatusCode> {
+impl IntoSubsystem<Report> for RelaySubsystem {
     async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
         debug!("Running the relay subsystem...");
+        let result = self.relay(&subsys).await;
+        if let Err(err) = &result {
+            self.events.emit(RolloutEvent::Error {
+                message: err.to_string(),
+            });
+        }
+        result
+    }
+}
+
+impl RelaySubsystem {
+    /// Upload observations and effect the backend's states until
+    /// the rollout is finished or we're asked to shut down.
+    async fn relay(&mut self, subsys: &SubsystemHandle) -> Result<()> {
         // Kick off a task to poll the backend for new states.
         let mut poller = self.new_poller();
         let mut state_stream = poller.take_stream()?;
@@ -90,7 +152,6 @@ impl IntoSubsystem<Report> for RelaySubsystem<StatusCode> {
             poller.into_subsystem(),
         ));
 
-        let mut observations = self.observations;
         loop {
             select! {
                 // Besides that, we can just hang out.
@@ -101,10 +162,10 @@ impl IntoSubsystem<Report> for RelaySubsystem<StatusCode> {
                 // • When we start the RelaySubsystem,
                 //   we need to select on the observation stream.
                 //   When a new observation arrives, we send it to the backend.
-                elem = observations.recv() => {
+                elem = self.observations.recv() => {
                     debug!("Received new observation: {:?}", &elem);
                     if let Some(batch) = elem {
-                        self.backend.upload_observations(&self.meta, batch).await?;
+                        self.observe(batch).await?;
                     } else {
                         // The stream has been closed, so we should shutdown.
                         debug!("Shutting down in relay");
@@ -115,7 +176,7 @@ impl IntoSubsystem<Report> for RelaySubsystem<StatusCode> {
                 elem = state_stream.recv() => {
                     debug!("Received new state: {:?}", &elem);
                     if let Some(state) = elem {
-                        let state_id = state.id;
+                        let state_id = *state.id();
                         // When we receive a new state, we attempt to lock it.
                         let lock_manager = LockManager::builder()
                             .backend(self.backend.clone())
@@ -124,19 +185,38 @@ impl IntoSubsystem<Report> for RelaySubsystem<StatusCode> {
                             .build().await?;
                         let mut locked_state = lock_manager.state().clone();
                         // Launch the lock manager.
-                        subsys.start(SubsystemBuilder::new(
+                        let lock_subsystem = subsys.start(SubsystemBuilder::new(
                             format!("LockManager {}", state_id),
                             lock_manager.into_subsystem(),
                         ));
+                        let state_type = *locked_state.state().state_type();
+                        self.dashboard.enter_state(state_type);
+                        self.events.emit(RolloutEvent::StateLocked { state_id, state_type });
+                        // • Run the user's hooks before effecting the state. If one
+                        //   fails, we release the lock so a later run can effect it.
+                        let mut hook_context = HookContext::new(&self.meta, locked_state.state(), self.canary.borrow().clone());
+                        if let Err(err) = self.hooks.run(HookStage::Before, &hook_context).await {
+                            lock_subsystem.initiate_shutdown();
+                            if let Err(join_err) = lock_subsystem.join().await {
+                                warn!("Failed to abandon the lock on state {state_id}: {join_err}");
+                            }
+                            return Err(err);
+                        }
                         // Now that we have the lock managed, we
                         // need to tell the Platform/Ingress
                         // to effect the state.
-                        match locked_state.state().state_type {
+                        match state_type {
                             PromoteCanary => {
                                 // Ingress operation.
                                 self.ingress.promote_canary().await?;
+                                // Once the ingress serves the canary, make it
+                                // the platform's new baseline.
+                                self.platform.promote_rollout().await?;
+                                self.dashboard.set_canary_traffic(WholePercent::try_from(100).unwrap());
+                                self.events.emit(RolloutEvent::Promoted);
 
                                 locked_state.mark_done().await?;
+                                self.clear_in_flight()?;
 
                                 // If the canary is promoted, we can safely just shut down the CLI
                                 subsys.request_shutdown();
@@ -146,36 +226,51 @@ impl IntoSubsystem<Report> for RelaySubsystem<StatusCode> {
                                 // this point, it won't have any traffic, and the ingress doesn't
                                 // know anything about it.
                                 let platform_id = self.platform.deploy().await.inspect(|res| debug!("Result: {res:?}"))?;
+                                self.record_canary(&platform_id)?;
                                 // Next, we need the ingress to acknowledge the platform's existance,
                                 // creating a CanarySettings objects with zero traffic.
-                                self.ingress.release_canary(platform_id).await.inspect(|res| debug!("Result: {res:?}"))?;
+                                self.ingress.release_canary(platform_id.clone()).await.inspect(|res| debug!("Result: {res:?}"))?;
+                                self.dashboard.set_canary_traffic(WholePercent::try_from(0).unwrap());
+                                hook_context.set_canary_id(platform_id.clone());
+                                self.events.emit(RolloutEvent::CanaryDeployed { platform_id: platform_id.clone() });
 
                                 locked_state.mark_done().await?;
+                                // Before the canary receives any traffic, make
+                                // sure it can serve requests at all.
+                                self.probe_canary(&platform_id).await?;
                             },
                             SetCanaryTraffic => {
-                                let percent_traffic = if let Some(data) = locked_state.state().data.clone().flatten() {
-                                    let RolloutStateData::RolloutStateDataOneOf(state_data) = *data;
-                                    state_data.set_canary_traffic.percent_traffic
-                                } else{
-                                    return Err(miette!("No data found in state"));
-                                };
-                                let percent = WholePercent::try_from(percent_traffic).unwrap();
-                                self.ingress.set_canary_traffic(percent).await?;
+                                let percent = locked_state
+                                    .state()
+                                    .percent_traffic()
+                                    .clone()
+                                    .ok_or(miette!("No data found in state"))?;
+                                self.ingress.set_canary_traffic(percent.clone()).await?;
+                                self.events.emit(RolloutEvent::TrafficSet { percent: percent.as_whole() });
+                                self.dashboard.set_canary_traffic(percent);
 
                                 locked_state.mark_done().await?;
                             },
                             RollbackCanary => {
-                                // Set traffic to 0 immediately.
-                                self.ingress.set_canary_traffic(WholePercent::try_from(0).unwrap()).await?;
-                                // Then, yank the canary from the ingress.
-                                self.ingress.rollback_canary().await?;
+                                self.rollback_canary().await?;
 
                                 locked_state.mark_done().await?;
+                                self.clear_in_flight()?;
 
                                 // If the canary is rolled back, we can safely just shut down the CLI
                                 subsys.request_shutdown();
                             },
                         }
+                        // • Run the user's hooks now that the state is effected.
+                        //   If the canary is still live and fails them, we roll it
+                        //   back and tell the backend the rollout is over.
+                        if let Err(err) = self.hooks.run(HookStage::After, &hook_context).await {
+                            if matches!(state_type, DeployCanary | SetCanaryTraffic) {
+                                warn!("Rolling back the canary because a hook failed...");
+                                self.abandon_canary().await?;
+                            }
+                            return Err(err);
+                        }
                     } else {
                         // The stream has been closed, so we should shutdown.
                         subsys.request_shutdown();
@@ -184,6 +279,60 @@ impl IntoSubsystem<Report> for RelaySubsystem<StatusCode> {
             }
         }
     }
+
+    /// Show the batch to the operator, then send it to the backend.
+    async fn observe(&mut self, batch: Vec<Measurement>) -> Result<()> {
+        self.dashboard.observe(&batch);
+        self.backend.upload_observations(&self.meta, batch).await
+    }
+
+    /// Send the synthetic probes to the canary. What they observe is
+    /// handled like any other observation of the canary, and if any of
+    /// them fails, or they can't be sent at all, the canary is rolled back.
+    async fn probe_canary(&mut self, canary_id: &str) -> Result<()> {
+        let Some(probes) = &self.probes else {
+            return Ok(());
+        };
+        let result = match probes.run(canary_id).await {
+            Ok(report) => {
+                let result = report.check();
+                // The monitor controller announces its own batches,
+                // but these come from the probes.
+                self.events
+                    .emit(RolloutEvent::observation_batch(&report.measurements));
+                self.observe(report.measurements).await?;
+                result
+            }
+            Err(err) => Err(err),
+        };
+        if result.is_err() {
+            warn!("Rolling back the canary because it failed the synthetic probes...");
+            self.abandon_canary().await?;
+        }
+        result
+    }
+
+    /// Roll back a canary that failed a check, and tell the
+    /// backend the rollout is over.
+    async fn abandon_canary(&mut self) -> Result<()> {
+        self.rollback_canary().await?;
+        self.backend.retire(&self.meta).await?;
+        self.clear_in_flight()
+    }
+
+    /// Cut all traffic to the canary, then remove it from the ingress.
+    async fn rollback_canary(&mut self) -> Result<()> {
+        // Set traffic to 0 immediately.
+        self.ingress
+            .set_canary_traffic(WholePercent::try_from(0).unwrap())
+            .await?;
+        // Then, yank the canary from the ingress.
+        self.ingress.rollback_canary().await?;
+        self.dashboard
+            .set_canary_traffic(WholePercent::try_from(0).unwrap());
+        self.events.emit(RolloutEvent::RolledBack);
+        Ok(())
+    }
 }
 
 mod lock_mgmt;
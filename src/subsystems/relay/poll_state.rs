@@ -5,10 +5,9 @@ use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
 
 use crate::{
     Shutdownable,
-    adapters::{BackendClient, RolloutMetadata},
+    adapters::{RolloutMetadata, SharedBackend, TargetState},
     subsystems::{ShutdownResult, TakenOptionalError},
 };
-use multitool_sdk::models::RolloutState;
 use tokio::{
     select,
     sync::mpsc::{self, Receiver, Sender},
@@ -24,7 +23,7 @@ const DEFAULT_CHANNEL_SIZE: usize = 1 << 5;
 
 pub struct StatePoller {
     /// This is the client we use to poll for new state.
-    backend: BackendClient,
+    backend: SharedBackend,
     /// This timer ticks every so often, letting us know
     /// its time to poll the backend for new state.
     timer: Interval,
@@ -32,10 +31,10 @@ pub struct StatePoller {
     /// context we pass to the backend on each request.
     meta: RolloutMetadata,
     /// This is where we write new messages when we have them.
-    outbox: Sender<RolloutState>,
+    outbox: Sender<TargetState>,
     /// We give this to the caller so it can stream new
     /// messages.
-    stream: Option<Receiver<RolloutState>>,
+    stream: Option<Receiver<TargetState>>,
 }
 
 #[bon]
@@ -43,7 +42,7 @@ impl StatePoller {
     #[builder]
     pub(super) fn new(
         meta: RolloutMetadata,
-        backend: BackendClient,
+        backend: SharedBackend,
         freq: Option<Duration>,
     ) -> Self {
         let freq = freq.unwrap_or(DEFAULT_POLLING_FREQUENCY);
@@ -58,7 +57,7 @@ impl StatePoller {
         }
     }
 
-    pub fn take_stream(&mut self) -> Result<Receiver<RolloutState>> {
+    pub fn take_stream(&mut self) -> Result<Receiver<TargetState>> {
         self.stream.take().ok_or(TakenOptionalError.into())
     }
 }
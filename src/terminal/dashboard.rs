@@ -0,0 +1,329 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use console::{Term, style};
+use miette::{Report, Result};
+use multitool_sdk::models::RolloutStateType;
+use tokio::time::interval;
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
+use tracing::info;
+
+use crate::{
+    WholePercent,
+    adapters::{Measurement, ResponseTally, backend::RolloutId},
+    stats::Group,
+};
+
+/// This is the name as reported to the `TopLevelSubsystem`,
+/// presumably for logging.
+pub const DASHBOARD_SUBSYSTEM_NAME: &str = "dashboard";
+
+/// How often the dashboard is redrawn, so the time in state keeps ticking.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+/// The error rates are computed over this many of the most recent
+/// batches of observations of each group.
+const ROLLING_WINDOW: usize = 10;
+/// The width of the traffic bar, in characters.
+const BAR_WIDTH: usize = 30;
+
+/// The lines of the dashboard currently on screen. Log lines are written
+/// above the dashboard, so they have to clear it and draw it again.
+static SCREEN: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// Whether log lines go to stderr, because stdout is spoken for.
+static LOGS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Write log lines to stderr from now on.
+pub(super) fn log_to_stderr() {
+    LOGS_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+/// The [Dashboard] shows the progress of a rollout. On an interactive
+/// terminal, it's redrawn in place. Otherwise, progress is reported as
+/// log lines, whose fields let structured logs be indexed.
+#[derive(Clone)]
+pub(crate) struct Dashboard {
+    rollout_id: RolloutId,
+    interactive: bool,
+    progress: Arc<Mutex<Progress>>,
+}
+
+impl Dashboard {
+    pub(crate) fn new(rollout_id: RolloutId, interactive: bool) -> Self {
+        Self {
+            rollout_id,
+            interactive,
+            progress: Arc::default(),
+        }
+    }
+
+    pub(crate) fn interactive(&self) -> bool {
+        self.interactive
+    }
+
+    /// The rollout has moved into a new state.
+    pub(crate) fn enter_state(&self, state_type: RolloutStateType) {
+        self.update(|progress| progress.state = Some((state_type, Instant::now())));
+        if !self.interactive {
+            info!(
+                rollout_id = self.rollout_id,
+                state_type = %state_type,
+                "Rollout state is now {state_type}."
+            );
+        }
+    }
+
+    /// The ingress now sends the given percentage of traffic to the canary.
+    pub(crate) fn set_canary_traffic(&self, percent: WholePercent) {
+        if !self.interactive {
+            info!(
+                rollout_id = self.rollout_id,
+                traffic_percent = percent.as_whole(),
+                "The canary now receives {percent} of traffic."
+            );
+        }
+        self.update(|progress| progress.canary_traffic = Some(percent));
+    }
+
+    /// Fold a batch of observations into the error rates.
+    pub(crate) fn observe(&self, batch: &[Measurement]) {
+        let baseline = ResponseTally::new(batch, Group::Control);
+        let canary = ResponseTally::new(batch, Group::Experimental);
+        let (baseline_rate, canary_rate) = {
+            let mut progress = self.progress.lock().unwrap_or_else(PoisonError::into_inner);
+            progress.baseline.push(baseline);
+            progress.canary.push(canary);
+            (progress.baseline.rate(), progress.canary.rate())
+        };
+        if !self.interactive {
+            info!(
+                rollout_id = self.rollout_id,
+                baseline_5xx = baseline.errors,
+                baseline_total = baseline.total,
+                canary_5xx = canary.errors,
+                canary_total = canary.total,
+                "5XX rate: baseline {}, canary {}.",
+                format_rate(baseline_rate),
+                format_rate(canary_rate)
+            );
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Progress)) {
+        let mut progress = self.progress.lock().unwrap_or_else(PoisonError::into_inner);
+        f(&mut progress);
+    }
+
+    fn draw(&self) {
+        let lines = self
+            .progress
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .render();
+        let mut screen = SCREEN.lock().unwrap_or_else(PoisonError::into_inner);
+        clear(&screen);
+        *screen = lines;
+        draw(&screen);
+    }
+}
+
+#[async_trait]
+impl IntoSubsystem<Report> for Dashboard {
+    async fn run(self, subsys: SubsystemHandle) -> Result<()> {
+        let mut ticker = interval(REFRESH_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => {
+                    // Leave the last frame on screen, but stop
+                    // treating it as the dashboard.
+                    self.draw();
+                    SCREEN.lock().unwrap_or_else(PoisonError::into_inner).clear();
+                    return Ok(());
+                }
+                _ = ticker.tick() => self.draw(),
+            }
+        }
+    }
+}
+
+/// Everything the dashboard knows about the rollout.
+#[derive(Default)]
+struct Progress {
+    /// The current state, and when the rollout entered it.
+    state: Option<(RolloutStateType, Instant)>,
+    canary_traffic: Option<WholePercent>,
+    baseline: ErrorRate,
+    canary: ErrorRate,
+}
+
+impl Progress {
+    fn render(&self) -> Vec<String> {
+        let state = match &self.state {
+            Some((state_type, since)) => format!(
+                "{} {} for {}",
+                style("●").cyan(),
+                style(state_type).bold(),
+                format_elapsed(since.elapsed())
+            ),
+            None => format!("{} Waiting for the first state...", style("●").dim()),
+        };
+        let traffic = match &self.canary_traffic {
+            Some(percent) => format!(
+                "Canary traffic  [{}] {percent}",
+                style(traffic_bar(percent.as_fraction(), BAR_WIDTH)).green()
+            ),
+            None => "Canary traffic  not deployed".to_owned(),
+        };
+        let baseline = self.baseline.rate();
+        let canary = self.canary.rate();
+        let canary_rate = format_rate(canary);
+        // Highlight the canary when it's doing worse than the baseline.
+        let canary_rate = match (baseline, canary) {
+            (Some(baseline), Some(canary)) if canary > baseline => {
+                style(canary_rate).red().to_string()
+            }
+            _ => canary_rate,
+        };
+        let rates = format!(
+            "5XX rate        baseline {}  canary {canary_rate}",
+            format_rate(baseline),
+        );
+        vec![state, traffic, rates]
+    }
+}
+
+/// The share of 5XX responses over the most recent batches of a group.
+#[derive(Default)]
+struct ErrorRate {
+    /// The responses in each batch, oldest first.
+    recent: VecDeque<ResponseTally>,
+}
+
+impl ErrorRate {
+    /// Batches without any responses are skipped, so they don't
+    /// push real data out of the window.
+    fn push(&mut self, tally: ResponseTally) {
+        if tally.total == 0 {
+            return;
+        }
+        if self.recent.len() == ROLLING_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(tally);
+    }
+
+    /// Returns `None` if there haven't been any responses.
+    fn rate(&self) -> Option<f64> {
+        let (errors, total) = self
+            .recent
+            .iter()
+            .fold((0u64, 0u64), |(errors, total), tally| {
+                (
+                    errors + u64::from(tally.errors),
+                    total + u64::from(tally.total),
+                )
+            });
+        (total > 0).then(|| errors as f64 / total as f64)
+    }
+}
+
+fn format_rate(rate: Option<f64>) -> String {
+    rate.map_or_else(|| "n/a".to_owned(), |rate| format!("{:.2}%", rate * 100.0))
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m {s:02}s"),
+        (h, m, s) => format!("{h}h {m:02}m {s:02}s"),
+    }
+}
+
+fn traffic_bar(fraction: f64, width: usize) -> String {
+    let filled = ((fraction.clamp(0.0, 1.0) * width as f64).round() as usize).min(width);
+    format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+fn clear(lines: &[String]) {
+    if !lines.is_empty() {
+        let _ = Term::stderr().clear_last_lines(lines.len());
+    }
+}
+
+fn draw(lines: &[String]) {
+    let term = Term::stderr();
+    for line in lines {
+        let _ = term.write_line(line);
+    }
+}
+
+/// Writes log lines to stdout, or stderr if it's spoken for, moving
+/// the dashboard out of the way while they're written.
+pub(super) struct LogWriter;
+
+impl LogWriter {
+    fn output() -> Box<dyn Write> {
+        if LOGS_TO_STDERR.load(Ordering::Relaxed) {
+            Box::new(io::stderr())
+        } else {
+            Box::new(io::stdout())
+        }
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let screen = SCREEN.lock().unwrap_or_else(PoisonError::into_inner);
+        clear(&screen);
+        let result = Self::output().write_all(buf).map(|_| buf.len());
+        draw(&screen);
+        result
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Self::output().flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use super::{ErrorRate, ROLLING_WINDOW, format_elapsed, traffic_bar};
+    use crate::adapters::ResponseTally;
+
+    #[test]
+    fn error_rate_rolls() {
+        let mut rate = ErrorRate::default();
+        assert_eq!(rate.rate(), None);
+        rate.push(ResponseTally {
+            errors: 10,
+            total: 10,
+        });
+        for _ in 0..ROLLING_WINDOW {
+            rate.push(ResponseTally {
+                errors: 1,
+                total: 100,
+            });
+            // Empty batches don't count.
+            rate.push(ResponseTally::default());
+        }
+        // The batch with all errors has rolled out of the window.
+        assert_eq!(rate.rate(), Some(0.01));
+    }
+
+    #[test]
+    fn render_helpers() {
+        assert_eq!(format_elapsed(Duration::from_secs(5)), "5s");
+        assert_eq!(format_elapsed(Duration::from_secs(133)), "2m 13s");
+        assert_eq!(format_elapsed(Duration::from_secs(3723)), "1h 02m 03s");
+        assert_eq!(traffic_bar(0.25, 4), "█░░░");
+        assert_eq!(traffic_bar(1.5, 4), "████");
+    }
+}
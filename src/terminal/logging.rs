@@ -1,13 +1,19 @@
 use std::sync::Once;
 
-use tracing_subscriber::{filter::LevelFilter, fmt::time::ChronoLocal};
+use tracing::Subscriber;
+use tracing_subscriber::{
+    filter::LevelFilter,
+    fmt::{MakeWriter, time::ChronoLocal},
+};
+
+use super::dashboard::LogWriter;
+use crate::config::LogFormat;
 
 static LOGGER_READY: Once = Once::new();
 
 /// This string is our default local formatter, putting the local time
-/// into a human-readinable timestamp. In the future we can add support for
-/// other timestamp formats that are more machine readable, but our initial
-/// MVP release is focused on human operators.
+/// into a human-readinable timestamp. JSON logs use RFC 3339 timestamps
+/// in UTC instead, which are easier for machines to read.
 const CHRONO_LOCAL_FMT: &str = "%c %Z";
 // const CHRONO_LOCAL_FMT: &str = "%x %Z";
 
@@ -16,21 +22,128 @@ const CHRONO_LOCAL_FMT: &str = "%c %Z";
 /// has no effect.
 /// # Panics
 /// Panics if we cannot initialize the logger.
-pub(super) fn setup_logger(level: LevelFilter) {
+pub(super) fn setup_logger(level: LevelFilter, format: LogFormat) {
     LOGGER_READY.call_once(|| {
-        let subscriber = tracing_subscriber::fmt::Subscriber::builder()
-            .pretty()
-            .with_max_level(level)
-            .with_timer(ChronoLocal::new(CHRONO_LOCAL_FMT.to_owned()))
-            .with_file(false)
-            .with_line_number(false)
-            .with_target(false)
-            // Scope the subscriber to ONLY the multitool module.
-            .with_env_filter(format!("multitool={}", level.to_string()))
-            .compact()
-            .finish();
-
-        tracing::subscriber::set_global_default(subscriber)
+        // Keep log lines from drawing over the dashboard.
+        tracing::subscriber::set_global_default(subscriber(level, format, || LogWriter))
             .expect("setting tracing default failed");
     });
 }
+
+/// Build a subscriber that writes logs in the given format.
+fn subscriber<W>(
+    level: LevelFilter,
+    format: LogFormat,
+    writer: W,
+) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt::Subscriber::builder()
+        .with_max_level(level)
+        .with_file(false)
+        .with_line_number(false)
+        .with_target(false)
+        .with_writer(writer)
+        // Scope the subscriber to ONLY the multitool module.
+        .with_env_filter(format!("multitool={level}"));
+    let timer = ChronoLocal::new(CHRONO_LOCAL_FMT.to_owned());
+    match format {
+        LogFormat::Compact => Box::new(builder.with_timer(timer).compact().finish()),
+        LogFormat::Pretty => Box::new(builder.with_timer(timer).pretty().finish()),
+        // Flatten the events so their fields, like the rollout id,
+        // can be indexed at the top level.
+        LogFormat::Json => Box::new(builder.json().flatten_event(true).finish()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Write},
+        sync::{Arc, Mutex},
+    };
+
+    use multitool_sdk::models::RolloutStateType;
+    use pretty_assertions::assert_eq;
+    use serde_json::{Value, json};
+    use tracing_subscriber::{filter::LevelFilter, fmt::MakeWriter};
+
+    use super::subscriber;
+    use crate::{
+        WholePercent,
+        adapters::{Measurement, StatusCode},
+        config::LogFormat,
+        metrics::ResponseStatusCode,
+        stats::{CategoricalObservation, Group},
+        terminal::Dashboard,
+    };
+
+    /// Collects everything the subscriber writes.
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl MakeWriter<'_> for Captured {
+        type Writer = Self;
+
+        fn make_writer(&self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    fn status_codes(group: Group, ok: u32, errors: u32) -> Measurement {
+        let mut observation: StatusCode = CategoricalObservation::new(group);
+        observation.increment_by(&ResponseStatusCode::_2XX, ok);
+        observation.increment_by(&ResponseStatusCode::_5XX, errors);
+        Measurement::StatusCode(observation)
+    }
+
+    /// The dashboard's fields are flattened into each JSON log line,
+    /// so they can be indexed at the top level.
+    #[test]
+    fn json_logs_have_flat_fields() {
+        let captured = Captured::default();
+        let subscriber = subscriber(LevelFilter::INFO, LogFormat::Json, captured.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let dashboard = Dashboard::new(7, false);
+            dashboard.enter_state(RolloutStateType::SetCanaryTraffic);
+            dashboard.set_canary_traffic(WholePercent::try_from(25).unwrap());
+            dashboard.observe(&[
+                status_codes(Group::Control, 98, 2),
+                status_codes(Group::Experimental, 45, 5),
+            ]);
+        });
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["rollout_id"], json!(7));
+        assert_eq!(
+            lines[0]["state_type"],
+            json!(RolloutStateType::SetCanaryTraffic.to_string())
+        );
+        assert_eq!(lines[1]["rollout_id"], json!(7));
+        assert_eq!(lines[1]["traffic_percent"], json!(25));
+        assert_eq!(lines[2]["rollout_id"], json!(7));
+        assert_eq!(lines[2]["baseline_5xx"], json!(2));
+        assert_eq!(lines[2]["baseline_total"], json!(100));
+        assert_eq!(lines[2]["canary_5xx"], json!(5));
+        assert_eq!(lines[2]["canary_total"], json!(50));
+        // Nothing is nested under `fields`.
+        assert!(lines.iter().all(|line| line.get("fields").is_none()));
+    }
+}
@@ -3,9 +3,13 @@ use logging::setup_logger;
 use miette::{DebugReportHandler, GraphicalReportHandler, IntoDiagnostic, Result};
 
 use crate::Cli;
+use crate::adapters::backend::RolloutId;
 
+pub(crate) use dashboard::{DASHBOARD_SUBSYSTEM_NAME, Dashboard};
 use dest::TermDestination;
 
+/// Shows the progress of a rollout while `multi run` is running.
+mod dashboard;
 mod dest;
 mod logging;
 mod theme;
@@ -13,6 +17,9 @@ mod theme;
 pub struct Terminal {
     stdout: TermDestination,
     stderr: TermDestination,
+    /// Whether log lines are meant for machines. If so, we don't
+    /// draw anything that would get in their way.
+    structured_logs: bool,
 }
 
 impl Terminal {
@@ -21,9 +28,13 @@ impl Terminal {
         // terminal output.
         let stdout = TermDestination::stdout(cli);
         let stderr = TermDestination::stderr(cli);
-        setup_logger(*cli.log_level());
+        setup_logger(*cli.log_level(), *cli.log_format());
 
-        Self { stdout, stderr }
+        Self {
+            stdout,
+            stderr,
+            structured_logs: cli.log_format().is_structured(),
+        }
     }
 
     /// This constructs and sets the global error reporter we use -- constructed during
@@ -70,6 +81,26 @@ impl Terminal {
             .into_diagnostic()
     }
 
+    /// Build the dashboard for a rollout. It's only drawn when stderr is
+    /// an interactive terminal that allows color, and the logs aren't
+    /// structured; otherwise it falls back to log lines.
+    pub(crate) fn dashboard(&self, rollout_id: RolloutId) -> Dashboard {
+        let interactive =
+            self.stderr.term().is_term() && self.stderr.allow_color() && !self.structured_logs;
+        Dashboard::new(rollout_id, interactive)
+    }
+
+    /// Keep log lines off stdout, so a machine can read
+    /// whatever else is written there, e.g. events.
+    pub(crate) fn reserve_stdout(&self) {
+        dashboard::log_to_stderr();
+    }
+
+    /// Print a command's results to stdout, as is.
+    pub fn print_output(&self, output: &str) -> Result<()> {
+        self.stdout.term().write_str(output).into_diagnostic()
+    }
+
     pub fn logout_successful(&self) -> Result<()> {
         self.stdout
             .term()
@@ -77,6 +108,14 @@ impl Terminal {
             .into_diagnostic()
     }
 
+    pub fn override_successful(&self, outcome: &str, rollout_id: u64) -> Result<()> {
+        let msg = format!("Rollout {rollout_id} {outcome}.");
+        self.stdout
+            .term()
+            .write_line(msg.as_str())
+            .into_diagnostic()
+    }
+
     pub fn login_successful(&self) -> Result<()> {
         self.stdout
             .term()
@@ -0,0 +1,67 @@
+use aws_sdk_lambda::{client::Client, error::SdkError, operation::get_alias::GetAliasError};
+use miette::{Result, miette};
+
+/// Returns the version the alias points to, or `None`
+/// if the alias doesn't exist yet.
+pub(crate) async fn alias_version(
+    client: &Client,
+    function_name: &str,
+    alias: &str,
+) -> Result<Option<String>, SdkError<GetAliasError>> {
+    let response = client
+        .get_alias()
+        .function_name(function_name)
+        .name(alias)
+        .send()
+        .await;
+    match response {
+        Ok(alias) => Ok(alias.function_version().map(ToOwned::to_owned)),
+        Err(SdkError::ServiceError(err))
+            if matches!(err.err(), GetAliasError::ResourceNotFoundException(_)) =>
+        {
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Extract the version number from a qualified Lambda ARN, like
+/// `arn:aws:lambda:us-east-2:123456789012:function:my-function:7`.
+pub(crate) fn version_from_arn(arn: &str) -> Result<String> {
+    let parts: Vec<_> = arn.split(':').collect();
+    match parts.as_slice() {
+        ["arn", _, "lambda", _, _, "function", _, version]
+            if !version.is_empty() && version.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            Ok((*version).to_owned())
+        }
+        _ => Err(miette!(
+            "Expected an ARN qualified with a published version, but found {arn}"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::version_from_arn;
+
+    #[test]
+    fn parse_version_from_arn() {
+        let arn = "arn:aws:lambda:us-east-2:123456789012:function:my-function:7";
+        assert_eq!(version_from_arn(arn).unwrap(), "7");
+        // Unqualified ARNs and aliases don't identify a version.
+        assert!(
+            version_from_arn("arn:aws:lambda:us-east-2:123456789012:function:my-function").is_err()
+        );
+        assert!(
+            version_from_arn("arn:aws:lambda:us-east-2:123456789012:function:my-function:$LATEST")
+                .is_err()
+        );
+        assert!(
+            version_from_arn("arn:aws:lambda:us-east-2:123456789012:function:my-function:live")
+                .is_err()
+        );
+    }
+}
@@ -1,6 +1,15 @@
 use aws_config::{BehaviorVersion, SdkConfig};
 use tokio::sync::OnceCell;
 
+pub(crate) use lambda::{alias_version, version_from_arn};
+#[cfg(test)]
+pub(crate) use stub_server::StubServer;
+
+mod lambda;
+/// A local HTTP server for tests that talk to real sockets.
+#[cfg(test)]
+mod stub_server;
+
 /// Load AWS configuration using their standard rules. e.g. AWS_ACCESS_KEY_ID,
 /// or session profile information, etc. This function fetches the data only
 /// once, the first time it's called, and memoized the results, so all future
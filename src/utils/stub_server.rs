@@ -0,0 +1,106 @@
+use std::sync::{
+    Arc, Mutex, MutexGuard,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// A request received by a [StubServer].
+pub(crate) struct Request {
+    /// The request line, e.g. `GET /health HTTP/1.1`.
+    pub(crate) line: String,
+    /// The request line and headers, lowercased.
+    pub(crate) head: String,
+    pub(crate) body: Vec<u8>,
+}
+
+/// A local HTTP/1.1 server for tests. It answers each request however
+/// it's told to, and records every request it receives.
+pub(crate) struct StubServer {
+    /// Where the server listens, e.g. `http://127.0.0.1:8080`.
+    pub(crate) origin: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl StubServer {
+    /// Respond to each request with the status and body `respond` returns.
+    pub(crate) async fn start(
+        respond: impl Fn(&Request) -> (u16, String) + Send + 'static,
+    ) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let origin = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let request = read_request(&mut stream).await;
+                let (status, body) = respond(&request);
+                // Record the request before responding, so the
+                // test sees it as soon as the response arrives.
+                recorded.lock().unwrap().push(request);
+                let response = format!(
+                    "HTTP/1.1 {status} Status\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        Self { origin, requests }
+    }
+
+    /// Respond to every request with the same status and body.
+    pub(crate) async fn always(status: u16, body: impl Into<String>) -> Self {
+        let body = body.into();
+        Self::start(move |_| (status, body.clone())).await
+    }
+
+    /// Respond to the first request with the first response,
+    /// the second with the second, and so on.
+    pub(crate) async fn replying(responses: Vec<(u16, &'static str)>) -> Self {
+        let next = AtomicUsize::new(0);
+        Self::start(move |_| {
+            let (status, body) = responses[next.fetch_add(1, Ordering::Relaxed)];
+            (status, body.to_owned())
+        })
+        .await
+    }
+
+    /// The requests received so far, in order.
+    pub(crate) fn requests(&self) -> MutexGuard<'_, Vec<Request>> {
+        self.requests.lock().unwrap()
+    }
+}
+
+/// Read the request's head, then as much of the body as its
+/// `content-length` says there is.
+async fn read_request(stream: &mut TcpStream) -> Request {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    let body_start = loop {
+        let read = stream.read(&mut buf).await.unwrap();
+        request.extend_from_slice(&buf[..read]);
+        if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+    };
+    let head = String::from_utf8_lossy(&request[..body_start - 4]).into_owned();
+    let line = head.lines().next().unwrap_or_default().to_owned();
+    let head = head.to_lowercase();
+    let length: usize = head
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .map_or(0, |value| value.trim().parse().unwrap());
+    while request.len() < body_start + length {
+        let read = stream.read(&mut buf).await.unwrap();
+        request.extend_from_slice(&buf[..read]);
+    }
+    Request {
+        line,
+        head,
+        body: request[body_start..].to_vec(),
+    }
+}